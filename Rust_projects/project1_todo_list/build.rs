@@ -0,0 +1,5 @@
+// Миграции встраиваются в бинарник через sqlx::migrate!,
+// поэтому пересобираем проект при их изменении
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+}
@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS counter_reminders;
+DROP TABLE IF EXISTS user_reminders;
+DROP TABLE IF EXISTS todos;
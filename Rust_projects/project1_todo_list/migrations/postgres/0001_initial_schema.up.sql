@@ -0,0 +1,26 @@
+-- Исходная схема. IF NOT EXISTS — чтобы миграция применилась и к базам,
+-- созданным до появления миграций
+CREATE TABLE IF NOT EXISTS todos (
+    id VARCHAR PRIMARY KEY,
+    chat_id BIGINT NOT NULL,
+    text TEXT NOT NULL,
+    completed BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS user_reminders (
+    chat_id BIGINT PRIMARY KEY,
+    global_enabled BOOLEAN NOT NULL DEFAULT TRUE
+);
+
+CREATE TABLE IF NOT EXISTS counter_reminders (
+    chat_id BIGINT NOT NULL,
+    counter_type VARCHAR NOT NULL,
+    start_day INTEGER NOT NULL,
+    end_day INTEGER NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    last_sent_month VARCHAR,
+    last_sent_date VARCHAR,
+    completed_this_month BOOLEAN NOT NULL DEFAULT FALSE,
+    PRIMARY KEY (chat_id, counter_type)
+);
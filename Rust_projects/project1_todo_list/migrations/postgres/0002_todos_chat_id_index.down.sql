@@ -0,0 +1 @@
+DROP INDEX IF EXISTS idx_todos_chat_id_created_at;
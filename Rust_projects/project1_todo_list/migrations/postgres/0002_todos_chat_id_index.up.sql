@@ -0,0 +1,2 @@
+-- Все выборки задач идут по chat_id с сортировкой по created_at
+CREATE INDEX IF NOT EXISTS idx_todos_chat_id_created_at ON todos (chat_id, created_at);
@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS task_reminders (
+    id VARCHAR PRIMARY KEY,
+    chat_id BIGINT NOT NULL,
+    task_id VARCHAR NOT NULL,
+    remind_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_task_reminders_remind_at ON task_reminders (remind_at);
@@ -0,0 +1,2 @@
+-- Правило повторения задачи: daily, weekly:0,2,4, monthly:5, every:3
+ALTER TABLE todos ADD COLUMN IF NOT EXISTS recurrence VARCHAR(64);
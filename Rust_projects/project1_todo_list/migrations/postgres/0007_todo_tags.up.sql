@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS todo_tags (
+    todo_id VARCHAR NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+    tag VARCHAR(64) NOT NULL,
+    PRIMARY KEY (todo_id, tag)
+);
+
+CREATE INDEX IF NOT EXISTS idx_todo_tags_tag ON todo_tags(tag);
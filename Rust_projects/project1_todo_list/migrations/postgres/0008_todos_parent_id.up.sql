@@ -0,0 +1,4 @@
+-- Подзадачи (пункты чек-листа) удаляются вместе с родительской задачей
+ALTER TABLE todos ADD COLUMN IF NOT EXISTS parent_id VARCHAR REFERENCES todos(id) ON DELETE CASCADE;
+
+CREATE INDEX IF NOT EXISTS idx_todos_parent_id ON todos(parent_id);
@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS current_lists;
+ALTER TABLE todos DROP COLUMN IF EXISTS list_id;
+DROP TABLE IF EXISTS task_lists;
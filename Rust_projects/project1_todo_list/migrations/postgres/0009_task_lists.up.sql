@@ -0,0 +1,18 @@
+-- Именованные списки задач; задачи без list_id относятся к основному списку
+CREATE TABLE IF NOT EXISTS task_lists (
+    id VARCHAR PRIMARY KEY,
+    chat_id BIGINT NOT NULL,
+    name VARCHAR(64) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_task_lists_chat_name ON task_lists(chat_id, LOWER(name));
+
+-- При удалении списка его задачи переходят в основной
+ALTER TABLE todos ADD COLUMN IF NOT EXISTS list_id VARCHAR REFERENCES task_lists(id) ON DELETE SET NULL;
+
+-- Текущий список чата; нет строки — основной список
+CREATE TABLE IF NOT EXISTS current_lists (
+    chat_id BIGINT PRIMARY KEY,
+    list_id VARCHAR NOT NULL REFERENCES task_lists(id) ON DELETE CASCADE
+);
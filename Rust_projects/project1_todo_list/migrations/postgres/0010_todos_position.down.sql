@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_todos_chat_position;
+ALTER TABLE todos DROP COLUMN IF EXISTS position;
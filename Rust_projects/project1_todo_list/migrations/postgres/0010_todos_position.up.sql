@@ -0,0 +1,8 @@
+-- Порядок задач, заданный пользователем; существующие задачи нумеруются по времени добавления
+ALTER TABLE todos ADD COLUMN IF NOT EXISTS position BIGINT NOT NULL DEFAULT 0;
+
+UPDATE todos SET position = numbered.rn
+FROM (SELECT id, ROW_NUMBER() OVER (PARTITION BY chat_id ORDER BY created_at, id) AS rn FROM todos) AS numbered
+WHERE todos.id = numbered.id;
+
+CREATE INDEX IF NOT EXISTS idx_todos_chat_position ON todos(chat_id, position);
@@ -0,0 +1,7 @@
+-- Без корзины удаленные задачи не должны вернуться в списки
+DELETE FROM todos WHERE deleted_at IS NOT NULL;
+
+DROP INDEX IF EXISTS idx_todos_deleted_at;
+ALTER TABLE todos DROP COLUMN IF EXISTS deleted_at;
+ALTER TABLE todos DROP COLUMN IF EXISTS archived_at;
+ALTER TABLE todos DROP COLUMN IF EXISTS completed_at;
@@ -0,0 +1,2 @@
+-- Полнотекстовый поиск по задачам с русской морфологией
+CREATE INDEX IF NOT EXISTS idx_todos_text_fts ON todos USING GIN (to_tsvector('russian', text));
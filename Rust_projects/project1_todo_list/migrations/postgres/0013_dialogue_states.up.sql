@@ -0,0 +1,8 @@
+-- Незаконченные диалоги (какой ввод бот ждет от пользователя); нет строки — обычный режим
+CREATE TABLE IF NOT EXISTS dialogue_states (
+    chat_id BIGINT PRIMARY KEY,
+    state JSONB NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_dialogue_states_updated_at ON dialogue_states(updated_at);
@@ -0,0 +1,5 @@
+-- Прежняя версия знает только воду и электричество
+DELETE FROM counter_reminders WHERE counter_type NOT IN ('water', 'electricity');
+
+ALTER TABLE counter_reminders DROP COLUMN IF EXISTS emoji;
+ALTER TABLE counter_reminders DROP COLUMN IF EXISTS name;
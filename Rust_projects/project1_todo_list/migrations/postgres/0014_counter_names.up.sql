@@ -0,0 +1,12 @@
+-- Счетчики заводит пользователь: у каждого свое название и эмодзи.
+-- counter_type теперь хранит id счетчика; вода и электричество сохраняют прежние id
+ALTER TABLE counter_reminders ADD COLUMN IF NOT EXISTS name VARCHAR(64);
+ALTER TABLE counter_reminders ADD COLUMN IF NOT EXISTS emoji VARCHAR(16);
+
+UPDATE counter_reminders SET
+    name = CASE counter_type WHEN 'water' THEN 'Вода' WHEN 'electricity' THEN 'Электричество' ELSE counter_type END,
+    emoji = CASE counter_type WHEN 'water' THEN '💧' WHEN 'electricity' THEN '⚡' ELSE '📟' END
+WHERE name IS NULL;
+
+ALTER TABLE counter_reminders ALTER COLUMN name SET NOT NULL;
+ALTER TABLE counter_reminders ALTER COLUMN emoji SET NOT NULL;
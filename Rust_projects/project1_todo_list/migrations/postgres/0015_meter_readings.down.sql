@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS meter_readings;
+
+ALTER TABLE counter_reminders DROP COLUMN IF EXISTS zones;
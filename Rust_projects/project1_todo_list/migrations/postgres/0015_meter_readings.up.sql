@@ -0,0 +1,16 @@
+-- Тарифные зоны счетчика («день», «ночь»); пустой массив — одно показание
+ALTER TABLE counter_reminders ADD COLUMN IF NOT EXISTS zones TEXT[] NOT NULL DEFAULT '{}';
+
+-- Показания счетчиков: по строке на зону, у показаний одной отправки общее taken_at.
+-- zone = '' — счетчик без зон
+CREATE TABLE IF NOT EXISTS meter_readings (
+    id VARCHAR PRIMARY KEY,
+    chat_id BIGINT NOT NULL,
+    counter_id VARCHAR NOT NULL,
+    zone VARCHAR NOT NULL DEFAULT '',
+    value DOUBLE PRECISION NOT NULL CHECK (value >= 0),
+    taken_at TIMESTAMPTZ NOT NULL,
+    FOREIGN KEY (chat_id, counter_id) REFERENCES counter_reminders(chat_id, counter_type) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_meter_readings_counter ON meter_readings(chat_id, counter_id, taken_at);
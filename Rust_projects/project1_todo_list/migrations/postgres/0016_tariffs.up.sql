@@ -0,0 +1,11 @@
+-- Тарифы счетчиков: цена единицы расхода в зоне (zone = '' — счетчик без зон),
+-- действующая с даты effective_from до начала следующего тарифа той же зоны
+CREATE TABLE IF NOT EXISTS tariffs (
+    chat_id BIGINT NOT NULL,
+    counter_id VARCHAR NOT NULL,
+    zone VARCHAR NOT NULL DEFAULT '',
+    effective_from DATE NOT NULL,
+    price DOUBLE PRECISION NOT NULL CHECK (price > 0),
+    PRIMARY KEY (chat_id, counter_id, zone, effective_from),
+    FOREIGN KEY (chat_id, counter_id) REFERENCES counter_reminders(chat_id, counter_type) ON DELETE CASCADE
+);
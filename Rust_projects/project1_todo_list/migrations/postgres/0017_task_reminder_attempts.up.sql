@@ -0,0 +1 @@
+ALTER TABLE task_reminders ADD COLUMN IF NOT EXISTS attempts INTEGER NOT NULL DEFAULT 0;
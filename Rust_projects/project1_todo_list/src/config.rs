@@ -1,14 +1,85 @@
 use std::env;
+use chrono::Duration;
 
 pub struct Config {
     #[allow(dead_code)]
     pub bot_token: String,
+    pub retention: RetentionPolicy,
 }
 
 impl Config {
     pub fn from_env() -> Result<Self, env::VarError> {
         Ok(Self {
             bot_token: env::var("TELOXIDE_TOKEN")?,
+            retention: RetentionPolicy::from_env(),
         })
     }
-}
\ No newline at end of file
+}
+
+/// Сроки хранения выполненных и удаленных задач и незаконченных диалогов
+#[derive(Clone, Copy, Debug)]
+pub struct RetentionPolicy {
+    /// Через сколько после выполнения задача уходит в архив; `None` — не архивировать
+    pub archive_after: Option<Duration>,
+    /// Сколько задача лежит в корзине до окончательного удаления
+    pub trash_retention: Duration,
+    /// Сколько бот ждет ответа в незаконченном диалоге («введите номер задачи» и т.п.)
+    pub dialogue_ttl: Duration,
+}
+
+impl RetentionPolicy {
+    const DEFAULT_ARCHIVE_AFTER_DAYS: i64 = 7;
+    const DEFAULT_TRASH_RETENTION_DAYS: i64 = 30;
+    const DEFAULT_DIALOGUE_TTL_MINUTES: i64 = 60;
+
+    /// `ARCHIVE_AFTER_DAYS` (0 — не архивировать), `TRASH_RETENTION_DAYS` и `DIALOGUE_TTL_MINUTES` (оба от 1)
+    pub fn from_env() -> Self {
+        let archive_after_days = number_from_env("ARCHIVE_AFTER_DAYS", Self::DEFAULT_ARCHIVE_AFTER_DAYS, 0);
+        // При нулевом сроке корзина очищалась бы сразу и удаление нельзя было бы отменить
+        let trash_retention_days = number_from_env("TRASH_RETENTION_DAYS", Self::DEFAULT_TRASH_RETENTION_DAYS, 1);
+        // При нулевом сроке диалог сбрасывался бы раньше, чем пользователь успеет ответить
+        let dialogue_ttl_minutes = number_from_env("DIALOGUE_TTL_MINUTES", Self::DEFAULT_DIALOGUE_TTL_MINUTES, 1);
+
+        Self {
+            archive_after: (archive_after_days > 0).then(|| Duration::days(archive_after_days)),
+            trash_retention: Duration::days(trash_retention_days),
+            dialogue_ttl: Duration::minutes(dialogue_ttl_minutes),
+        }
+    }
+}
+
+/// Число из переменной окружения не меньше `min`; при отсутствии или неверном значении — `default`
+fn number_from_env(name: &str, default: i64, min: i64) -> i64 {
+    match env::var(name) {
+        Ok(value) => value.trim().parse::<i64>().ok().filter(|number| *number >= min).unwrap_or_else(|| {
+            log::warn!("Invalid {}={}, using {}", name, value, default);
+            default
+        }),
+        Err(_) => default,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_number_from_env_rejects_values_below_min() {
+        // Имена переменных уникальны: тесты идут параллельно в одном процессе
+        env::set_var("TEST_TRASH_RETENTION_ZERO", "0");
+        assert_eq!(number_from_env("TEST_TRASH_RETENTION_ZERO", 30, 1), 30);
+        env::set_var("TEST_TRASH_RETENTION_VALID", " 14 ");
+        assert_eq!(number_from_env("TEST_TRASH_RETENTION_VALID", 30, 1), 14);
+        // Для архива 0 — допустимое значение «не архивировать»
+        env::set_var("TEST_ARCHIVE_AFTER_ZERO", "0");
+        assert_eq!(number_from_env("TEST_ARCHIVE_AFTER_ZERO", 7, 0), 0);
+        assert_eq!(number_from_env("TEST_NUMBER_UNSET", 7, 0), 7);
+    }
+
+    #[test]
+    fn test_zero_dialogue_ttl_falls_back_to_default() {
+        env::set_var("TEST_DIALOGUE_TTL_ZERO", "0");
+        let default = RetentionPolicy::DEFAULT_DIALOGUE_TTL_MINUTES;
+        assert_eq!(number_from_env("TEST_DIALOGUE_TTL_ZERO", default, 1), default);
+    }
+}
@@ -1,242 +1,612 @@
 use teloxide::prelude::*;
+use teloxide::types::InlineKeyboardMarkup;
+use teloxide::{ApiError, RequestError};
 
-use crate::models::{UserState, UserStates};
-use crate::storage::StorageType;
-use crate::utils::{create_main_menu, create_todo_menu, create_reminder_menu, create_counters_menu};
-use crate::models::CounterType;
+use crate::models::{UserState, UndoJournal};
+use crate::storage::SharedStorage;
+use crate::utils::{create_main_menu, create_todo_menu, create_reminder_menu, create_task_picker_keyboard, create_priority_keyboard, create_clear_confirm_keyboard, create_empty_trash_confirm_keyboard, create_undo_keyboard, create_tags_keyboard, create_lists_keyboard, create_move_target_keyboard, create_cancel_keyboard, format_due, CallbackAction, Snooze};
+use super::dialogue::BotDialogue;
+use super::{HandlerError, HandlerResult};
+use super::counters::{consumption_report_view, counter_view, counters_view, delete_counter, reading_prompt, tariff_prompt};
+use super::messages::{clear_current_list, clear_prompt, complete_task_by_id, delete_current_list, empty_trash_prompt, remove_task_with_undo, removed_tasks_view, restore_task_by_id, shift_task, task_list_view, task_order_view, undo_last_removal};
+use crate::models::{TaskReminder, MAIN_LIST_NAME};
 
 pub async fn handle_callback(
     bot: Bot,
     q: CallbackQuery,
-    storage: StorageType,
-    user_states: UserStates,
-) -> ResponseResult<()> {
-    if let Some(data) = &q.data {
-        let chat_id = q.message.as_ref().unwrap().chat.id;
-        
-        match data.as_str() {
-            "todo_menu" => {
-                bot.send_message(chat_id, "📝 TODO List — выберите действие:")
-                    .reply_markup(create_todo_menu())
-                    .await?;
-            }
-            "main_menu" => {
-                bot.send_message(chat_id, "🏠 Главное меню:")
-                    .reply_markup(create_main_menu())
-                    .await?;
-            }
-            "add_task" => {
-                {
-                    let mut states = user_states.lock().await;
-                    states.insert(chat_id, UserState::WaitingForTask);
-                }
+    storage: SharedStorage,
+    dialogue: BotDialogue,
+    undo_journal: UndoJournal,
+) -> HandlerResult {
+    let result = match (&q.data, &q.message) {
+        (Some(data), Some(message)) => handle_callback_data(&bot, message, data, storage, dialogue, undo_journal).await,
+        _ => Ok(None),
+    };
+
+    // Ответ снимает «часики» с кнопки, поэтому отправляется и после ошибки обработки
+    let mut answer = bot.answer_callback_query(q.id);
+    if let Ok(Some(alert)) = &result {
+        answer = answer.text(alert).show_alert(true);
+    }
+    answer.await?;
+    result.map(|_| ())
+}
+
+/// Обрабатывает нажатие кнопки в сообщении `message`.
+/// Возвращает текст ошибки, которую нужно показать всплывающим окном, не трогая сообщение.
+async fn handle_callback_data(
+    bot: &Bot,
+    message: &Message,
+    data: &str,
+    storage: SharedStorage,
+    dialogue: BotDialogue,
+    undo_journal: UndoJournal,
+) -> Result<Option<String>, HandlerError> {
+    let chat_id = message.chat.id;
+
+    let Some(action) = CallbackAction::parse(data) else {
+        // Кнопка из старой версии бота или испорченные данные: предлагаем начать с меню
+        log::warn!("Unknown callback data in {}: {}", chat_id.0, data);
+        show(bot, message, "⚠️ Эта кнопка устарела. Выберите действие заново:", Some(create_main_menu())).await?;
+        return Ok(None);
+    };
+
+    match &action {
+        CallbackAction::TodoMenu => {
+            show(bot, message, "📝 TODO List — выберите действие:", Some(create_todo_menu())).await?;
+        }
+        CallbackAction::MainMenu => {
+            show(bot, message, "🏠 Главное меню:", Some(create_main_menu())).await?;
+        }
+        CallbackAction::Cancel => {
+            dialogue.exit().await?;
+            show(bot, message, "❌ Ввод отменен.", Some(create_main_menu())).await?;
+        }
+        CallbackAction::AddTask => {
+            dialogue.update(UserState::WaitingForTask).await?;
+            
+            show(bot, message, "📝 Введите текст новой задачи.\nПриоритет можно указать в начале: !high или !срочно\nТеги — в тексте: #работа\nСрок — в конце: @25.12 18:00 или @завтра", Some(create_cancel_keyboard())).await?;
+        }
+        CallbackAction::AddList => {
+            dialogue.update(UserState::WaitingForTaskList).await?;
+            
+            show(
+                bot,
+                message,
+                "📝 Введите список задач (каждая с новой строки):\n\n\
+                Примеры:\n\
+                • Купить хлеб\n\
+                • Позвонить маме\n\
+                • Сделать домашку\n\n\
+                Или:\n\
+                1. Первая задача\n\
+                2. Вторая задача\n\
+                3. Третья задача",
+                Some(create_cancel_keyboard()),
+            ).await?;
+        }
+        CallbackAction::MarkDone => {
+            let todos = storage.get_current_tasks(chat_id).await;
+            
+            if !todos.is_empty() {
+                dialogue.update(UserState::WaitingForTaskNumber).await?;
                 
-                bot.send_message(chat_id, "📝 Введите текст новой задачи:")
-                    .await?;
+                show(bot, message, "🔢 Введите номер или код задачи для отметки как выполненной:", Some(create_cancel_keyboard())).await?;
+            } else {
+                show(bot, message, "📝 У вас пока нет задач для отметки.", Some(create_todo_menu())).await?;
             }
-            "add_list" => {
-                {
-                    let mut states = user_states.lock().await;
-                    states.insert(chat_id, UserState::WaitingForTaskList);
-                }
+        }
+        CallbackAction::RemoveTask => {
+            let todos = storage.get_current_tasks(chat_id).await;
+            
+            if !todos.is_empty() {
+                dialogue.update(UserState::WaitingForRemovalNumber).await?;
                 
-                bot.send_message(
-                    chat_id, 
-                    "📝 Введите список задач (каждая с новой строки):\n\n\
+                show(bot, message, "🔢 Введите номер или код задачи для удаления:", Some(create_cancel_keyboard())).await?;
+            } else {
+                show(bot, message, "📝 У вас пока нет задач для удаления.", Some(create_todo_menu())).await?;
+            }
+        }
+        CallbackAction::RemindTask => {
+            let todos = storage.get_current_tasks(chat_id).await;
+
+            if todos.iter().any(|t| !t.is_completed()) {
+                dialogue.update(UserState::WaitingForTaskReminder).await?;
+
+                show(
+                    bot,
+                    message,
+                    "🔔 Введите номер или код задачи и время напоминания.\n\n\
                     Примеры:\n\
-                    • Купить хлеб\n\
-                    • Позвонить маме\n\
-                    • Сделать домашку\n\n\
-                    Или:\n\
-                    1. Первая задача\n\
-                    2. Вторая задача\n\
-                    3. Третья задача"
+                    • 1 18:30\n\
+                    • 2 завтра 9:00\n\
+                    • 3 25.12 18:00\n\
+                    • 1 через 30 мин",
+                    Some(create_cancel_keyboard()),
                 ).await?;
+            } else {
+                show(bot, message, "📝 У вас нет невыполненных задач для напоминания.", Some(create_todo_menu())).await?;
             }
-            "list_tasks" => {
-                let todos = storage.get_tasks(chat_id).await;
-
-                if !todos.is_empty() {
-                    let mut response = "📋 Ваши задачи:\n\n".to_string();
-                    for (index, todo) in todos.iter().enumerate() {
-                        let status = if todo.is_completed() { "✅" } else { "⏳" };
-                        response.push_str(&format!("{}. {} {}\n", index + 1, status, todo.text));
-                    }
-                    bot.send_message(chat_id, response)
-                        .reply_markup(create_todo_menu())
-                        .await?;
-                } else {
-                    bot.send_message(chat_id, "📝 У вас пока нет задач.")
-                        .reply_markup(create_todo_menu())
-                        .await?;
-                }
-            }
-            "mark_done" => {
-                let todos = storage.get_tasks(chat_id).await;
-                
-                if !todos.is_empty() {
-                    {
-                        let mut states = user_states.lock().await;
-                        states.insert(chat_id, UserState::WaitingForTaskNumber);
-                    }
-                    
-                    bot.send_message(chat_id, "🔢 Введите номер задачи для отметки как выполненной:")
-                        .await?;
-                } else {
-                    bot.send_message(chat_id, "📝 У вас пока нет задач для отметки.")
-                        .reply_markup(create_todo_menu())
-                        .await?;
-                }
-            }
-            "remove_task" => {
-                let todos = storage.get_tasks(chat_id).await;
-                
-                if !todos.is_empty() {
-                    {
-                        let mut states = user_states.lock().await;
-                        states.insert(chat_id, UserState::WaitingForRemovalNumber);
-                    }
-                    
-                    bot.send_message(chat_id, "🔢 Введите номер задачи для удаления:")
-                        .await?;
-                } else {
-                    bot.send_message(chat_id, "📝 У вас пока нет задач для удаления.")
-                        .reply_markup(create_todo_menu())
-                        .await?;
-                }
-            }
-            "clear_all" => {
-                if let Err(_) = storage.clear_tasks(chat_id).await {
-                    bot.send_message(chat_id, "❌ Ошибка при очистке задач")
-                        .reply_markup(create_todo_menu())
-                        .await?;
-                } else {
-                    bot.send_message(chat_id, "🧹 Все задачи очищены")
-                        .reply_markup(create_todo_menu())
-                        .await?;
-                }
-            }
-            "help" => {
-                let help_text = "📖 Справка по боту\n\n\
-                    Этот бот помогает вести список задач (TODO List) и напоминать о подаче показаний счетчиков.\n\n\
-                    Разделы:\n\
-                    • 📝 TODO List — добавляйте задачи по одной или списком, смотрите список, отмечайте выполненными, удаляйте.\n\
-                    • ⏰ Напоминалка — настройте период подачи показаний по воде и электричеству, получайте напоминания в нужные дни.\n\n\
-                    Используйте кнопки меню для навигации. Данные сохраняются автоматически.";
-                
-                bot.send_message(chat_id, help_text)
-                    .reply_markup(create_main_menu())
-                    .await?;
-            }
-            "todo_help" => {
-                let help_text = "📖 Справка по TODO List\n\n\
-                    TODO List — это список ваших задач. Можно добавлять задачи по одной или списком,\n\
-                    просматривать текущие, отмечать выполненными и удалять.\n\n\
-                    Команды:\n\
-                    • /add <текст> — добавить задачу\n\
-                    • /list — показать все задачи\n\
-                    • /done <номер> — отметить выполненной\n\
-                    • /remove <номер> — удалить задачу\n\
-                    • /clear — очистить все задачи\n\n\
-                    Подсказка: удобнее всего пользоваться кнопками меню.";
-                
-                bot.send_message(chat_id, help_text)
-                    .reply_markup(create_todo_menu())
-                    .await?;
-            }
-            "reminder_menu" => {
-                bot.send_message(chat_id, "⏰ Напоминалка — выберите действие:")
-                    .reply_markup(create_reminder_menu())
-                    .await?;
-            }
-            "reminder_help" => {
-                let help_text = "📖 Справка по Напоминалке\n\n\
-                    Здесь настраиваются периоды подачи показаний по 💧 воде и ⚡ электричеству.\n\
-                    Укажите диапазон дней (например, 16–25). Бот напомнит: в первый день периода, в середине\n\
-                    и каждый из последних 3 дней. После подтверждения напоминания прекращаются до следующего месяца.";
-                
-                bot.send_message(chat_id, help_text)
-                    .reply_markup(create_reminder_menu())
-                    .await?;
-            }
-            "counters_menu" => {
-                bot.send_message(chat_id, "🏠 Выберите тип счетчика для настройки:")
-                    .reply_markup(create_counters_menu())
-                    .await?;
-            }
-            "counter_water" => {
-                {
-                    let mut states = user_states.lock().await;
-                    states.insert(chat_id, UserState::WaitingForWaterPeriod);
-                }
-                let user = storage.get_user_reminders(chat_id).await;
-                let info = user.reminders.get("water").map(|r| format!("Текущий период: {}–{}", r.start_day, r.end_day)).unwrap_or_else(|| "Период не задан".to_string());
-                bot.send_message(
-                    chat_id,
-                    format!(
-                        "💧 Настройка напоминаний для счетчика воды\n{}\n\nВведите период в формате: начало-конец (например, 16-25)",
-                        info
-                    )
+        }
+        CallbackAction::EditTask => {
+            let todos = storage.get_current_tasks(chat_id).await;
+
+            if !todos.is_empty() {
+                dialogue.update(UserState::WaitingForTaskEdit).await?;
+
+                show(
+                    bot,
+                    message,
+                    "✏️ Введите номер или код задачи и новый текст.\n\n\
+                    Пример: 1 Купить кефир",
+                    Some(create_cancel_keyboard()),
                 ).await?;
+            } else {
+                show(bot, message, "📝 У вас пока нет задач.", Some(create_todo_menu())).await?;
             }
-            "counter_electricity" => {
-                {
-                    let mut states = user_states.lock().await;
-                    states.insert(chat_id, UserState::WaitingForElectricityPeriod);
-                }
-                let user = storage.get_user_reminders(chat_id).await;
-                let info = user.reminders.get("electricity").map(|r| format!("Текущий период: {}–{}", r.start_day, r.end_day)).unwrap_or_else(|| "Период не задан".to_string());
-                bot.send_message(
-                    chat_id,
-                    format!(
-                        "⚡ Настройка напоминаний для счетчика электричества\n{}\n\nВведите период в формате: начало-конец (например, 16-25)",
-                        info
-                    )
+        }
+        CallbackAction::OrderMenu => {
+            let todos = storage.get_current_tasks(chat_id).await;
+
+            if todos.len() > 1 {
+                show(bot, message, "↕️ Выберите задачу, которую нужно переставить:", Some(create_task_picker_keyboard(&todos, CallbackAction::TaskOrder))).await?;
+            } else {
+                show(bot, message, "📝 Переставлять пока нечего: в списке меньше двух задач.", Some(create_todo_menu())).await?;
+            }
+        }
+        CallbackAction::PriorityMenu => {
+            let todos = storage.get_current_tasks(chat_id).await;
+
+            if todos.iter().any(|t| !t.is_completed()) {
+                show(bot, message, "🔼 Выберите задачу, чтобы изменить приоритет:", Some(create_task_picker_keyboard(&todos, CallbackAction::TaskPriority))).await?;
+            } else {
+                show(bot, message, "📝 У вас нет невыполненных задач.", Some(create_todo_menu())).await?;
+            }
+        }
+        CallbackAction::TagsMenu => {
+            let tags = storage.get_tags(chat_id).await;
+
+            if !tags.is_empty() {
+                show(bot, message, "🏷 Выберите тег:", Some(create_tags_keyboard(&tags))).await?;
+            } else {
+                show(bot, message, "🏷 У задач пока нет тегов. Добавьте их в текст задачи: Купить билеты #отпуск", Some(create_todo_menu())).await?;
+            }
+        }
+        CallbackAction::RepeatTask => {
+            let todos = storage.get_current_tasks(chat_id).await;
+
+            if !todos.is_empty() {
+                dialogue.update(UserState::WaitingForTaskRecurrence).await?;
+
+                show(
+                    bot,
+                    message,
+                    "🔁 Введите номер или код задачи и правило повторения.\n\n\
+                    Примеры:\n\
+                    • 1 каждый день\n\
+                    • 2 по пн, чт\n\
+                    • 3 ежемесячно 5\n\
+                    • 1 каждые 3 дня\n\
+                    • 2 нет — не повторять",
+                    Some(create_cancel_keyboard()),
                 ).await?;
+            } else {
+                show(bot, message, "📝 У вас пока нет задач.", Some(create_todo_menu())).await?;
+            }
+        }
+        CallbackAction::ClearAll => {
+            match clear_prompt(&storage, chat_id).await {
+                Some(prompt) => {
+                    show(bot, message, prompt, Some(create_clear_confirm_keyboard())).await?;
+                }
+                None => {
+                    show(bot, message, "📝 У вас пока нет задач.", Some(create_todo_menu())).await?;
+                }
+            }
+        }
+        CallbackAction::ClearConfirm => {
+            match clear_current_list(&storage, &undo_journal, chat_id).await {
+                Ok(confirmation) => {
+                    show(bot, message, confirmation, Some(create_undo_keyboard())).await?;
+                }
+                Err(error) => {
+                    show(bot, message, format!("❌ {}", error), Some(create_todo_menu())).await?;
+                }
+            }
+        }
+        CallbackAction::ArchiveMenu | CallbackAction::TrashMenu => {
+            let (text, keyboard) = removed_tasks_view(&storage, chat_id, action == CallbackAction::TrashMenu).await;
+            show(bot, message, text, Some(keyboard)).await?;
+        }
+        CallbackAction::EmptyTrash => {
+            match empty_trash_prompt(&storage, chat_id).await {
+                Some(prompt) => {
+                    show(bot, message, prompt, Some(create_empty_trash_confirm_keyboard())).await?;
+                }
+                None => {
+                    show(bot, message, "♻️ Корзина пуста.", Some(create_todo_menu())).await?;
+                }
+            }
+        }
+        CallbackAction::EmptyTrashConfirm => {
+            let text = match storage.empty_trash(chat_id).await {
+                Ok(count) => format!("🔥 Корзина очищена, удалено задач: {}", count),
+                Err(error) => format!("❌ {}", error),
+            };
+            show(bot, message, text, Some(create_todo_menu())).await?;
+        }
+        CallbackAction::Undo => {
+            let text = match undo_last_removal(&storage, &undo_journal, chat_id).await {
+                Ok(confirmation) => confirmation,
+                Err(error) => format!("❌ {}", error),
+            };
+            show(bot, message, text, Some(create_todo_menu())).await?;
+        }
+        CallbackAction::ListsMenu => {
+            let lists = storage.get_lists(chat_id).await;
+            let current = storage.get_current_list(chat_id).await.map(|list| list.id);
+
+            show(bot, message, "🗂 Ваши списки задач. Выберите текущий:", Some(create_lists_keyboard(&lists, current.as_deref()))).await?;
+        }
+        CallbackAction::SearchTasks => {
+            dialogue.update(UserState::WaitingForSearchQuery).await?;
+
+            show(bot, message, "🔍 Введите слово или часть текста задачи. Ищу во всех списках и в архиве:", Some(create_cancel_keyboard())).await?;
+        }
+        CallbackAction::CreateList => {
+            dialogue.update(UserState::WaitingForListName).await?;
+
+            show(bot, message, "🗂 Введите название нового списка, например: Работа", Some(create_cancel_keyboard())).await?;
+        }
+        CallbackAction::RenameList => {
+            match storage.get_current_list(chat_id).await {
+                Some(list) => {
+                    dialogue.update(UserState::WaitingForListRename).await?;
+
+                    show(bot, message, format!("✏️ Введите новое название для списка «{}»:", list.name), Some(create_cancel_keyboard())).await?;
+                }
+                None => return Ok(Some("❌ Основной список нельзя переименовать. Сначала выберите другой список.".to_string())),
+            }
+        }
+        CallbackAction::DeleteList => {
+            let reply = match delete_current_list(&storage, chat_id).await {
+                Ok(confirmation) => confirmation,
+                Err(error) => format!("❌ {}", error),
+            };
+            let lists = storage.get_lists(chat_id).await;
+            let current = storage.get_current_list(chat_id).await.map(|list| list.id);
+
+            show(bot, message, reply, Some(create_lists_keyboard(&lists, current.as_deref()))).await?;
+        }
+        CallbackAction::MoveTaskPicker => {
+            let todos = storage.get_current_tasks(chat_id).await;
+
+            if todos.iter().any(|t| !t.is_completed()) {
+                show(bot, message, "📦 Выберите задачу для переноса (подзадачи переносятся вместе с ней):", Some(create_task_picker_keyboard(&todos, CallbackAction::MoveTask))).await?;
+            } else {
+                show(bot, message, "📝 У вас нет невыполненных задач.", Some(create_todo_menu())).await?;
             }
-            "toggle_reminders" => {
-                match storage.toggle_global_reminders(chat_id).await {
-                    Ok(enabled) => {
-                        let status = if enabled { "включены ✅" } else { "отключены ❌" };
-                        bot.send_message(chat_id, format!("🔔 Напоминания {}", status))
-                            .reply_markup(create_reminder_menu())
-                            .await?;
-                    }
-                    Err(_) => {
-                        bot.send_message(chat_id, "❌ Ошибка при изменении настроек")
-                            .reply_markup(create_reminder_menu())
-                            .await?;
-                    }
-                }
-            }
-            data if data.starts_with("sent_yes_") => {
-                let counter_type_str = data.strip_prefix("sent_yes_").unwrap();
-                if let Some(counter_type) = CounterType::from_str(counter_type_str) {
-                    if let Err(_) = storage.mark_counter_completed(chat_id, counter_type.clone()).await {
-                        bot.send_message(chat_id, "❌ Ошибка при сохранении")
-                            .await?;
-                    } else {
-                        bot.send_message(
-                            chat_id, 
-                            format!("✅ Отлично! Показания {} отмечены как отправленные.\nНапоминания приостановлены до следующего месяца.", counter_type.display_name())
-                        ).await?;
-                    }
-                }
-            }
-            data if data.starts_with("sent_no_") => {
-                let counter_type_str = data.strip_prefix("sent_no_").unwrap();
-                if let Some(counter_type) = CounterType::from_str(counter_type_str) {
-                    bot.send_message(
-                        chat_id, 
-                        format!("⏰ Хорошо, я продолжу напоминать о показаниях {}.\nНе забудьте отправить их вовремя!", counter_type.display_name())
-                    ).await?;
-                }
-            }
-            _ => {}
-        }
-        
-        bot.answer_callback_query(q.id).await?;
+        }
+        CallbackAction::Help => {
+            let help_text = "📖 Справка по боту\n\n\
+                Этот бот помогает вести список задач (TODO List) и напоминать о подаче показаний счетчиков.\n\n\
+                Разделы:\n\
+                • 📝 TODO List — добавляйте задачи по одной или списком, смотрите список, отмечайте выполненными, удаляйте.\n\
+                • ⏰ Напоминалка — заведите свои счетчики (вода, электричество, газ, интернет…) с периодом подачи показаний, получайте напоминания в нужные дни.\n\n\
+                Используйте кнопки меню для навигации. Данные сохраняются автоматически.";
+            
+            show(bot, message, help_text, Some(create_main_menu())).await?;
+        }
+        CallbackAction::TodoHelp => {
+            let help_text = "📖 Справка по TODO List\n\n\
+                TODO List — это список ваших задач. Можно добавлять задачи по одной или списком,\n\
+                просматривать текущие, отмечать выполненными и удалять.\n\n\
+                Команды:\n\
+                • /add <текст> [@срок] — добавить задачу (срок: @25.12 18:00, @завтра)\n\
+                • /list [срок] — показать все задачи (по сроку)\n\
+                • /edit <номер> <текст> — изменить текст задачи\n\
+                • /move <номер> <место> — переставить задачу\n\
+                • /done <номер или код> — отметить выполненной\n\
+                • /remove <номер или код> — удалить задачу\n\
+                • /clear — очистить задачи текущего списка\n\
+                • /undo — отменить последнее удаление или очистку\n\
+                • /archive, /trash — архив выполненных и корзина удаленных задач\n\
+                • /search <текст> — найти задачу во всех списках и в архиве\n\
+                • /lists — списки задач («Дом», «Работа»…), /switch <название> — перейти в список\n\n\
+                Подсказка: удобнее всего пользоваться кнопками меню.";
+            
+            show(bot, message, help_text, Some(create_todo_menu())).await?;
+        }
+        CallbackAction::ReminderMenu => {
+            show(bot, message, "⏰ Напоминалка — выберите действие:", Some(create_reminder_menu())).await?;
+        }
+        CallbackAction::ReminderHelp => {
+            let help_text = "📖 Справка по Напоминалке\n\n\
+                Здесь настраиваются периоды подачи показаний счетчиков. Счетчики заводите сами:\n\
+                💧 вода, ⚡ электричество, 🔥 газ, 🌐 интернет — с любым названием и эмодзи.\n\
+                Для каждого укажите диапазон дней (например, 16–25). Бот напомнит: в первый день периода, в середине\n\
+                и каждый из последних 3 дней. После подтверждения напоминания прекращаются до следующего месяца.\n\n\
+                Показания можно вводить прямо в боте. Задайте в карточке счетчика тариф (цену за единицу, по зонам день/ночь),\n\
+                и бот посчитает расход и стоимость за месяц — отчет по всем счетчикам в разделе «📊 Расходы».";
+            
+            show(bot, message, help_text, Some(create_reminder_menu())).await?;
+        }
+        CallbackAction::CountersMenu => {
+            let (text, keyboard) = counters_view(&storage, chat_id).await;
+            show(bot, message, text, Some(keyboard)).await?;
+        }
+        CallbackAction::AddCounter => {
+            dialogue.update(UserState::WaitingForNewCounter).await?;
+            show(
+                bot,
+                message,
+                "➕ Новый счетчик\n\nВведите эмодзи, название и период подачи показаний (начало-конец), например:\n\
+                🔥 Газ 20-25\n🌐 Интернет 1-5",
+                Some(create_cancel_keyboard()),
+            ).await?;
+        }
+        CallbackAction::Counter(counter_id) => {
+            match counter_view(&storage, chat_id, counter_id).await {
+                Some((text, keyboard)) => show(bot, message, text, Some(keyboard)).await?,
+                None => return Ok(Some("❌ Счетчик не найден".to_string())),
+            }
+        }
+        CallbackAction::CounterPeriod(counter_id) => {
+            let user = storage.get_user_reminders(chat_id).await;
+            let Some(reminder) = user.get_reminder(counter_id) else {
+                return Ok(Some("❌ Счетчик не найден".to_string()));
+            };
+            dialogue.update(UserState::WaitingForPeriod(counter_id.clone())).await?;
+            show(
+                bot,
+                message,
+                format!(
+                    "{} Настройка напоминаний для счетчика «{}»\n\
+                    Текущий период: {}–{}\n\n\
+                    Введите период в формате: начало-конец (например, 16-25)",
+                    reminder.counter_type.emoji, reminder.counter_type.name, reminder.start_day, reminder.end_day
+                ),
+                Some(create_cancel_keyboard()),
+            ).await?;
+        }
+        CallbackAction::RenameCounter(counter_id) => {
+            let user = storage.get_user_reminders(chat_id).await;
+            let Some(reminder) = user.get_reminder(counter_id) else {
+                return Ok(Some("❌ Счетчик не найден".to_string()));
+            };
+            dialogue.update(UserState::WaitingForCounterRename(counter_id.clone())).await?;
+            show(
+                bot,
+                message,
+                format!("✏️ Введите новое название счетчика {} (можно с эмодзи в начале):", reminder.counter_type.display_name()),
+                Some(create_cancel_keyboard()),
+            ).await?;
+        }
+        CallbackAction::CounterZones(counter_id) => {
+            let user = storage.get_user_reminders(chat_id).await;
+            let Some(reminder) = user.get_reminder(counter_id) else {
+                return Ok(Some("❌ Счетчик не найден".to_string()));
+            };
+            let current = if reminder.counter_type.zones.is_empty() {
+                "без зон".to_string()
+            } else {
+                reminder.counter_type.zones.join(", ")
+            };
+            dialogue.update(UserState::WaitingForCounterZones(counter_id.clone())).await?;
+            show(
+                bot,
+                message,
+                format!(
+                    "🔀 Тарифные зоны счетчика {}\nСейчас: {}\n\nВведите зоны через запятую, например: день, ночь или горячая, холодная.\n\
+                    «нет» — одно показание без зон.",
+                    reminder.counter_type.display_name(), current
+                ),
+                Some(create_cancel_keyboard()),
+            ).await?;
+        }
+        CallbackAction::EnterReading(counter_id) => {
+            let Some(prompt) = reading_prompt(&storage, chat_id, counter_id).await else {
+                return Ok(Some("❌ Счетчик не найден".to_string()));
+            };
+            dialogue.update(UserState::WaitingForReading(counter_id.clone())).await?;
+            show(bot, message, prompt, Some(create_cancel_keyboard())).await?;
+        }
+        CallbackAction::CounterTariff(counter_id) => {
+            let Some(prompt) = tariff_prompt(&storage, chat_id, counter_id).await else {
+                return Ok(Some("❌ Счетчик не найден".to_string()));
+            };
+            dialogue.update(UserState::WaitingForTariff(counter_id.clone())).await?;
+            show(bot, message, prompt, Some(create_cancel_keyboard())).await?;
+        }
+        CallbackAction::ConsumptionReport(month) => {
+            let (text, keyboard) = consumption_report_view(&storage, chat_id, *month).await;
+            show(bot, message, text, Some(keyboard)).await?;
+        }
+        CallbackAction::DeleteCounter(counter_id) => {
+            match delete_counter(&storage, chat_id, counter_id).await {
+                Ok(confirmation) => {
+                    let (text, keyboard) = counters_view(&storage, chat_id).await;
+                    show(bot, message, format!("{}\n\n{}", confirmation, text), Some(keyboard)).await?;
+                }
+                Err(error) => return Ok(Some(format!("❌ {}", error))),
+            }
+        }
+        CallbackAction::ToggleReminders => {
+            match storage.toggle_global_reminders(chat_id).await {
+                Ok(enabled) => {
+                    let status = if enabled { "включены ✅" } else { "отключены ❌" };
+                    show(bot, message, format!("🔔 Напоминания {}", status), Some(create_reminder_menu())).await?;
+                }
+                Err(_) => {
+                    show(bot, message, "❌ Ошибка при изменении настроек", Some(create_reminder_menu())).await?;
+                }
+            }
+        }
+        CallbackAction::ListTasks { sort, page, tag } => {
+            let (text, keyboard) = task_list_view(&storage, chat_id, *sort, tag.as_deref(), *page).await;
+            show(bot, message, text, Some(keyboard)).await?;
+        }
+        CallbackAction::CounterSent { counter_id, sent } => {
+            let user = storage.get_user_reminders(chat_id).await;
+            let Some(reminder) = user.get_reminder(counter_id) else {
+                return Ok(Some("❌ Счетчик удален".to_string()));
+            };
+            if !*sent {
+                show(bot, message, format!("⏰ Хорошо, я продолжу напоминать о показаниях {}.\nНе забудьте отправить их вовремя!", reminder.counter_type.display_name()), None).await?;
+            } else if storage.mark_counter_completed(chat_id, counter_id).await.is_err() {
+                return Ok(Some("❌ Ошибка при сохранении".to_string()));
+            } else {
+                show(bot, message, format!("✅ Отлично! Показания {} отмечены как отправленные.\nНапоминания приостановлены до следующего месяца.", reminder.counter_type.display_name()), None).await?;
+            }
+        }
+        CallbackAction::TaskDone(task_id) => {
+            match complete_task_by_id(&storage, chat_id, task_id).await {
+                Ok(task_text) => {
+                    show(bot, message, format!("✅ Задача \"{}\" отмечена как выполненная!", task_text), Some(create_todo_menu())).await?;
+                }
+                Err(error) => return Ok(Some(format!("❌ {}", error))),
+            }
+        }
+        CallbackAction::DeleteTask(task_id) => {
+            match remove_task_with_undo(&storage, &undo_journal, chat_id, task_id).await {
+                Ok(confirmation) => {
+                    show(bot, message, confirmation, Some(create_undo_keyboard())).await?;
+                }
+                Err(error) => return Ok(Some(format!("❌ {}", error))),
+            }
+        }
+        CallbackAction::EditText(task_id) => {
+            let todos = storage.get_current_tasks(chat_id).await;
+
+            match todos.iter().find(|t| &t.id == task_id) {
+                Some(todo) => {
+                    dialogue.update(UserState::WaitingForNewTaskText(todo.id.clone())).await?;
+
+                    show(bot, message, format!("✏️ Введите новый текст задачи \"{}\":", todo.text), Some(create_cancel_keyboard())).await?;
+                }
+                None => {
+                    show(bot, message, "❌ Задача не найдена", Some(create_todo_menu())).await?;
+                }
+            }
+        }
+        CallbackAction::TaskOrder(task_id) => {
+            let (text, keyboard) = task_order_view(&storage, chat_id, task_id, 0).await;
+            show(bot, message, text, Some(keyboard)).await?;
+        }
+        CallbackAction::MoveTaskUp { task_id, page } | CallbackAction::MoveTaskDown { task_id, page } => {
+            let up = matches!(action, CallbackAction::MoveTaskUp { .. });
+            if let Err(error) = shift_task(&storage, chat_id, task_id, up).await {
+                return Ok(Some(format!("❌ {}", error)));
+            }
+            let (text, keyboard) = task_order_view(&storage, chat_id, task_id, *page).await;
+            show(bot, message, text, Some(keyboard)).await?;
+        }
+        CallbackAction::TaskPriority(task_id) => {
+            let todos = storage.get_current_tasks(chat_id).await;
+
+            match todos.iter().find(|t| &t.id == task_id) {
+                Some(todo) => {
+                    show(bot, message, format!("Задача \"{}\"\nТекущий приоритет: {}", todo.text, todo.priority.display_name()), Some(create_priority_keyboard(task_id))).await?;
+                }
+                None => {
+                    show(bot, message, "❌ Задача не найдена", Some(create_todo_menu())).await?;
+                }
+            }
+        }
+        CallbackAction::SetPriority { task_id, priority } => {
+            match storage.set_task_priority(chat_id, task_id, *priority).await {
+                Ok(task_text) => {
+                    show(bot, message, format!("✅ Приоритет задачи \"{}\": {}", task_text, priority.display_name()), Some(create_todo_menu())).await?;
+                }
+                Err(error) => return Ok(Some(format!("❌ {}", error))),
+            }
+        }
+        CallbackAction::Restore(task_id) => {
+            let text = match restore_task_by_id(&storage, chat_id, task_id).await {
+                Ok(confirmation) => confirmation,
+                Err(error) => format!("❌ {}", error),
+            };
+            show(bot, message, text, Some(create_todo_menu())).await?;
+        }
+        CallbackAction::SwitchList(list_id) => {
+            let list_id = list_id.as_deref();
+            match storage.set_current_list(chat_id, list_id).await {
+                Ok(()) => {
+                    let lists = storage.get_lists(chat_id).await;
+                    let name = lists
+                        .iter()
+                        .find(|l| Some(l.id.as_str()) == list_id)
+                        .map_or(MAIN_LIST_NAME, |l| l.name.as_str());
+                    show(bot, message, format!("🗂 Текущий список: «{}»", name), Some(create_todo_menu())).await?;
+                }
+                Err(error) => return Ok(Some(format!("❌ {}", error))),
+            }
+        }
+        CallbackAction::MoveTask(task_id) => {
+            let lists = storage.get_lists(chat_id).await;
+            let current = storage.get_current_list(chat_id).await.map(|list| list.id);
+
+            show(bot, message, "📦 В какой список перенести задачу?", Some(create_move_target_keyboard(&lists, current.as_deref(), task_id))).await?;
+        }
+        CallbackAction::MoveTaskTo { task_id, list_id } => {
+            let list_id = list_id.as_deref();
+            match storage.move_task_to_list(chat_id, task_id, list_id).await {
+                Ok(task_text) => {
+                    let lists = storage.get_lists(chat_id).await;
+                    let name = lists
+                        .iter()
+                        .find(|l| Some(l.id.as_str()) == list_id)
+                        .map_or(MAIN_LIST_NAME, |l| l.name.as_str());
+                    show(bot, message, format!("📦 Задача \"{}\" перенесена в «{}»", task_text, name), Some(create_todo_menu())).await?;
+                }
+                Err(error) => return Ok(Some(format!("❌ {}", error))),
+            }
+        }
+        CallbackAction::SnoozeTask { task_id, snooze } => {
+            // Задачу могли выполнить или удалить после того, как пришло напоминание
+            let tasks = storage.get_tasks(chat_id).await;
+            if !tasks.iter().any(|t| &t.id == task_id && t.is_active() && !t.is_completed()) {
+                return Ok(Some("❌ Задача не найдена".to_string()));
+            }
+
+            let delay = match snooze {
+                Snooze::Hour => chrono::Duration::hours(1),
+                Snooze::Day => chrono::Duration::days(1),
+            };
+            let now = chrono::Utc::now();
+            let reminder = TaskReminder::new(chat_id.0, task_id.clone(), now + delay);
+
+            if storage.add_task_reminder(reminder).await.is_err() {
+                return Ok(Some("❌ Ошибка при сохранении напоминания".to_string()));
+            }
+            show(bot, message, format!("⏰ Хорошо, напомню {}", format_due(now + delay, now)), None).await?;
+        }
+    }
+
+    Ok(None)
+}
+
+/// Показывает ответ на нажатие в том же сообщении, чтобы навигация по меню не засоряла чат.
+/// Без `keyboard` кнопки сообщения убираются. Если сообщение изменить нельзя
+/// (например, оно слишком старое), ответ отправляется новым сообщением.
+async fn show(bot: &Bot, message: &Message, text: impl Into<String>, keyboard: Option<InlineKeyboardMarkup>) -> ResponseResult<()> {
+    let text = text.into();
+    let mut edit = bot.edit_message_text(message.chat.id, message.id, text.clone());
+    if let Some(keyboard) = keyboard.clone() {
+        edit = edit.reply_markup(keyboard);
+    }
+
+    match edit.await {
+        Ok(_) | Err(RequestError::Api(ApiError::MessageNotModified)) => Ok(()),
+        Err(RequestError::Api(error)) => {
+            log::debug!("Can't edit message {} in {}: {}", message.id.0, message.chat.id.0, error);
+            let mut send = bot.send_message(message.chat.id, text);
+            if let Some(keyboard) = keyboard {
+                send = send.reply_markup(keyboard);
+            }
+            send.await?;
+            Ok(())
+        }
+        Err(error) => Err(error),
     }
-    
-    Ok(())
-}
\ No newline at end of file
+}
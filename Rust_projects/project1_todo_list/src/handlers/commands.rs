@@ -1,8 +1,12 @@
+use chrono::Utc;
 use teloxide::{prelude::*, utils::command::BotCommands};
 
-use crate::models::{UserState, UserStates};
-use crate::storage::StorageType;
-use crate::utils::{create_main_menu, create_todo_menu};
+use crate::models::{Priority, TodoItem, UndoJournal, UserState};
+use crate::storage::SharedStorage;
+use super::dialogue::BotDialogue;
+use super::HandlerResult;
+use super::messages::{clear_prompt, complete_task_by_id, create_task_list, delete_current_list, edit_task_text, empty_trash_prompt, removed_tasks_view, restore_removed_task, move_task_to_named_list, move_task_to_position, remove_task_with_undo, rename_current_list, schedule_task_reminder, search_view, switch_list, task_list_view, todo_from_list_line, undo_last_removal, update_task_recurrence};
+use crate::utils::{create_main_menu, create_todo_menu, create_lists_keyboard, create_clear_confirm_keyboard, create_empty_trash_confirm_keyboard, create_undo_keyboard, extract_priority, extract_tags, extract_due_date, format_task_title, normalize_tag, resolve_task, TaskSort, TaskValidator};
 
 #[derive(BotCommands, Clone)]
 #[command(rename_rule = "lowercase", description = "Доступные команды:")]
@@ -11,16 +15,56 @@ pub enum Command {
     Start,
     #[command(description = "показать помощь")]
     Help,
-    #[command(description = "добавить задачу")]
+    #[command(description = "добавить задачу (приоритет: !high, теги: #работа, срок: @25.12 18:00)")]
     Add(String),
-    #[command(description = "показать все задачи")]
-    List,
-    #[command(description = "отметить задачу как выполненную")]
+    #[command(description = "показать все задачи (/list срок, /list приоритет, /list #тег)")]
+    List(String),
+    #[command(description = "добавить подзадачу: /sub <номер> <текст>")]
+    Sub(String),
+    #[command(description = "изменить текст задачи: /edit <номер> <текст>")]
+    Edit(String),
+    #[command(description = "переставить задачу: /move <номер> <новое место>")]
+    Move(String),
+    #[command(description = "отметить задачу как выполненную (номер или код)")]
     Done(String),
-    #[command(description = "удалить задачу")]
+    #[command(description = "удалить задачу (номер или код)")]
     Remove(String),
-    #[command(description = "очистить все задачи")]
+    #[command(description = "очистить задачи текущего списка")]
     Clear,
+    #[command(description = "отменить последнее удаление или очистку")]
+    Undo,
+    #[command(description = "показать архив выполненных задач")]
+    Archive,
+    #[command(description = "вернуть задачу из архива: /unarchive <номер или код>")]
+    Unarchive(String),
+    #[command(description = "показать корзину удаленных задач")]
+    Trash,
+    #[command(description = "вернуть задачу из корзины: /restore <номер или код>")]
+    Restore(String),
+    #[command(description = "очистить корзину навсегда")]
+    EmptyTrash,
+    #[command(description = "найти задачи во всех списках и архиве: /search <текст>")]
+    Search(String),
+    #[command(description = "напомнить о задаче: /remind <номер> <когда>")]
+    Remind(String),
+    #[command(description = "повторять задачу: /repeat <номер> <правило>")]
+    Repeat(String),
+    #[command(description = "приоритет задачи: /priority <номер> <low|normal|high|urgent>")]
+    Priority(String),
+    #[command(description = "показать списки задач")]
+    Lists,
+    #[command(description = "создать список: /newlist <название>")]
+    NewList(String),
+    #[command(description = "переименовать текущий список: /renamelist <название>")]
+    RenameList(String),
+    #[command(description = "удалить текущий список (задачи перейдут в основной)")]
+    DeleteList,
+    #[command(description = "перейти в другой список: /switch <название>")]
+    Switch(String),
+    #[command(description = "перенести задачу в другой список: /moveto <номер> <список>")]
+    MoveTo(String),
+    #[command(description = "отменить текущий ввод")]
+    Cancel,
     #[command(description = "тестировать напоминания (только для разработки)")]
     TestReminders,
 }
@@ -29,14 +73,13 @@ pub async fn handle_command(
     bot: Bot,
     msg: Message,
     command: Command,
-    storage: StorageType,
-    user_states: UserStates,
-) -> ResponseResult<()> {
-    // Сброс состояния пользователя при любой команде
-    {
-        let mut states = user_states.lock().await;
-        states.insert(msg.chat.id, UserState::Default);
-    }
+    storage: SharedStorage,
+    dialogue: BotDialogue,
+    state: UserState,
+    undo_journal: UndoJournal,
+) -> HandlerResult {
+    // Любая команда прерывает начатый ввод
+    dialogue.exit().await?;
 
     match command {
         Command::Start => {
@@ -53,11 +96,34 @@ pub async fn handle_command(
                 🔹 Используйте кнопки меню для удобной работы\n\
                 🔹 Или команды:\n\n\
                 /start - главное меню\n\
-                /add <текст> - добавить задачу\n\
-                /list - показать все задачи\n\
-                /done <номер> - отметить выполненной\n\
-                /remove <номер> - удалить задачу\n\
-                /clear - очистить все задачи\n\n\
+                /add [!приоритет] <текст> [#тег] [@срок] - добавить задачу\n\
+                /list [срок | приоритет] [#тег] - показать задачи\n\
+                /sub <номер> <текст> - добавить подзадачу (пункт чек-листа)\n\
+                /edit <номер> <текст> - изменить текст задачи\n\
+                /move <номер> <место> - переставить задачу в списке\n\
+                /done <номер или код> - отметить выполненной\n\
+                /remove <номер или код> - удалить задачу\n\
+                /clear - очистить задачи текущего списка\n\
+                /undo - отменить последнее удаление или очистку\n\
+                /archive - архив выполненных задач, /unarchive <номер> - вернуть из архива\n\
+                /trash - корзина, /restore <номер> - вернуть из корзины\n\
+                /emptytrash - очистить корзину навсегда\n\
+                /search <текст> - найти задачи во всех списках и в архиве\n\
+                /remind <номер> <когда> - напомнить о задаче\n\
+                /repeat <номер> <правило> - повторять задачу\n\
+                /priority <номер> <уровень> - изменить приоритет\n\
+                /lists - списки задач\n\
+                /newlist <название> - создать список\n\
+                /renamelist <название> - переименовать текущий список\n\
+                /deletelist - удалить текущий список\n\
+                /switch <название> - перейти в другой список\n\
+                /moveto <номер> <список> - перенести задачу в другой список\n\
+                /cancel - отменить начатый ввод\n\n\
+                ‼️ Приоритет: !low, !normal, !high, !urgent (или !низкий, !высокий, !срочно)\n\
+                🏷 Теги: #работа, #дом — в любом месте текста\n\
+                📋 Подзадачи при добавлении списка — строки с отступом под задачей\n\
+                📅 Срок: @25.12, @25.12 18:00, @сегодня, @завтра 9:00\n\
+                🔁 Повторение: каждый день, по пн, чт, ежемесячно 5, каждые 3 дня, нет\n\n\
                 💡 Совет: используйте кнопки - это удобнее!";
             
             bot.send_message(msg.chat.id, help_text)
@@ -72,47 +138,141 @@ pub async fn handle_command(
                 return Ok(());
             }
 
-            if let Err(_) = storage.add_task(msg.chat.id, task_text.trim()).await {
+            let (task_text, priority) = extract_priority(&task_text);
+            let (task_text, tags) = extract_tags(&task_text);
+            let todo_item = match extract_due_date(&task_text, Utc::now()) {
+                Ok((text, _)) if text.is_empty() => {
+                    bot.send_message(msg.chat.id, "Пожалуйста, укажите текст задачи. Пример: /add Купить молоко @25.12 18:00")
+                        .reply_markup(create_todo_menu())
+                        .await?;
+                    return Ok(());
+                }
+                Ok((text, due_at)) => TodoItem::new(text)
+                    .with_due(due_at)
+                    .with_priority(priority)
+                    .with_tags(tags)
+                    .with_list(storage.get_current_list(msg.chat.id).await.map(|list| list.id)),
+                Err(error) => {
+                    bot.send_message(msg.chat.id, format!("❌ {}. Пример срока: @25.12 18:00 или @завтра", error))
+                        .reply_markup(create_todo_menu())
+                        .await?;
+                    return Ok(());
+                }
+            };
+            let title = format_task_title(&todo_item);
+
+            if storage.add_task(msg.chat.id, todo_item).await.is_err() {
                 bot.send_message(msg.chat.id, "❌ Ошибка при добавлении задачи")
                     .reply_markup(create_todo_menu())
                     .await?;
                 return Ok(());
             }
             
-            bot.send_message(msg.chat.id, format!("✅ Задача добавлена: {}", task_text.trim()))
+            bot.send_message(msg.chat.id, format!("✅ Задача добавлена: {}", title))
                 .reply_markup(create_todo_menu())
                 .await?;
         }
-        Command::List => {
-            let todos = storage.get_tasks(msg.chat.id).await;
+        Command::List(args) => {
+            // Аргументы: необязательная сортировка и тег-фильтр, например `/list срок #работа`
+            let (tag_args, sort_args): (Vec<&str>, Vec<&str>) = args.split_whitespace().partition(|arg| arg.starts_with('#'));
+            let tag = match tag_args.as_slice() {
+                [] => None,
+                [tag] => match normalize_tag(tag) {
+                    Some(tag) => Some(tag),
+                    None => {
+                        bot.send_message(msg.chat.id, "❌ Некорректный тег. Пример: /list #работа")
+                            .reply_markup(create_todo_menu())
+                            .await?;
+                        return Ok(());
+                    }
+                },
+                _ => {
+                    bot.send_message(msg.chat.id, "❌ Укажите один тег. Пример: /list #работа")
+                        .reply_markup(create_todo_menu())
+                        .await?;
+                    return Ok(());
+                }
+            };
+            let sort = match TaskSort::from_arg(&sort_args.join(" ")) {
+                Some(sort) => sort,
+                None => {
+                    bot.send_message(msg.chat.id, "Неизвестная сортировка. Пример: /list, /list срок или /list приоритет")
+                        .reply_markup(create_todo_menu())
+                        .await?;
+                    return Ok(());
+                }
+            };
+            let (text, keyboard) = task_list_view(&storage, msg.chat.id, sort, tag.as_deref(), 0).await;
+            bot.send_message(msg.chat.id, text)
+                .reply_markup(keyboard)
+                .await?;
+        }
+        Command::Sub(args) => {
+            let (task_ref, text) = args.trim().split_once(char::is_whitespace).unwrap_or((args.trim(), ""));
+            let tasks = storage.get_current_tasks(msg.chat.id).await;
+
+            let result = match resolve_task(&tasks, task_ref) {
+                Ok(parent) if parent.parent_id.is_some() => Err("Подзадачи можно добавлять только к задачам верхнего уровня".to_string()),
+                Ok(parent) if parent.is_completed() => Err("Задача уже выполнена".to_string()),
+                Ok(parent) => match TaskValidator::new() {
+                    Ok(validator) => todo_from_list_line(&validator, text).map(|item| {
+                        item.with_parent(Some(parent.id.clone())).with_list(parent.list_id.clone())
+                    }),
+                    Err(_) => Err("Ошибка проверки текста".to_string()),
+                },
+                Err(error) => Err(error),
+            };
 
-            if !todos.is_empty() {
-                let mut response = "📋 Ваши задачи:\n\n".to_string();
-                for (index, todo) in todos.iter().enumerate() {
-                    let status = if todo.is_completed() { "✅" } else { "⏳" };
-                    response.push_str(&format!("{}. {} {}\n", index + 1, status, todo.text));
+            let reply = match result {
+                Ok(todo_item) => {
+                    let title = format_task_title(&todo_item);
+                    match storage.add_task(msg.chat.id, todo_item).await {
+                        Ok(()) => format!("✅ Подзадача добавлена: {}", title),
+                        Err(_) => "❌ Ошибка при добавлении задачи".to_string(),
+                    }
                 }
-                bot.send_message(msg.chat.id, response)
-                    .reply_markup(create_todo_menu())
-                    .await?;
-            } else {
-                bot.send_message(msg.chat.id, "📝 У вас пока нет задач.")
-                    .reply_markup(create_todo_menu())
-                    .await?;
-            }
+                Err(error) => format!("❌ {}. Пример: /sub 1 Купить молоко", error),
+            };
+            bot.send_message(msg.chat.id, reply)
+                .reply_markup(create_todo_menu())
+                .await?;
         }
-        Command::Done(task_number) => {
-            let task_index: usize = match task_number.parse::<usize>() {
-                Ok(num) if num > 0 => num - 1,
-                _ => {
-                    bot.send_message(msg.chat.id, "Пожалуйста, укажите корректный номер задачи. Пример: /done 1")
+        Command::Edit(args) => {
+            let (task_ref, text) = args.trim().split_once(char::is_whitespace).unwrap_or((args.trim(), ""));
+
+            let reply = match edit_task_text(&storage, msg.chat.id, task_ref, text).await {
+                Ok(confirmation) => confirmation,
+                Err(error) => format!("❌ {}. Пример: /edit 1 Купить кефир", error),
+            };
+            bot.send_message(msg.chat.id, reply)
+                .reply_markup(create_todo_menu())
+                .await?;
+        }
+        Command::Move(args) => {
+            let (task_ref, to) = args.trim().split_once(char::is_whitespace).unwrap_or((args.trim(), ""));
+
+            let reply = match move_task_to_position(&storage, msg.chat.id, task_ref, to).await {
+                Ok(confirmation) => confirmation,
+                Err(error) => format!("❌ {}. Пример: /move 3 1", error),
+            };
+            bot.send_message(msg.chat.id, reply)
+                .reply_markup(create_todo_menu())
+                .await?;
+        }
+        Command::Done(task_ref) => {
+            // Номер из /list сопоставляем с id сразу, дальше работаем только с id
+            let tasks = storage.get_current_tasks(msg.chat.id).await;
+            let task_id = match resolve_task(&tasks, &task_ref) {
+                Ok(task) => task.id.clone(),
+                Err(error) => {
+                    bot.send_message(msg.chat.id, format!("❌ {}. Пример: /done 1", error))
                         .reply_markup(create_todo_menu())
                         .await?;
                     return Ok(());
                 }
             };
 
-            match storage.mark_task_completed(msg.chat.id, task_index).await {
+            match complete_task_by_id(&storage, msg.chat.id, &task_id).await {
                 Ok(task_text) => {
                     bot.send_message(msg.chat.id, format!("✅ Задача \"{}\" отмечена как выполненная!", task_text))
                         .reply_markup(create_todo_menu())
@@ -125,21 +285,23 @@ pub async fn handle_command(
                 }
             }
         }
-        Command::Remove(task_number) => {
-            let task_index: usize = match task_number.parse::<usize>() {
-                Ok(num) if num > 0 => num - 1,
-                _ => {
-                    bot.send_message(msg.chat.id, "Пожалуйста, укажите корректный номер задачи. Пример: /remove 1")
+        Command::Remove(task_ref) => {
+            // Номер из /list сопоставляем с id сразу, дальше работаем только с id
+            let tasks = storage.get_current_tasks(msg.chat.id).await;
+            let task_id = match resolve_task(&tasks, &task_ref) {
+                Ok(task) => task.id.clone(),
+                Err(error) => {
+                    bot.send_message(msg.chat.id, format!("❌ {}. Пример: /remove 1", error))
                         .reply_markup(create_todo_menu())
                         .await?;
                     return Ok(());
                 }
             };
 
-            match storage.remove_task(msg.chat.id, task_index).await {
-                Ok(task_text) => {
-                    bot.send_message(msg.chat.id, format!("🗑️ Задача \"{}\" удалена", task_text))
-                        .reply_markup(create_todo_menu())
+            match remove_task_with_undo(&storage, &undo_journal, msg.chat.id, &task_id).await {
+                Ok(confirmation) => {
+                    bot.send_message(msg.chat.id, confirmation)
+                        .reply_markup(create_undo_keyboard())
                         .await?;
                 }
                 Err(error) => {
@@ -150,17 +312,199 @@ pub async fn handle_command(
             }
         }
         Command::Clear => {
-            if let Err(_) = storage.clear_tasks(msg.chat.id).await {
-                bot.send_message(msg.chat.id, "❌ Ошибка при очистке задач")
+            // Очистка выполняется только после подтверждения кнопкой
+            match clear_prompt(&storage, msg.chat.id).await {
+                Some(prompt) => {
+                    bot.send_message(msg.chat.id, prompt)
+                        .reply_markup(create_clear_confirm_keyboard())
+                        .await?;
+                }
+                None => {
+                    bot.send_message(msg.chat.id, "📝 У вас пока нет задач.")
+                        .reply_markup(create_todo_menu())
+                        .await?;
+                }
+            }
+        }
+        Command::Archive | Command::Trash => {
+            let (text, keyboard) = removed_tasks_view(&storage, msg.chat.id, matches!(command, Command::Trash)).await;
+            bot.send_message(msg.chat.id, text)
+                .reply_markup(keyboard)
+                .await?;
+        }
+        Command::Unarchive(ref task_ref) | Command::Restore(ref task_ref) => {
+            let trash = matches!(command, Command::Restore(_));
+            let text = match restore_removed_task(&storage, msg.chat.id, task_ref, trash).await {
+                Ok(confirmation) => confirmation,
+                Err(error) => format!("❌ {}. Номер — из {}", error, if trash { "/trash" } else { "/archive" }),
+            };
+            bot.send_message(msg.chat.id, text)
+                .reply_markup(create_todo_menu())
+                .await?;
+        }
+        Command::EmptyTrash => {
+            match empty_trash_prompt(&storage, msg.chat.id).await {
+                Some(prompt) => {
+                    bot.send_message(msg.chat.id, prompt)
+                        .reply_markup(create_empty_trash_confirm_keyboard())
+                        .await?;
+                }
+                None => {
+                    bot.send_message(msg.chat.id, "♻️ Корзина пуста.")
+                        .reply_markup(create_todo_menu())
+                        .await?;
+                }
+            }
+        }
+        Command::Undo => {
+            let text = match undo_last_removal(&storage, &undo_journal, msg.chat.id).await {
+                Ok(confirmation) => confirmation,
+                Err(error) => format!("❌ {}", error),
+            };
+            bot.send_message(msg.chat.id, text)
+                .reply_markup(create_todo_menu())
+                .await?;
+        }
+        Command::Search(query) => {
+            if query.trim().is_empty() {
+                bot.send_message(msg.chat.id, "Пожалуйста, укажите, что искать. Пример: /search молоко")
                     .reply_markup(create_todo_menu())
                     .await?;
                 return Ok(());
             }
-            
-            bot.send_message(msg.chat.id, "🧹 Все задачи очищены")
+
+            match search_view(&storage, msg.chat.id, &query).await {
+                Ok((text, keyboard)) => {
+                    bot.send_message(msg.chat.id, text)
+                        .reply_markup(keyboard)
+                        .await?;
+                }
+                Err(error) => {
+                    bot.send_message(msg.chat.id, format!("❌ {}", error))
+                        .reply_markup(create_todo_menu())
+                        .await?;
+                }
+            }
+        }
+        Command::Remind(args) => {
+            let (task_ref, when) = args.trim().split_once(char::is_whitespace).unwrap_or((args.trim(), ""));
+
+            match schedule_task_reminder(&storage, msg.chat.id, task_ref, when).await {
+                Ok(confirmation) => {
+                    bot.send_message(msg.chat.id, confirmation)
+                        .reply_markup(create_todo_menu())
+                        .await?;
+                }
+                Err(error) => {
+                    bot.send_message(msg.chat.id, format!("❌ {}. Пример: /remind 1 завтра 18:30", error))
+                        .reply_markup(create_todo_menu())
+                        .await?;
+                }
+            }
+        }
+        Command::Repeat(args) => {
+            let (task_ref, rule) = args.trim().split_once(char::is_whitespace).unwrap_or((args.trim(), ""));
+
+            match update_task_recurrence(&storage, msg.chat.id, task_ref, rule).await {
+                Ok(confirmation) => {
+                    bot.send_message(msg.chat.id, confirmation)
+                        .reply_markup(create_todo_menu())
+                        .await?;
+                }
+                Err(error) => {
+                    bot.send_message(msg.chat.id, format!("❌ {}. Пример: /repeat 1 по пн, чт", error))
+                        .reply_markup(create_todo_menu())
+                        .await?;
+                }
+            }
+        }
+        Command::Priority(args) => {
+            let (task_ref, level) = args.trim().split_once(char::is_whitespace).unwrap_or((args.trim(), ""));
+            let priority = match Priority::from_str(level) {
+                Some(priority) => priority,
+                None => {
+                    bot.send_message(msg.chat.id, "❌ Неизвестный приоритет. Пример: /priority 1 high (low, normal, high, urgent)")
+                        .reply_markup(create_todo_menu())
+                        .await?;
+                    return Ok(());
+                }
+            };
+
+            let tasks = storage.get_current_tasks(msg.chat.id).await;
+            let result = match resolve_task(&tasks, task_ref) {
+                Ok(task) => storage.set_task_priority(msg.chat.id, &task.id, priority).await,
+                Err(error) => Err(error),
+            };
+
+            let reply = match result {
+                Ok(task_text) => format!("✅ Приоритет задачи \"{}\": {}", task_text, priority.display_name()),
+                Err(error) => format!("❌ {}", error),
+            };
+            bot.send_message(msg.chat.id, reply)
+                .reply_markup(create_todo_menu())
+                .await?;
+        }
+        Command::Lists => {
+            let lists = storage.get_lists(msg.chat.id).await;
+            let current = storage.get_current_list(msg.chat.id).await.map(|list| list.id);
+
+            bot.send_message(msg.chat.id, "🗂 Ваши списки задач. Выберите текущий:")
+                .reply_markup(create_lists_keyboard(&lists, current.as_deref()))
+                .await?;
+        }
+        Command::NewList(name) => {
+            let reply = match create_task_list(&storage, msg.chat.id, &name).await {
+                Ok(confirmation) => confirmation,
+                Err(error) => format!("❌ {}. Пример: /newlist Работа", error),
+            };
+            bot.send_message(msg.chat.id, reply)
                 .reply_markup(create_todo_menu())
                 .await?;
         }
+        Command::RenameList(name) => {
+            let reply = match rename_current_list(&storage, msg.chat.id, &name).await {
+                Ok(confirmation) => confirmation,
+                Err(error) => format!("❌ {}. Пример: /renamelist Дом", error),
+            };
+            bot.send_message(msg.chat.id, reply)
+                .reply_markup(create_todo_menu())
+                .await?;
+        }
+        Command::DeleteList => {
+            let reply = match delete_current_list(&storage, msg.chat.id).await {
+                Ok(confirmation) => confirmation,
+                Err(error) => format!("❌ {}", error),
+            };
+            bot.send_message(msg.chat.id, reply)
+                .reply_markup(create_todo_menu())
+                .await?;
+        }
+        Command::Switch(name) => {
+            let reply = match switch_list(&storage, msg.chat.id, &name).await {
+                Ok(confirmation) => confirmation,
+                Err(error) => format!("❌ {}. Пример: /switch Работа", error),
+            };
+            bot.send_message(msg.chat.id, reply)
+                .reply_markup(create_todo_menu())
+                .await?;
+        }
+        Command::MoveTo(args) => {
+            let (task_ref, list_name) = args.trim().split_once(char::is_whitespace).unwrap_or((args.trim(), ""));
+
+            let reply = match move_task_to_named_list(&storage, msg.chat.id, task_ref, list_name).await {
+                Ok(confirmation) => confirmation,
+                Err(error) => format!("❌ {}. Пример: /moveto 1 Работа", error),
+            };
+            bot.send_message(msg.chat.id, reply)
+                .reply_markup(create_todo_menu())
+                .await?;
+        }
+        Command::Cancel => {
+            let reply = if state == UserState::Default { "🤷 Нечего отменять." } else { "❌ Ввод отменен." };
+            bot.send_message(msg.chat.id, reply)
+                .reply_markup(create_main_menu())
+                .await?;
+        }
         Command::TestReminders => {
             // Эта команда только для тестирования
             bot.send_message(msg.chat.id, "🧪 Команда для тестирования напоминаний доступна только разработчику")
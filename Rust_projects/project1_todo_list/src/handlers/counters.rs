@@ -0,0 +1,385 @@
+use chrono::{NaiveDate, TimeZone, Utc};
+use chrono_tz::Europe::Moscow;
+use teloxide::prelude::*;
+use teloxide::types::InlineKeyboardMarkup;
+
+use crate::models::{last_reading, month_of, tariff_on, CounterReminder, CounterType, MeterReading, MonthlyConsumption, Tariff};
+use crate::storage::SharedStorage;
+use crate::utils::{create_counters_menu, create_counter_keyboard, create_consumption_report_keyboard, parse_day_range, parse_counter_name, parse_new_counter, parse_zones, parse_reading_values, parse_tariff, format_amount, format_month, format_money, format_consumption, format_reading_date, format_reading_values, ReadingValidator, ValidationResult};
+
+
+/// Счетчики чата с периодами и кнопка на каждый
+pub(crate) async fn counters_view(storage: &SharedStorage, chat_id: ChatId) -> (String, InlineKeyboardMarkup) {
+    let user_reminders = storage.get_user_reminders(chat_id).await;
+    let counters = user_reminders.counters();
+
+    let text = if counters.is_empty() {
+        "🏠 Счетчиков пока нет.\nДобавьте первый кнопкой «➕ Новый счетчик»: газ, вода, отопление, домофон…".to_string()
+    } else {
+        let lines: Vec<String> = counters
+            .iter()
+            .map(|r| format!("{} — с {} по {} число", r.counter_type.display_name(), r.start_day, r.end_day))
+            .collect();
+        format!("🏠 Ваши счетчики:\n\n{}\n\nВыберите счетчик для настройки:", lines.join("\n"))
+    };
+    (text, create_counters_menu(&counters))
+}
+
+/// Карточка счетчика; `None`, если счетчик уже удален
+pub(crate) async fn counter_view(storage: &SharedStorage, chat_id: ChatId, counter_id: &str) -> Option<(String, InlineKeyboardMarkup)> {
+    let user_reminders = storage.get_user_reminders(chat_id).await;
+    let reminder = user_reminders.get_reminder(counter_id)?;
+
+    let status = if reminder.completed_this_month { "✅ показания за этот месяц отправлены" } else { "⏰ ждет показаний" };
+    let mut text = format!(
+        "{}\n📅 Период: с {} по {} число\n{}",
+        reminder.counter_type.display_name(),
+        reminder.start_day,
+        reminder.end_day,
+        status
+    );
+    if !reminder.counter_type.zones.is_empty() {
+        text.push_str(&format!("\n🔀 Зоны: {}", reminder.counter_type.zones.join(", ")));
+    }
+    let history = storage.get_meter_readings(chat_id, counter_id).await;
+    if let Some(last) = format_last_readings(&reminder.counter_type, &history) {
+        text.push_str(&format!("\n🔢 Последние показания {}", last));
+    }
+    let tariffs = storage.get_tariffs(chat_id, counter_id).await;
+    if let Some(current) = format_current_tariff(&reminder.counter_type, &tariffs) {
+        text.push_str(&format!("\n💰 Тариф: {}", current));
+    }
+    Some((text, create_counter_keyboard(counter_id)))
+}
+
+/// Цена тарифа с зоной: «ночь 2.51 ₽»
+fn format_tariff_price(tariff: &Tariff) -> String {
+    if tariff.zone.is_empty() {
+        format!("{} ₽", format_amount(tariff.price))
+    } else {
+        format!("{} {} ₽", tariff.zone, format_amount(tariff.price))
+    }
+}
+
+/// Действующие сегодня цены по зонам: «день 6.43 ₽, ночь 2.51 ₽»; `None`, если тариф не задан
+fn format_current_tariff(counter: &CounterType, tariffs: &[Tariff]) -> Option<String> {
+    let today = Moscow.from_utc_datetime(&Utc::now().naive_utc()).date_naive();
+    let prices: Vec<String> = counter
+        .reading_zones()
+        .into_iter()
+        .filter_map(|zone| {
+            tariff_on(tariffs, zone, today).map(format_tariff_price)
+        })
+        .collect();
+    if prices.is_empty() {
+        None
+    } else {
+        Some(prices.join(", "))
+    }
+}
+
+/// Последнее показание в каждой зоне счетчика
+fn last_readings<'a>(counter: &CounterType, history: &'a [MeterReading]) -> Vec<&'a MeterReading> {
+    counter
+        .reading_zones()
+        .into_iter()
+        .filter_map(|zone| last_reading(history, zone))
+        .collect()
+}
+
+/// Последние показания с датой самого свежего: «от 15.05.2024: день 1234, ночь 567»; `None`, если показаний нет
+fn format_last_readings(counter: &CounterType, history: &[MeterReading]) -> Option<String> {
+    let last = last_readings(counter, history);
+    let taken_at = last.iter().map(|r| r.taken_at).max()?;
+    Some(format!("от {}: {}", format_reading_date(taken_at), format_reading_values(&last)))
+}
+
+/// Подтверждение настройки периода счетчика
+fn counter_period_confirmation(reminder: &CounterReminder) -> String {
+    format!(
+        "✅ Напоминание для {} настроено!\n\n\
+        📅 Период: с {} по {} число каждого месяца\n\
+        🔔 Буду напоминать:\n\
+        • В первый день периода ({})\n\
+        • В середине периода\n\
+        • Каждый день за последние 3 дня\n\n\
+        Напоминания можно отключить в настройках.",
+        reminder.counter_type.display_name(),
+        reminder.start_day,
+        reminder.end_day,
+        reminder.start_day
+    )
+}
+
+/// Добавляет счетчик по вводу `🔥 Газ 20-25`. Возвращает текст подтверждения.
+pub(crate) async fn add_counter(storage: &SharedStorage, chat_id: ChatId, input: &str) -> Result<String, String> {
+    let reminder = parse_new_counter(input)?;
+    if let Some(existing) = storage.get_user_reminders(chat_id).await.find_by_name(&reminder.counter_type.name, None) {
+        return Err(format!("Счетчик «{}» уже есть", existing.counter_type.name));
+    }
+
+    let confirmation = counter_period_confirmation(&reminder);
+    storage
+        .add_counter_reminder(chat_id, reminder)
+        .await
+        .map_err(|_| "Ошибка при сохранении настроек".to_string())?;
+    Ok(confirmation)
+}
+
+/// Меняет период счетчика по вводу `16-25`. Возвращает текст подтверждения.
+pub(crate) async fn set_counter_period(storage: &SharedStorage, chat_id: ChatId, counter_id: &str, input: &str) -> Result<String, String> {
+    let (start_day, end_day) = parse_day_range(input)?;
+    let mut reminder = storage
+        .get_user_reminders(chat_id)
+        .await
+        .get_reminder(counter_id)
+        .cloned()
+        .ok_or_else(|| "Счетчик не найден".to_string())?;
+
+    reminder.start_day = start_day;
+    reminder.end_day = end_day;
+    let confirmation = counter_period_confirmation(&reminder);
+    storage
+        .add_counter_reminder(chat_id, reminder)
+        .await
+        .map_err(|_| "Ошибка при сохранении настроек".to_string())?;
+    Ok(confirmation)
+}
+
+/// Меняет название и эмодзи счетчика по вводу `🔥 Газ`. Возвращает текст подтверждения.
+pub(crate) async fn rename_counter(storage: &SharedStorage, chat_id: ChatId, counter_id: &str, input: &str) -> Result<String, String> {
+    let (emoji, name) = parse_counter_name(input)?;
+    let user_reminders = storage.get_user_reminders(chat_id).await;
+    if let Some(existing) = user_reminders.find_by_name(&name, Some(counter_id)) {
+        return Err(format!("Счетчик «{}» уже есть", existing.counter_type.name));
+    }
+    let mut reminder = user_reminders
+        .get_reminder(counter_id)
+        .cloned()
+        .ok_or_else(|| "Счетчик не найден".to_string())?;
+
+    let old_name = reminder.counter_type.display_name();
+    reminder.counter_type.name = name;
+    reminder.counter_type.emoji = emoji;
+    let confirmation = format!("✏️ Счетчик {} переименован в {}", old_name, reminder.counter_type.display_name());
+    storage
+        .add_counter_reminder(chat_id, reminder)
+        .await
+        .map_err(|_| "Ошибка при сохранении настроек".to_string())?;
+    Ok(confirmation)
+}
+
+/// Удаляет счетчик вместе с напоминаниями. Возвращает текст подтверждения.
+pub(crate) async fn delete_counter(storage: &SharedStorage, chat_id: ChatId, counter_id: &str) -> Result<String, String> {
+    let reminder = storage
+        .get_user_reminders(chat_id)
+        .await
+        .get_reminder(counter_id)
+        .cloned()
+        .ok_or_else(|| "Счетчик не найден".to_string())?;
+
+    storage
+        .delete_counter_reminder(chat_id, counter_id)
+        .await
+        .map_err(|_| "Ошибка при удалении счетчика".to_string())?;
+    Ok(format!("🗑️ Счетчик {} удален", reminder.counter_type.display_name()))
+}
+
+/// Вопрос о показаниях счетчика с подсказкой по зонам и прошлыми значениями; `None`, если счетчик удален
+pub(crate) async fn reading_prompt(storage: &SharedStorage, chat_id: ChatId, counter_id: &str) -> Option<String> {
+    let user_reminders = storage.get_user_reminders(chat_id).await;
+    let counter = &user_reminders.get_reminder(counter_id)?.counter_type;
+
+    let mut text = if counter.zones.is_empty() {
+        format!("🔢 Введите текущее показание счетчика {} (например, 12345.6):", counter.display_name())
+    } else {
+        format!(
+            "🔢 Введите показания счетчика {} через пробел в порядке зон: {}\nНапример: 12345.6 6789",
+            counter.display_name(),
+            counter.zones.join(", ")
+        )
+    };
+    let history = storage.get_meter_readings(chat_id, counter_id).await;
+    if let Some(last) = format_last_readings(counter, &history) {
+        text.push_str(&format!("\n\nПрошлые показания {}", last));
+    }
+    Some(text)
+}
+
+/// Сохраняет показания счетчика по вводу (`12345.6`, для зон — по числу на зону через пробел)
+/// и отмечает показания месяца отправленными. Каждое значение не меньше прошлого в своей зоне.
+/// Возвращает текст подтверждения с расходом с прошлых показаний.
+pub(crate) async fn submit_reading(storage: &SharedStorage, chat_id: ChatId, counter_id: &str, input: &str) -> Result<String, String> {
+    let reminder = storage
+        .get_user_reminders(chat_id)
+        .await
+        .get_reminder(counter_id)
+        .cloned()
+        .ok_or_else(|| "Счетчик не найден".to_string())?;
+    let counter = &reminder.counter_type;
+    let zones = counter.reading_zones();
+    let values = parse_reading_values(input, zones.len())?;
+    let history = storage.get_meter_readings(chat_id, counter_id).await;
+
+    let now = Utc::now();
+    let mut readings = Vec::new();
+    let mut consumption = Vec::new();
+    for (zone, value) in zones.into_iter().zip(values) {
+        let previous = last_reading(&history, zone);
+        if let ValidationResult::Invalid(error_msg) = ReadingValidator::validate_reading(value, previous.map(|r| r.value)) {
+            return Err(if zone.is_empty() { error_msg } else { format!("{}: {}", zone, error_msg) });
+        }
+        if let Some(previous) = previous {
+            let delta = format!("+{}", format_amount(value - previous.value));
+            consumption.push(if zone.is_empty() { delta } else { format!("{} {}", zone, delta) });
+        }
+        readings.push(MeterReading::new(counter_id.to_string(), zone.to_string(), value, now));
+    }
+
+    let mut text = format!(
+        "✅ Показания {} сохранены: {}",
+        counter.display_name(),
+        format_reading_values(&readings.iter().collect::<Vec<_>>())
+    );
+    if let Some(since) = last_readings(counter, &history).iter().map(|r| r.taken_at).max() {
+        text.push_str(&format!("\n📈 Расход с {}: {}", format_reading_date(since), consumption.join(", ")));
+    }
+    let tariffs = storage.get_tariffs(chat_id, counter_id).await;
+    let month = month_of(now);
+    let all_readings: Vec<MeterReading> = history.iter().chain(&readings).cloned().collect();
+    if let Some(consumption) = MonthlyConsumption::calculate(&counter.reading_zones(), &all_readings, &tariffs, month) {
+        match consumption.cost() {
+            Some(cost) => text.push_str(&format!("\n💰 За {}: {}", format_month(month), format_money(cost))),
+            None => text.push_str("\n💰 Задайте тариф в карточке счетчика, чтобы бот считал стоимость."),
+        }
+    }
+    text.push_str("\nНапоминания приостановлены до следующего месяца.");
+
+    storage
+        .add_meter_readings(chat_id, readings)
+        .await
+        .map_err(|_| "Ошибка при сохранении показаний".to_string())?;
+    if let Err(e) = storage.mark_counter_completed(chat_id, counter_id).await {
+        log::warn!("Failed to mark counter {} completed: {}", counter_id, e);
+    }
+    Ok(text)
+}
+
+/// Задает тарифные зоны счетчика по вводу `день, ночь` (`нет` — без зон). Возвращает текст подтверждения.
+pub(crate) async fn set_counter_zones(storage: &SharedStorage, chat_id: ChatId, counter_id: &str, input: &str) -> Result<String, String> {
+    let zones = parse_zones(input)?;
+    let mut reminder = storage
+        .get_user_reminders(chat_id)
+        .await
+        .get_reminder(counter_id)
+        .cloned()
+        .ok_or_else(|| "Счетчик не найден".to_string())?;
+
+    let confirmation = if zones.is_empty() {
+        format!("🔀 Счетчик {} теперь без тарифных зон", reminder.counter_type.display_name())
+    } else {
+        format!("🔀 Зоны счетчика {}: {}", reminder.counter_type.display_name(), zones.join(", "))
+    };
+    reminder.counter_type.zones = zones;
+    storage
+        .add_counter_reminder(chat_id, reminder)
+        .await
+        .map_err(|_| "Ошибка при сохранении настроек".to_string())?;
+    Ok(confirmation)
+}
+
+/// Вопрос о тарифе счетчика с примером по его зонам; `None`, если счетчик удален
+pub(crate) async fn tariff_prompt(storage: &SharedStorage, chat_id: ChatId, counter_id: &str) -> Option<String> {
+    let user_reminders = storage.get_user_reminders(chat_id).await;
+    let counter = &user_reminders.get_reminder(counter_id)?.counter_type;
+
+    let mut text = if counter.zones.is_empty() {
+        format!(
+            "💰 Введите цену за единицу для счетчика {} в рублях, например: 6.43\n\
+            С датой начала действия: 6.43 с 01.07.2025",
+            counter.display_name()
+        )
+    } else {
+        format!(
+            "💰 Введите цены за единицу для счетчика {} в рублях через пробел в порядке зон: {}\n\
+            Например: 6.43 2.51\nС датой начала действия: 6.43 2.51 с 01.07.2025",
+            counter.display_name(),
+            counter.zones.join(", ")
+        )
+    };
+    text.push_str("\n\nБез даты тариф действует с начала текущего месяца.");
+    let tariffs = storage.get_tariffs(chat_id, counter_id).await;
+    if let Some(current) = format_current_tariff(counter, &tariffs) {
+        text.push_str(&format!("\nСейчас: {}", current));
+    }
+    Some(text)
+}
+
+/// Сохраняет тариф счетчика по вводу `6.43 2.51 [с 01.07.2025]` (по цене на зону). Возвращает текст подтверждения.
+pub(crate) async fn set_counter_tariff(storage: &SharedStorage, chat_id: ChatId, counter_id: &str, input: &str) -> Result<String, String> {
+    let reminder = storage
+        .get_user_reminders(chat_id)
+        .await
+        .get_reminder(counter_id)
+        .cloned()
+        .ok_or_else(|| "Счетчик не найден".to_string())?;
+    let zones = reminder.counter_type.reading_zones();
+    let (prices, effective_from) = parse_tariff(input, zones.len(), Utc::now())?;
+
+    let tariffs: Vec<Tariff> = zones
+        .iter()
+        .zip(prices)
+        .map(|(zone, price)| Tariff::new(counter_id.to_string(), zone.to_string(), price, effective_from))
+        .collect();
+    storage
+        .set_tariffs(chat_id, tariffs.clone())
+        .await
+        .map_err(|_| "Ошибка при сохранении тарифа".to_string())?;
+
+    let prices: Vec<String> = tariffs.iter().map(format_tariff_price).collect();
+    Ok(format!(
+        "💰 Тариф счетчика {} с {}: {}",
+        reminder.counter_type.display_name(),
+        effective_from.format("%d.%m.%Y"),
+        prices.join(", ")
+    ))
+}
+
+/// Отчет о расходах и стоимости по всем счетчикам за месяц `month` (первое число месяца)
+pub(crate) async fn consumption_report_view(storage: &SharedStorage, chat_id: ChatId, month: NaiveDate) -> (String, InlineKeyboardMarkup) {
+    let user_reminders = storage.get_user_reminders(chat_id).await;
+    let counters = user_reminders.counters();
+    let mut text = format!("📊 Расходы за {}\n", format_month(month));
+    if counters.is_empty() {
+        text.push_str("\nСчетчиков пока нет.");
+        return (text, create_consumption_report_keyboard(month));
+    }
+
+    let mut total = 0.0;
+    let mut without_tariff = Vec::new();
+    for reminder in counters {
+        let counter = &reminder.counter_type;
+        let readings = storage.get_meter_readings(chat_id, &counter.id).await;
+        let tariffs = storage.get_tariffs(chat_id, &counter.id).await;
+        match MonthlyConsumption::calculate(&counter.reading_zones(), &readings, &tariffs, month) {
+            Some(consumption) => {
+                text.push_str(&format!("\n{}: {}", counter.display_name(), format_consumption(&consumption)));
+                match consumption.cost() {
+                    Some(cost) if consumption.zones.len() > 1 => {
+                        text.push_str(&format!(" → {}", format_money(cost)));
+                        total += cost;
+                    }
+                    Some(cost) => total += cost,
+                    None => without_tariff.push(counter.display_name()),
+                }
+            }
+            None => text.push_str(&format!("\n{}: нет показаний за этот и прошлый месяц", counter.display_name())),
+        }
+    }
+
+    text.push_str(&format!("\n\n💰 Итого: {}", format_money(total)));
+    if !without_tariff.is_empty() {
+        text.push_str(&format!("\nНе вошли в итог (нет тарифа): {}", without_tariff.join(", ")));
+    }
+    (text, create_consumption_report_keyboard(month))
+}
@@ -0,0 +1,474 @@
+use chrono::Utc;
+use teloxide::dispatching::UpdateHandler;
+use teloxide::prelude::*;
+
+use crate::models::{TodoItem, UserState, UndoJournal};
+use crate::storage::{DialogueStorage, SharedStorage};
+use crate::utils::{create_main_menu, create_todo_menu, create_cancel_keyboard, create_lists_keyboard, create_undo_keyboard, parse_task_list, resolve_task, extract_priority, extract_tags, extract_due_date, format_task_title, TaskValidator, ChatIdValidator, ValidationResult};
+use super::commands::{handle_command, Command};
+use super::callbacks::handle_callback;
+use super::counters::{add_counter, counters_view, rename_counter, set_counter_period, set_counter_tariff, set_counter_zones, submit_reading};
+use super::messages::{complete_task_by_id, create_task_list, edit_task_by_id, edit_task_text, remove_task_with_undo, rename_current_list, schedule_task_reminder, search_view, todo_from_list_line, update_task_recurrence};
+use super::{HandlerError, HandlerResult};
+
+/// Диалог с чатом: текущий шаг хранится в `DialogueStorage` и переживает перезапуск
+pub type BotDialogue = Dialogue<UserState, DialogueStorage>;
+
+/// Дерево обработчиков бота. Перед обработчиком загружается состояние диалога:
+/// команды и кнопки работают в любом состоянии, а обычный текст попадает
+/// в обработчик текущего шага. Новый шаг — вариант `UserState` и ветка `case!` здесь.
+pub fn schema() -> UpdateHandler<HandlerError> {
+    let steps = dptree::entry()
+        .branch(dptree::case![UserState::WaitingForTask].endpoint(receive_task))
+        .branch(dptree::case![UserState::WaitingForTaskList].endpoint(receive_task_list))
+        .branch(dptree::case![UserState::WaitingForTaskNumber].endpoint(receive_done_number))
+        .branch(dptree::case![UserState::WaitingForRemovalNumber].endpoint(receive_removal_number))
+        .branch(dptree::case![UserState::WaitingForTaskReminder].endpoint(receive_task_reminder))
+        .branch(dptree::case![UserState::WaitingForTaskRecurrence].endpoint(receive_task_recurrence))
+        .branch(dptree::case![UserState::WaitingForTaskEdit].endpoint(receive_task_edit))
+        .branch(dptree::case![UserState::WaitingForNewTaskText(task_id)].endpoint(receive_new_task_text))
+        .branch(dptree::case![UserState::WaitingForListName].endpoint(receive_list_name))
+        .branch(dptree::case![UserState::WaitingForListRename].endpoint(receive_list_rename))
+        .branch(dptree::case![UserState::WaitingForSearchQuery].endpoint(receive_search_query))
+        .branch(dptree::case![UserState::WaitingForNewCounter].endpoint(receive_new_counter))
+        .branch(dptree::case![UserState::WaitingForPeriod(counter_id)].endpoint(receive_period))
+        .branch(dptree::case![UserState::WaitingForCounterRename(counter_id)].endpoint(receive_counter_rename))
+        .branch(dptree::case![UserState::WaitingForCounterZones(counter_id)].endpoint(receive_counter_zones))
+        .branch(dptree::case![UserState::WaitingForReading(counter_id)].endpoint(receive_reading))
+        .branch(dptree::case![UserState::WaitingForTariff(counter_id)].endpoint(receive_tariff))
+        .branch(dptree::case![UserState::Default].endpoint(receive_unexpected_text));
+
+    let messages = Update::filter_message()
+        .enter_dialogue::<Message, DialogueStorage, UserState>()
+        .branch(dptree::entry().filter_command::<Command>().endpoint(handle_command))
+        .branch(
+            dptree::filter(|msg: Message| msg.text().is_some() && is_valid_chat(&msg))
+                .branch(dptree::filter_map(message_error).endpoint(reply_message_error))
+                .branch(steps),
+        );
+
+    let callbacks = Update::filter_callback_query()
+        .enter_dialogue::<CallbackQuery, DialogueStorage, UserState>()
+        .endpoint(handle_callback);
+
+    dptree::entry()
+        .branch(messages)
+        .branch(callbacks)
+}
+
+fn is_valid_chat(msg: &Message) -> bool {
+    match ChatIdValidator::validate_chat_id(msg.chat.id.0) {
+        ValidationResult::Valid => true,
+        ValidationResult::Invalid(error_msg) => {
+            log::warn!("Invalid chat ID: {} - {}", msg.chat.id.0, error_msg);
+            false
+        }
+    }
+}
+
+/// Текст ошибки, если сообщение не проходит общую проверку (длина, недопустимые символы)
+fn message_error(msg: Message) -> Option<String> {
+    let task_validator = match TaskValidator::new() {
+        Ok(validator) => validator,
+        Err(e) => {
+            log::error!("Failed to create task validator: {}", e);
+            return None;
+        }
+    };
+
+    match task_validator.validate_message(input(&msg)) {
+        ValidationResult::Valid => None,
+        ValidationResult::Invalid(error_msg) => Some(error_msg),
+    }
+}
+
+async fn reply_message_error(bot: Bot, msg: Message, error_msg: String) -> HandlerResult {
+    bot.send_message(msg.chat.id, format!("❌ {}", error_msg))
+        .reply_markup(create_todo_menu())
+        .await?;
+    Ok(())
+}
+
+fn input(msg: &Message) -> &str {
+    msg.text().unwrap_or_default().trim()
+}
+
+async fn receive_task(bot: Bot, msg: Message, dialogue: BotDialogue, storage: SharedStorage) -> HandlerResult {
+    let text = input(&msg);
+    if text.is_empty() {
+        bot.send_message(msg.chat.id, "❌ Пожалуйста, введите текст задачи:")
+            .await?;
+        return Ok(());
+    }
+
+    // Приоритет, теги и срок отделяем до валидации, чтобы они не считались текстом задачи
+    let (text, priority) = extract_priority(text);
+    let (text, tags) = extract_tags(&text);
+    let (task_text, due_at) = match extract_due_date(&text, Utc::now()) {
+        Ok(parsed) => parsed,
+        Err(error_msg) => {
+            bot.send_message(msg.chat.id, format!("❌ {}. Пример срока: @25.12 18:00 или @завтра", error_msg))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    // Валидация текста задачи
+    let task_validator = TaskValidator::new()?;
+    if let ValidationResult::Invalid(error_msg) = task_validator.validate_task_text(&task_text) {
+        bot.send_message(msg.chat.id, format!("❌ {}", error_msg))
+            .await?;
+        return Ok(());
+    }
+
+    // Санитизируем текст перед сохранением
+    let sanitized_text = task_validator.sanitize_task_text(&task_text);
+    let list_id = storage.get_current_list(msg.chat.id).await.map(|list| list.id);
+    let todo_item = TodoItem::new(sanitized_text)
+        .with_due(due_at)
+        .with_priority(priority)
+        .with_tags(tags)
+        .with_list(list_id);
+    let title = format_task_title(&todo_item);
+
+    if storage.add_task(msg.chat.id, todo_item).await.is_err() {
+        bot.send_message(msg.chat.id, "❌ Ошибка при добавлении задачи")
+            .reply_markup(create_todo_menu())
+            .await?;
+        return Ok(());
+    }
+
+    dialogue.exit().await?;
+    bot.send_message(msg.chat.id, format!("✅ Задача добавлена: {}", title))
+        .reply_markup(create_todo_menu())
+        .await?;
+    Ok(())
+}
+
+async fn receive_task_list(bot: Bot, msg: Message, dialogue: BotDialogue, storage: SharedStorage) -> HandlerResult {
+    let text = input(&msg);
+    if text.is_empty() {
+        bot.send_message(msg.chat.id, "❌ Пожалуйста, введите список задач:")
+            .await?;
+        return Ok(());
+    }
+
+    let tasks = parse_task_list(text);
+    if tasks.is_empty() {
+        bot.send_message(msg.chat.id, "❌ Не удалось распознать задачи. Попробуйте еще раз:")
+            .await?;
+        return Ok(());
+    }
+
+    let task_validator = TaskValidator::new()?;
+    let list_id = storage.get_current_list(msg.chat.id).await.map(|list| list.id);
+    let mut added_count = 0;
+    let mut valid_tasks = Vec::new();
+
+    // Валидируем каждую задачу; подзадачи добавляются только вместе с родителем
+    for task in &tasks {
+        let todo_item = match todo_from_list_line(&task_validator, &task.text) {
+            Ok(todo_item) => todo_item.with_list(list_id.clone()),
+            Err(error_msg) => {
+                log::warn!("Invalid task from user {}: {} - {}", msg.chat.id.0, task.text, error_msg);
+                continue;
+            }
+        };
+        let parent_id = todo_item.id.clone();
+        let title = format_task_title(&todo_item);
+        if storage.add_task(msg.chat.id, todo_item).await.is_err() {
+            continue;
+        }
+        added_count += 1;
+        valid_tasks.push(format!("{}. {}", added_count, title));
+
+        for child in &task.children {
+            match todo_from_list_line(&task_validator, child) {
+                Ok(child_item) => {
+                    let child_item = child_item.with_parent(Some(parent_id.clone())).with_list(list_id.clone());
+                    let title = format!("    • {}", format_task_title(&child_item));
+                    if storage.add_task(msg.chat.id, child_item).await.is_ok() {
+                        valid_tasks.push(title);
+                    }
+                }
+                Err(error_msg) => {
+                    log::warn!("Invalid subtask from user {}: {} - {}", msg.chat.id.0, child, error_msg);
+                }
+            }
+        }
+    }
+
+    dialogue.exit().await?;
+    if added_count > 0 {
+        bot.send_message(
+            msg.chat.id,
+            format!("✅ Добавлено {} задач:\n{}",
+                added_count,
+                valid_tasks.join("\n")
+            )
+        )
+        .reply_markup(create_todo_menu())
+        .await?;
+    } else {
+        bot.send_message(msg.chat.id, "❌ Ошибка при добавлении задач или все задачи содержат недопустимые символы")
+            .reply_markup(create_todo_menu())
+            .await?;
+    }
+    Ok(())
+}
+
+async fn receive_done_number(bot: Bot, msg: Message, dialogue: BotDialogue, storage: SharedStorage) -> HandlerResult {
+    // Номер сопоставляем с id задачи, чтобы не промахнуться при параллельных изменениях
+    let tasks = storage.get_current_tasks(msg.chat.id).await;
+    let task = match resolve_task(&tasks, input(&msg)) {
+        Ok(task) => task,
+        Err(error_msg) => {
+            bot.send_message(msg.chat.id, format!("❌ {}", error_msg))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    match complete_task_by_id(&storage, msg.chat.id, &task.id).await {
+        Ok(task_text) => {
+            dialogue.exit().await?;
+            bot.send_message(msg.chat.id, format!("✅ Задача \"{}\" отмечена как выполненная!", task_text))
+                .reply_markup(create_todo_menu())
+                .await?;
+        }
+        Err(error) => {
+            log::warn!("Failed to update task {} for {}: {}", task.id, msg.chat.id.0, error);
+            bot.send_message(msg.chat.id, "❌ Ошибка при обновлении задачи. Попробуйте еще раз:")
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+async fn receive_removal_number(
+    bot: Bot,
+    msg: Message,
+    dialogue: BotDialogue,
+    storage: SharedStorage,
+    undo_journal: UndoJournal,
+) -> HandlerResult {
+    // Номер сопоставляем с id задачи, чтобы не промахнуться при параллельных изменениях
+    let tasks = storage.get_current_tasks(msg.chat.id).await;
+    let task = match resolve_task(&tasks, input(&msg)) {
+        Ok(task) => task,
+        Err(error_msg) => {
+            bot.send_message(msg.chat.id, format!("❌ {}", error_msg))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    match remove_task_with_undo(&storage, &undo_journal, msg.chat.id, &task.id).await {
+        Ok(confirmation) => {
+            dialogue.exit().await?;
+            bot.send_message(msg.chat.id, confirmation)
+                .reply_markup(create_undo_keyboard())
+                .await?;
+        }
+        Err(error) => {
+            log::warn!("Failed to update task {} for {}: {}", task.id, msg.chat.id.0, error);
+            bot.send_message(msg.chat.id, "❌ Ошибка при удалении задачи. Попробуйте еще раз:")
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Шаги, где вводится номер задачи и параметр через пробел, заканчиваются одинаково:
+/// при успехе — подтверждение и меню, при ошибке — повторный ввод
+async fn finish_step(bot: &Bot, msg: &Message, dialogue: &BotDialogue, result: Result<String, String>) -> HandlerResult {
+    match result {
+        Ok(confirmation) => {
+            dialogue.exit().await?;
+            bot.send_message(msg.chat.id, confirmation)
+                .reply_markup(create_todo_menu())
+                .await?;
+        }
+        Err(error_msg) => {
+            bot.send_message(msg.chat.id, format!("❌ {}", error_msg))
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+async fn receive_task_reminder(bot: Bot, msg: Message, dialogue: BotDialogue, storage: SharedStorage) -> HandlerResult {
+    let text = input(&msg);
+    let (task_ref, when) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    let result = schedule_task_reminder(&storage, msg.chat.id, task_ref, when).await;
+    finish_step(&bot, &msg, &dialogue, result).await
+}
+
+async fn receive_task_recurrence(bot: Bot, msg: Message, dialogue: BotDialogue, storage: SharedStorage) -> HandlerResult {
+    let text = input(&msg);
+    let (task_ref, rule) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    let result = update_task_recurrence(&storage, msg.chat.id, task_ref, rule).await;
+    finish_step(&bot, &msg, &dialogue, result).await
+}
+
+async fn receive_task_edit(bot: Bot, msg: Message, dialogue: BotDialogue, storage: SharedStorage) -> HandlerResult {
+    let text = input(&msg);
+    let (task_ref, new_text) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    let result = edit_task_text(&storage, msg.chat.id, task_ref, new_text).await;
+    finish_step(&bot, &msg, &dialogue, result).await
+}
+
+async fn receive_new_task_text(
+    bot: Bot,
+    msg: Message,
+    dialogue: BotDialogue,
+    storage: SharedStorage,
+    task_id: String,
+) -> HandlerResult {
+    let result = edit_task_by_id(&storage, msg.chat.id, &task_id, input(&msg)).await;
+    finish_step(&bot, &msg, &dialogue, result).await
+}
+
+async fn receive_list_name(bot: Bot, msg: Message, dialogue: BotDialogue, storage: SharedStorage) -> HandlerResult {
+    let result = create_task_list(&storage, msg.chat.id, input(&msg)).await;
+    finish_list_step(&bot, &msg, &dialogue, &storage, result).await
+}
+
+async fn receive_list_rename(bot: Bot, msg: Message, dialogue: BotDialogue, storage: SharedStorage) -> HandlerResult {
+    let result = rename_current_list(&storage, msg.chat.id, input(&msg)).await;
+    finish_list_step(&bot, &msg, &dialogue, &storage, result).await
+}
+
+/// После создания или переименования списка показываются все списки
+async fn finish_list_step(
+    bot: &Bot,
+    msg: &Message,
+    dialogue: &BotDialogue,
+    storage: &SharedStorage,
+    result: Result<String, String>,
+) -> HandlerResult {
+    match result {
+        Ok(confirmation) => {
+            dialogue.exit().await?;
+            let lists = storage.get_lists(msg.chat.id).await;
+            let current = storage.get_current_list(msg.chat.id).await.map(|list| list.id);
+            bot.send_message(msg.chat.id, confirmation)
+                .reply_markup(create_lists_keyboard(&lists, current.as_deref()))
+                .await?;
+        }
+        Err(error_msg) => {
+            bot.send_message(msg.chat.id, format!("❌ {}. Введите другое название:", error_msg))
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+async fn receive_search_query(bot: Bot, msg: Message, dialogue: BotDialogue, storage: SharedStorage) -> HandlerResult {
+    match search_view(&storage, msg.chat.id, input(&msg)).await {
+        Ok((results, keyboard)) => {
+            dialogue.exit().await?;
+            bot.send_message(msg.chat.id, results)
+                .reply_markup(keyboard)
+                .await?;
+        }
+        Err(error_msg) => {
+            bot.send_message(msg.chat.id, format!("❌ {}. Введите другой запрос:", error_msg))
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+async fn receive_new_counter(bot: Bot, msg: Message, dialogue: BotDialogue, storage: SharedStorage) -> HandlerResult {
+    let result = add_counter(&storage, msg.chat.id, input(&msg)).await;
+    finish_counter_step(&bot, &msg, &dialogue, &storage, result).await
+}
+
+/// Период подачи показаний счетчика в формате «начало-конец»
+async fn receive_period(
+    bot: Bot,
+    msg: Message,
+    dialogue: BotDialogue,
+    storage: SharedStorage,
+    counter_id: String,
+) -> HandlerResult {
+    let result = set_counter_period(&storage, msg.chat.id, &counter_id, input(&msg)).await;
+    finish_counter_step(&bot, &msg, &dialogue, &storage, result).await
+}
+
+async fn receive_counter_rename(
+    bot: Bot,
+    msg: Message,
+    dialogue: BotDialogue,
+    storage: SharedStorage,
+    counter_id: String,
+) -> HandlerResult {
+    let result = rename_counter(&storage, msg.chat.id, &counter_id, input(&msg)).await;
+    finish_counter_step(&bot, &msg, &dialogue, &storage, result).await
+}
+
+async fn receive_counter_zones(
+    bot: Bot,
+    msg: Message,
+    dialogue: BotDialogue,
+    storage: SharedStorage,
+    counter_id: String,
+) -> HandlerResult {
+    let result = set_counter_zones(&storage, msg.chat.id, &counter_id, input(&msg)).await;
+    finish_counter_step(&bot, &msg, &dialogue, &storage, result).await
+}
+
+async fn receive_reading(
+    bot: Bot,
+    msg: Message,
+    dialogue: BotDialogue,
+    storage: SharedStorage,
+    counter_id: String,
+) -> HandlerResult {
+    let result = submit_reading(&storage, msg.chat.id, &counter_id, input(&msg)).await;
+    finish_counter_step(&bot, &msg, &dialogue, &storage, result).await
+}
+
+async fn receive_tariff(
+    bot: Bot,
+    msg: Message,
+    dialogue: BotDialogue,
+    storage: SharedStorage,
+    counter_id: String,
+) -> HandlerResult {
+    let result = set_counter_tariff(&storage, msg.chat.id, &counter_id, input(&msg)).await;
+    finish_counter_step(&bot, &msg, &dialogue, &storage, result).await
+}
+
+/// После изменения счетчика показываются все счетчики; при ошибке ввод можно повторить или отменить
+async fn finish_counter_step(
+    bot: &Bot,
+    msg: &Message,
+    dialogue: &BotDialogue,
+    storage: &SharedStorage,
+    result: Result<String, String>,
+) -> HandlerResult {
+    match result {
+        Ok(confirmation) => {
+            dialogue.exit().await?;
+            let (_, keyboard) = counters_view(storage, msg.chat.id).await;
+            bot.send_message(msg.chat.id, confirmation)
+                .reply_markup(keyboard)
+                .await?;
+        }
+        Err(error_msg) => {
+            bot.send_message(msg.chat.id, format!("❌ {}", error_msg))
+                .reply_markup(create_cancel_keyboard())
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+async fn receive_unexpected_text(bot: Bot, msg: Message) -> HandlerResult {
+    bot.send_message(msg.chat.id, "🤔 Не понимаю. Используйте кнопки меню или команды.")
+        .reply_markup(create_main_menu())
+        .await?;
+    Ok(())
+}
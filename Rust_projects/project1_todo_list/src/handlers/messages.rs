@@ -1,326 +1,519 @@
+use chrono::Utc;
 use teloxide::prelude::*;
+use teloxide::types::InlineKeyboardMarkup;
 
-use crate::models::{UserState, UserStates, CounterType, CounterReminder};
-use crate::storage::StorageType;
-use crate::utils::{create_main_menu, create_todo_menu, create_reminder_menu, parse_task_list, TaskValidator, TaskIndexValidator, DayValidator, ChatIdValidator, ValidationResult};
-
-pub async fn handle_text_message(
-    bot: Bot,
-    msg: Message,
-    storage: StorageType,
-    user_states: UserStates,
-) -> ResponseResult<()> {
-    let text = match msg.text() {
-        Some(text) => text.trim(),
-        None => return Ok(()),
-    };
+use crate::models::{TaskEdit, TodoItem, TaskReminder, UndoEntry, UndoJournal, MAIN_LIST_NAME, UNDO_WINDOW_MINUTES};
+use crate::storage::SharedStorage;
+use crate::utils::{create_todo_menu, create_removed_tasks_keyboard, create_search_results_keyboard, create_task_page_keyboard, create_task_order_keyboard, resolve_task, resolve_list, extract_priority, extract_tags, extract_due_date, parse_remind_at, parse_recurrence, format_due, format_task_page, task_page_number, format_removed_tasks, format_search_result, TaskPage, TaskSort, TaskValidator, ListNameValidator, SearchQueryValidator, ValidationResult};
+
+/// Разбирает строку из списка задач: приоритет, теги, срок и проверка текста
+pub(crate) fn todo_from_list_line(task_validator: &TaskValidator, line: &str) -> Result<TodoItem, String> {
+    let (line, priority) = extract_priority(line);
+    let (line, tags) = extract_tags(&line);
+    let (task_text, due_at) = extract_due_date(&line, Utc::now())?;
 
-    // Валидация Chat ID
-    if let ValidationResult::Invalid(error_msg) = ChatIdValidator::validate_chat_id(msg.chat.id.0) {
-        log::warn!("Invalid chat ID: {} - {}", msg.chat.id.0, error_msg);
-        return Ok(());
+    match task_validator.validate_task_text(&task_text) {
+        ValidationResult::Valid => Ok(TodoItem::new(task_validator.sanitize_task_text(&task_text))
+            .with_due(due_at)
+            .with_priority(priority)
+            .with_tags(tags)),
+        ValidationResult::Invalid(error_msg) => Err(error_msg),
     }
+}
 
-    // Создаем валидатор задач
-    let task_validator = match TaskValidator::new() {
-        Ok(validator) => validator,
-        Err(e) => {
-            log::error!("Failed to create task validator: {}", e);
-            return Ok(());
-        }
-    };
+/// Создает напоминание о задаче по вводу пользователя (номер/код задачи и время).
+/// Возвращает текст подтверждения.
+pub(crate) async fn schedule_task_reminder(
+    storage: &SharedStorage,
+    chat_id: ChatId,
+    task_ref: &str,
+    when: &str,
+) -> Result<String, String> {
+    let now = Utc::now();
+    let remind_at = parse_remind_at(when, now)?;
 
-    // Валидация сообщения
-    if let ValidationResult::Invalid(error_msg) = task_validator.validate_message(text) {
-        bot.send_message(msg.chat.id, format!("❌ {}", error_msg))
-            .reply_markup(create_todo_menu())
-            .await?;
-        return Ok(());
+    let tasks = storage.get_current_tasks(chat_id).await;
+    let task = resolve_task(&tasks, task_ref)?;
+    if task.is_completed() {
+        return Err("Задача уже выполнена".to_string());
     }
 
-    let current_state = {
-        let states = user_states.lock().await;
-        states.get(&msg.chat.id).cloned().unwrap_or_default()
+    storage
+        .add_task_reminder(TaskReminder::new(chat_id.0, task.id.clone(), remind_at))
+        .await
+        .map_err(|_| "Ошибка при сохранении напоминания".to_string())?;
+
+    Ok(format!("🔔 Напомню о задаче \"{}\" {}", task.text, format_due(remind_at, now)))
+}
+
+/// Задает или снимает правило повторения задачи по вводу пользователя.
+/// Возвращает текст подтверждения.
+pub(crate) async fn update_task_recurrence(
+    storage: &SharedStorage,
+    chat_id: ChatId,
+    task_ref: &str,
+    rule: &str,
+) -> Result<String, String> {
+    let recurrence = parse_recurrence(rule)?;
+
+    let tasks = storage.get_current_tasks(chat_id).await;
+    let task = resolve_task(&tasks, task_ref)?;
+    let confirmation = match &recurrence {
+        Some(recurrence) => format!("🔁 Задача \"{}\" будет повторяться {}", task.text, recurrence.describe()),
+        None => format!("🔁 Задача \"{}\" больше не повторяется", task.text),
     };
 
-    match current_state {
-        UserState::WaitingForTask => {
-            if text.is_empty() {
-                bot.send_message(msg.chat.id, "❌ Пожалуйста, введите текст задачи:")
-                    .await?;
-                return Ok(());
-            }
+    storage.set_task_recurrence(chat_id, &task.id, recurrence).await?;
+    Ok(confirmation)
+}
 
-            // Валидация текста задачи
-            match task_validator.validate_task_text(text) {
-                ValidationResult::Valid => {
-                    // Санитизируем текст перед сохранением
-                    let sanitized_text = task_validator.sanitize_task_text(text);
-                    
-                    if let Err(_) = storage.add_task(msg.chat.id, &sanitized_text).await {
-                        bot.send_message(msg.chat.id, "❌ Ошибка при добавлении задачи")
-                            .reply_markup(create_todo_menu())
-                            .await?;
-                        return Ok(());
-                    }
-                    
-                    // Сброс состояния
-                    {
-                        let mut states = user_states.lock().await;
-                        states.insert(msg.chat.id, UserState::Default);
-                    }
-
-                    bot.send_message(msg.chat.id, format!("✅ Задача добавлена: {}", sanitized_text))
-                        .reply_markup(create_todo_menu())
-                        .await?;
-                }
-                ValidationResult::Invalid(error_msg) => {
-                    bot.send_message(msg.chat.id, format!("❌ {}", error_msg))
-                        .await?;
-                    return Ok(());
-                }
-            }
-        }
-        UserState::WaitingForTaskList => {
-            if text.is_empty() {
-                bot.send_message(msg.chat.id, "❌ Пожалуйста, введите список задач:")
-                    .await?;
-                return Ok(());
-            }
+/// Меняет текст задачи по вводу пользователя (номер/код задачи и новый текст).
+/// Приоритет, теги и срок из нового текста заменяют прежние, неуказанные сохраняются.
+/// Возвращает текст подтверждения.
+pub(crate) async fn edit_task_text(
+    storage: &SharedStorage,
+    chat_id: ChatId,
+    task_ref: &str,
+    text: &str,
+) -> Result<String, String> {
+    let tasks = storage.get_current_tasks(chat_id).await;
+    let task = resolve_task(&tasks, task_ref)?;
+    edit_task_by_id(storage, chat_id, &task.id, text).await
+}
 
-            let tasks = parse_task_list(text);
-            if tasks.is_empty() {
-                bot.send_message(msg.chat.id, "❌ Не удалось распознать задачи. Попробуйте еще раз:")
-                    .await?;
-                return Ok(());
-            }
+/// Разбирает новый текст задачи так же, как при добавлении: приоритет, теги, срок и проверка текста
+fn task_edit_from_text(task_validator: &TaskValidator, text: &str) -> Result<TaskEdit, String> {
+    let (rest, priority) = extract_priority(text);
+    let priority_given = rest != text.trim();
+    let (rest, tags) = extract_tags(&rest);
+    let (task_text, due_at) = extract_due_date(&rest, Utc::now())?;
 
-            let mut added_count = 0;
-            let mut valid_tasks = Vec::new();
-            
-            // Валидируем каждую задачу
-            for task in &tasks {
-                match task_validator.validate_task_text(task) {
-                    ValidationResult::Valid => {
-                        let sanitized_task = task_validator.sanitize_task_text(task);
-                        if let Ok(_) = storage.add_task(msg.chat.id, &sanitized_task).await {
-                            added_count += 1;
-                            valid_tasks.push(sanitized_task);
-                        }
-                    }
-                    ValidationResult::Invalid(error_msg) => {
-                        log::warn!("Invalid task from user {}: {} - {}", msg.chat.id.0, task, error_msg);
-                    }
-                }
-            }
+    if let ValidationResult::Invalid(error_msg) = task_validator.validate_task_text(&task_text) {
+        return Err(error_msg);
+    }
+    Ok(TaskEdit {
+        text: task_validator.sanitize_task_text(&task_text),
+        priority: priority_given.then_some(priority),
+        tags: (!tags.is_empty()).then_some(tags),
+        due_at,
+    })
+}
 
-            // Сброс состояния
-            {
-                let mut states = user_states.lock().await;
-                states.insert(msg.chat.id, UserState::Default);
-            }
+/// Меняет текст задачи по id. Возвращает текст подтверждения.
+pub(crate) async fn edit_task_by_id(storage: &SharedStorage, chat_id: ChatId, task_id: &str, text: &str) -> Result<String, String> {
+    let task_validator = TaskValidator::new().map_err(|_| "Ошибка проверки текста".to_string())?;
+    let edit = task_edit_from_text(&task_validator, text)?;
 
-            if added_count > 0 {
-                bot.send_message(
-                    msg.chat.id, 
-                    format!("✅ Добавлено {} задач:\n{}", 
-                        added_count, 
-                        valid_tasks.iter().enumerate()
-                            .map(|(i, task)| format!("{}. {}", i + 1, task))
-                            .collect::<Vec<_>>()
-                            .join("\n")
-                    )
-                )
-                .reply_markup(create_todo_menu())
-                .await?;
-            } else {
-                bot.send_message(msg.chat.id, "❌ Ошибка при добавлении задач или все задачи содержат недопустимые символы")
-                    .reply_markup(create_todo_menu())
-                    .await?;
-            }
-        }
-        UserState::WaitingForTaskNumber => {
-            match text.parse::<usize>() {
-                Ok(num) if num > 0 => {
-                    let task_index = num - 1;
-                    
-                    // Получаем список задач для валидации индекса
-                    let tasks = storage.get_tasks(msg.chat.id).await;
-                    match TaskIndexValidator::validate_task_index(task_index, tasks.len()) {
-                        ValidationResult::Valid => {
-                            match storage.mark_task_completed(msg.chat.id, task_index).await {
-                                Ok(task_text) => {
-                                    // Сброс состояния
-                                    {
-                                        let mut states = user_states.lock().await;
-                                        states.insert(msg.chat.id, UserState::Default);
-                                    }
-
-                                    bot.send_message(msg.chat.id, format!("✅ Задача \"{}\" отмечена как выполненная!", task_text))
-                                        .reply_markup(create_todo_menu())
-                                        .await?;
-                                }
-                                Err(_) => {
-                                    bot.send_message(msg.chat.id, "❌ Ошибка при обновлении задачи. Попробуйте еще раз:")
-                                        .await?;
-                                }
-                            }
-                        }
-                        ValidationResult::Invalid(error_msg) => {
-                            bot.send_message(msg.chat.id, format!("❌ {}", error_msg))
-                                .await?;
-                        }
-                    }
-                }
-                _ => {
-                    bot.send_message(msg.chat.id, "❌ Пожалуйста, введите корректный номер задачи:")
-                        .await?;
-                }
-            }
-        }
-        UserState::WaitingForRemovalNumber => {
-            match text.parse::<usize>() {
-                Ok(num) if num > 0 => {
-                    let task_index = num - 1;
-                    
-                    // Получаем список задач для валидации индекса
-                    let tasks = storage.get_tasks(msg.chat.id).await;
-                    match TaskIndexValidator::validate_task_index(task_index, tasks.len()) {
-                        ValidationResult::Valid => {
-                            match storage.remove_task(msg.chat.id, task_index).await {
-                                Ok(task_text) => {
-                                    {
-                                        let mut states = user_states.lock().await;
-                                        states.insert(msg.chat.id, UserState::Default);
-                                    }
-                                    
-                                    bot.send_message(msg.chat.id, format!("🗑️ Задача \"{}\" удалена", task_text))
-                                        .reply_markup(create_todo_menu())
-                                        .await?;
-                                }
-                                Err(_) => {
-                                    bot.send_message(msg.chat.id, "❌ Ошибка при удалении задачи. Попробуйте еще раз:")
-                                        .await?;
-                                }
-                            }
-                        }
-                        ValidationResult::Invalid(error_msg) => {
-                            bot.send_message(msg.chat.id, format!("❌ {}", error_msg))
-                                .await?;
-                        }
-                    }
-                }
-                _ => {
-                    bot.send_message(msg.chat.id, "❌ Пожалуйста, введите корректный номер задачи:")
-                        .await?;
-                }
-            }
-        }
-        UserState::WaitingForWaterPeriod => {
-            handle_period_input(bot, msg.chat.id, storage, user_states, text, CounterType::Water).await?;
-        }
-        UserState::WaitingForElectricityPeriod => {
-            handle_period_input(bot, msg.chat.id, storage, user_states, text, CounterType::Electricity).await?;
-        }
-        UserState::Default => {
-            bot.send_message(msg.chat.id, "🤔 Не понимаю. Используйте кнопки меню или команды.")
-                .reply_markup(create_main_menu())
-                .await?;
-        }
+    let old_text = storage.edit_task(chat_id, task_id, &edit).await?;
+    Ok(format!("✏️ Задача \"{}\" изменена: {}", old_text, edit.text))
+}
+
+/// Отмечает задачу выполненной и снимает её напоминания. Возвращает текст задачи.
+pub(crate) async fn complete_task_by_id(storage: &SharedStorage, chat_id: ChatId, task_id: &str) -> Result<String, String> {
+    let task_text = storage.mark_task_completed(chat_id, task_id).await?;
+    if let Err(e) = storage.remove_task_reminders(chat_id, task_id).await {
+        log::warn!("Failed to remove reminders for task {}: {}", task_id, e);
     }
+    Ok(task_text)
+}
+
+/// Переносит задачу текущего списка на место с номером `to` из `/list`.
+/// Возвращает текст подтверждения.
+pub(crate) async fn move_task_to_position(
+    storage: &SharedStorage,
+    chat_id: ChatId,
+    task_ref: &str,
+    to: &str,
+) -> Result<String, String> {
+    let tasks = storage.get_current_tasks(chat_id).await;
+    let task = resolve_task(&tasks, task_ref)?;
+    let to = match to.trim().parse::<usize>() {
+        Ok(to) if (1..=tasks.len()).contains(&to) => to,
+        _ => return Err(format!("Укажите новое место от 1 до {}", tasks.len())),
+    };
 
-    Ok(())
+    let mut task_ids: Vec<String> = tasks.iter().filter(|t| t.id != task.id).map(|t| t.id.clone()).collect();
+    task_ids.insert(to - 1, task.id.clone());
+    storage.reorder_tasks(chat_id, &task_ids).await?;
+    Ok(format!("↕️ Задача \"{}\" перемещена на место {}", task.text, to))
 }
 
-async fn handle_period_input(
-    bot: Bot,
+/// Меняет задачу местами с соседней выше или ниже; подзадачи двигаются только среди пунктов своего родителя.
+/// Возвращает текст задачи.
+pub(crate) async fn shift_task(storage: &SharedStorage, chat_id: ChatId, task_id: &str, up: bool) -> Result<String, String> {
+    let tasks = storage.get_current_tasks(chat_id).await;
+    let task = tasks.iter().find(|t| t.id == task_id).ok_or_else(|| "Задача не найдена".to_string())?;
+    let siblings: Vec<&TodoItem> = tasks.iter().filter(|t| t.parent_id == task.parent_id).collect();
+    let index = siblings.iter().position(|t| t.id == task_id).unwrap_or_default();
+
+    let neighbor = if up { index.checked_sub(1) } else { Some(index + 1) }
+        .and_then(|i| siblings.get(i))
+        .ok_or_else(|| if up { "Задача уже первая" } else { "Задача уже последняя" }.to_string())?;
+    // Две задачи меняются местами: первой в новом порядке идет та, что должна оказаться выше
+    let task_ids = if up {
+        [task.id.clone(), neighbor.id.clone()]
+    } else {
+        [neighbor.id.clone(), task.id.clone()]
+    };
+
+    storage.reorder_tasks(chat_id, &task_ids).await?;
+    Ok(task.text.clone())
+}
+
+/// Запоминает удаленные задачи, чтобы последнее удаление можно было отменить
+async fn record_undo(undo_journal: &UndoJournal, chat_id: ChatId, description: String, tasks: Vec<TodoItem>) {
+    let mut journal = undo_journal.lock().await;
+    let now = Utc::now();
+    journal.retain(|_, entry| !entry.is_expired(now));
+    journal.insert(chat_id, UndoEntry::new(description, tasks));
+}
+
+/// Удаляет задачу с подзадачами и запоминает их для отмены. Возвращает текст подтверждения.
+pub(crate) async fn remove_task_with_undo(
+    storage: &SharedStorage,
+    undo_journal: &UndoJournal,
     chat_id: ChatId,
-    storage: StorageType,
-    user_states: UserStates,
-    text: &str,
-    counter_type: CounterType,
-) -> ResponseResult<()> {
-    // Парсим период в формате "начало-конец"
-    let parts: Vec<&str> = text.split('-').collect();
-    if parts.len() != 2 {
-        bot.send_message(
-            chat_id,
-            "❌ Неверный формат. Используйте формат: **начало-конец**\n\
-            Например: `16-25` или `1-10`"
-        ).await?;
-        return Ok(());
+    task_id: &str,
+) -> Result<String, String> {
+    let removed: Vec<TodoItem> = storage
+        .get_tasks(chat_id)
+        .await
+        .into_iter()
+        .filter(|t| t.id == task_id || t.parent_id.as_deref() == Some(task_id))
+        .collect();
+
+    let task_text = storage.remove_task(chat_id, task_id).await?;
+    record_undo(undo_journal, chat_id, format!("удаление задачи \"{}\"", task_text), removed).await;
+    Ok(format!("🗑️ Задача \"{}\" перенесена в корзину. Отменить можно в течение {} мин.", task_text, UNDO_WINDOW_MINUTES))
+}
+
+/// Вопрос перед очисткой текущего списка; `None`, если очищать нечего
+pub(crate) async fn clear_prompt(storage: &SharedStorage, chat_id: ChatId) -> Option<String> {
+    let count = storage.get_current_tasks(chat_id).await.len();
+    if count == 0 {
+        return None;
     }
+    let list_name = storage
+        .get_current_list(chat_id)
+        .await
+        .map(|list| list.name)
+        .unwrap_or_else(|| MAIN_LIST_NAME.to_string());
+    Some(format!("🧹 Удалить все задачи списка «{}» (всего {})?", list_name, count))
+}
 
-    let start_day: u32 = match parts[0].trim().parse() {
-        Ok(day) => day,
-        _ => {
-            bot.send_message(
-                chat_id,
-                "❌ Неверный формат дня начала. Укажите число от 1 до 31."
-            ).await?;
-            return Ok(());
-        }
+/// Очищает текущий список и запоминает задачи для отмены. Возвращает текст подтверждения.
+pub(crate) async fn clear_current_list(storage: &SharedStorage, undo_journal: &UndoJournal, chat_id: ChatId) -> Result<String, String> {
+    let list_name = storage
+        .get_current_list(chat_id)
+        .await
+        .map(|list| list.name)
+        .unwrap_or_else(|| MAIN_LIST_NAME.to_string());
+    let removed = storage.get_current_tasks(chat_id).await;
+    storage
+        .clear_tasks(chat_id)
+        .await
+        .map_err(|_| "Ошибка при очистке задач".to_string())?;
+
+    record_undo(undo_journal, chat_id, format!("очистка списка «{}», задач: {}", list_name, removed.len()), removed).await;
+    Ok(format!("🧹 Задачи текущего списка перенесены в корзину. Отменить можно в течение {} мин.", UNDO_WINDOW_MINUTES))
+}
+
+/// Возвращает задачи последнего удаления, если окно отмены еще не закрылось. Возвращает текст подтверждения.
+pub(crate) async fn undo_last_removal(storage: &SharedStorage, undo_journal: &UndoJournal, chat_id: ChatId) -> Result<String, String> {
+    let entry = undo_journal
+        .lock()
+        .await
+        .remove(&chat_id)
+        .filter(|entry| !entry.is_expired(Utc::now()))
+        .ok_or_else(|| "Нечего отменять".to_string())?;
+
+    // Список, из которого удаляли, мог быть удален с тех пор: тогда задачи вернутся в основной.
+    // Подзадача, родителя которой удалили позже, не восстанавливается.
+    let lists = storage.get_lists(chat_id).await;
+    let existing = storage.get_tasks(chat_id).await;
+    let has_task = |id: &str| existing.iter().chain(&entry.tasks).any(|t| t.id == id);
+    let tasks: Vec<TodoItem> = entry
+        .tasks
+        .iter()
+        .filter(|task| task.parent_id.as_deref().is_none_or(has_task))
+        .cloned()
+        .map(|mut task| {
+            if task.list_id.as_ref().is_some_and(|id| !lists.iter().any(|l| &l.id == id)) {
+                task.list_id = None;
+            }
+            task
+        })
+        .collect();
+
+    if let Err(e) = storage.restore_tasks(chat_id, tasks).await {
+        log::error!("Failed to restore tasks for {}: {}", chat_id.0, e);
+        // Запись возвращается в журнал, чтобы отмену можно было повторить
+        undo_journal.lock().await.insert(chat_id, entry);
+        return Err("Ошибка при восстановлении задач".to_string());
+    }
+    Ok(format!("↩️ Отменено: {}", entry.description))
+}
+
+/// Сколько задач архива или корзины показывать в одном сообщении
+const REMOVED_TASKS_LIMIT: usize = 30;
+
+/// Архив (`trash = false`) или корзина чата: текст и кнопки восстановления
+pub(crate) async fn removed_tasks_view(storage: &SharedStorage, chat_id: ChatId, trash: bool) -> (String, InlineKeyboardMarkup) {
+    let mut todos = if trash {
+        storage.get_trashed_tasks(chat_id).await
+    } else {
+        storage.get_archived_tasks(chat_id).await
+    };
+    if todos.is_empty() {
+        let text = if trash { "♻️ Корзина пуста." } else { "📦 В архиве пока нет задач." };
+        return (text.to_string(), create_removed_tasks_keyboard(&todos, trash));
+    }
+
+    let total = todos.len();
+    todos.truncate(REMOVED_TASKS_LIMIT);
+    let mut text = if trash {
+        "♻️ Корзина — удаленные задачи, новые сверху:\n\n".to_string()
+    } else {
+        "📦 Архив — выполненные задачи, новые сверху:\n\n".to_string()
+    };
+    text.push_str(&format_removed_tasks(&todos));
+    if total > todos.len() {
+        text.push_str(&format!("\n…и еще {}", total - todos.len()));
+    }
+    (text, create_removed_tasks_keyboard(&todos, trash))
+}
+
+/// Возвращает задачу по номеру или коду из корзины (`trash = true`) или архива.
+/// Возвращает текст подтверждения.
+pub(crate) async fn restore_removed_task(storage: &SharedStorage, chat_id: ChatId, task_ref: &str, trash: bool) -> Result<String, String> {
+    let todos = if trash {
+        storage.get_trashed_tasks(chat_id).await
+    } else {
+        storage.get_archived_tasks(chat_id).await
     };
+    let task = resolve_task(&todos, task_ref)?;
+    restore_task_by_id(storage, chat_id, &task.id).await
+}
+
+/// Возвращает задачу из архива или корзины по id. Возвращает текст подтверждения.
+pub(crate) async fn restore_task_by_id(storage: &SharedStorage, chat_id: ChatId, task_id: &str) -> Result<String, String> {
+    let task_text = storage.restore_task(chat_id, task_id).await?;
+    Ok(format!("♻️ Задача \"{}\" возвращена в список", task_text))
+}
+
+/// Вопрос перед окончательной очисткой корзины; `None`, если она пуста
+pub(crate) async fn empty_trash_prompt(storage: &SharedStorage, chat_id: ChatId) -> Option<String> {
+    let count = storage.get_trashed_tasks(chat_id).await.len();
+    (count > 0).then(|| format!("🔥 Удалить задачи из корзины навсегда (всего {})? Вернуть их будет нельзя.", count))
+}
+
+/// Сколько найденных задач показывать в одном сообщении
+const SEARCH_RESULTS_LIMIT: usize = 20;
 
-    let end_day: u32 = match parts[1].trim().parse() {
-        Ok(day) => day,
+/// Ищет задачи во всех списках и архиве: текст с местом каждой задачи (список и номер в нем
+/// или номер в архиве) и кнопки действий. Ошибка — если запрос не прошел проверку.
+pub(crate) async fn search_view(storage: &SharedStorage, chat_id: ChatId, query: &str) -> Result<(String, InlineKeyboardMarkup), String> {
+    if let ValidationResult::Invalid(error_msg) = SearchQueryValidator::validate_search_query(query) {
+        return Err(error_msg);
+    }
+    let query = query.trim();
+    let mut found = storage.search_tasks(chat_id, query).await;
+    if found.is_empty() {
+        return Ok((format!("🔍 По запросу «{}» ничего не найдено", query), create_search_results_keyboard(&found)));
+    }
+
+    let total = found.len();
+    found.truncate(SEARCH_RESULTS_LIMIT);
+    let tasks = storage.get_tasks(chat_id).await;
+    let archived = storage.get_archived_tasks(chat_id).await;
+    let lists = storage.get_lists(chat_id).await;
+
+    let mut text = format!("🔍 Найдено по запросу «{}»: {}\n\n", query, total);
+    for (index, todo) in found.iter().enumerate() {
+        let location = if todo.is_active() {
+            let list_name = todo
+                .list_id
+                .as_ref()
+                .and_then(|id| lists.iter().find(|l| &l.id == id))
+                .map_or(MAIN_LIST_NAME, |l| l.name.as_str());
+            // Номер — тот же, что в `/list` этого списка
+            let number = tasks
+                .iter()
+                .filter(|t| t.list_id == todo.list_id)
+                .position(|t| t.id == todo.id)
+                .unwrap_or_default() + 1;
+            format!("🗂 {}, №{}", list_name, number)
+        } else {
+            let number = archived.iter().position(|t| t.id == todo.id).unwrap_or_default() + 1;
+            format!("📦 архив, №{}", number)
+        };
+        text.push_str(&format_search_result(index, todo, &location));
+        text.push('\n');
+    }
+    if total > found.len() {
+        text.push_str(&format!("\n…и еще {}. Уточните запрос", total - found.len()));
+    }
+    Ok((text, create_search_results_keyboard(&found)))
+}
+
+/// Название текущего списка
+async fn current_list_name(storage: &SharedStorage, chat_id: ChatId) -> String {
+    storage
+        .get_current_list(chat_id)
+        .await
+        .map(|list| list.name)
+        .unwrap_or_else(|| MAIN_LIST_NAME.to_string())
+}
+
+/// Текст страницы списка с названием списка в заголовке и номером страницы
+fn task_page_text(list_name: &str, task_page: &TaskPage) -> String {
+    let mut text = format!("🗂 Список «{}»\n\n{}", list_name, task_page.text);
+    if task_page.pages > 1 {
+        text.push_str(&format!("\nСтраница {} из {}", task_page.page + 1, task_page.pages));
+    }
+    text
+}
+
+/// Страница текущего списка с названием списка в заголовке, кнопками задач и перелистыванием
+pub(crate) async fn task_list_view(
+    storage: &SharedStorage,
+    chat_id: ChatId,
+    sort: TaskSort,
+    tag: Option<&str>,
+    page: usize,
+) -> (String, InlineKeyboardMarkup) {
+    let list_name = current_list_name(storage, chat_id).await;
+    let todos = storage.get_current_tasks(chat_id).await;
+
+    let body = match tag {
+        _ if todos.is_empty() => "📝 У вас пока нет задач.".to_string(),
+        Some(tag) if !todos.iter().any(|t| t.has_tag(tag)) => format!("🏷 Нет задач с тегом #{}", tag),
         _ => {
-            bot.send_message(
-                chat_id,
-                "❌ Неверный формат дня окончания. Укажите число от 1 до 31."
-            ).await?;
-            return Ok(());
+            let task_page = format_task_page(&todos, sort, tag, page);
+            let keyboard = create_task_page_keyboard(&task_page.tasks, task_page.page, task_page.pages, sort, tag);
+            return (task_page_text(&list_name, &task_page), keyboard);
         }
     };
+    (format!("🗂 Список «{}»\n\n{}", list_name, body), create_todo_menu())
+}
 
-    // Валидация дней с использованием DayValidator
-    match DayValidator::validate_day_range(start_day, end_day) {
-        ValidationResult::Valid => {
-            // Продолжаем обработку
-        }
-        ValidationResult::Invalid(error_msg) => {
-            bot.send_message(chat_id, format!("❌ {}", error_msg)).await?;
-            return Ok(());
-        }
+/// Страница текущего списка с задачей `task_id` и кнопками её перемещения.
+/// Если задачи в списке уже нет, показывается страница `page`
+pub(crate) async fn task_order_view(storage: &SharedStorage, chat_id: ChatId, task_id: &str, page: usize) -> (String, InlineKeyboardMarkup) {
+    let list_name = current_list_name(storage, chat_id).await;
+    let todos = storage.get_current_tasks(chat_id).await;
+    if todos.is_empty() {
+        return (format!("🗂 Список «{}»\n\n📝 У вас пока нет задач.", list_name), create_todo_menu());
     }
 
-    // Создаем напоминание
-    let reminder = CounterReminder::new(counter_type.clone(), start_day, end_day);
-    
-    // Сохраняем
-    match storage.add_counter_reminder(chat_id, reminder).await {
-        Ok(_) => {
-            // Сброс состояния
-            {
-                let mut states = user_states.lock().await;
-                states.insert(chat_id, UserState::Default);
-            }
+    let page = task_page_number(&todos, TaskSort::Added, None, task_id).unwrap_or(page);
+    let task_page = format_task_page(&todos, TaskSort::Added, None, page);
+    (task_page_text(&list_name, &task_page), create_task_order_keyboard(task_id, task_page.page))
+}
 
-            bot.send_message(
-                chat_id,
-                format!(
-                    "✅ Напоминание для {} настроено!\n\n\
-                    📅 Период: с {} по {} число каждого месяца\n\
-                    🔔 Буду напоминать:\n\
-                    • В первый день периода ({})\n\
-                    • В середине периода\n\
-                    • Каждый день за последние 3 дня\n\n\
-                    Напоминания можно отключить в настройках.",
-                    counter_type.display_name(),
-                    start_day,
-                    end_day,
-                    start_day
-                )
-            )
-            .reply_markup(create_reminder_menu())
-            .await?;
-        }
-        Err(_) => {
-            bot.send_message(chat_id, "❌ Ошибка при сохранении настроек")
-                .reply_markup(create_reminder_menu())
-                .await?;
-        }
+/// Проверяет название нового или переименованного списка
+fn validate_list_name(name: &str) -> Result<&str, String> {
+    if let ValidationResult::Invalid(error_msg) = ListNameValidator::validate_list_name(name) {
+        return Err(error_msg);
+    }
+    let name = name.trim();
+    if name.to_lowercase() == MAIN_LIST_NAME.to_lowercase() {
+        return Err("Это название занято основным списком".to_string());
+    }
+    Ok(name)
+}
+
+/// Создает список и делает его текущим. Возвращает текст подтверждения.
+pub(crate) async fn create_task_list(storage: &SharedStorage, chat_id: ChatId, name: &str) -> Result<String, String> {
+    let list = storage.create_list(chat_id, validate_list_name(name)?).await?;
+    storage.set_current_list(chat_id, Some(&list.id)).await?;
+    Ok(format!("🗂 Список «{}» создан, новые задачи попадут в него", list.name))
+}
+
+/// Переименовывает текущий список. Возвращает текст подтверждения.
+pub(crate) async fn rename_current_list(storage: &SharedStorage, chat_id: ChatId, name: &str) -> Result<String, String> {
+    let name = validate_list_name(name)?;
+    let list = storage
+        .get_current_list(chat_id)
+        .await
+        .ok_or_else(|| "Основной список нельзя переименовать".to_string())?;
+
+    let old_name = storage.rename_list(chat_id, &list.id, name).await?;
+    Ok(format!("✏️ Список «{}» переименован в «{}»", old_name, name))
+}
+
+/// Удаляет текущий список, его задачи переходят в основной. Возвращает текст подтверждения.
+pub(crate) async fn delete_current_list(storage: &SharedStorage, chat_id: ChatId) -> Result<String, String> {
+    let list = storage
+        .get_current_list(chat_id)
+        .await
+        .ok_or_else(|| "Основной список нельзя удалить".to_string())?;
+
+    let name = storage.delete_list(chat_id, &list.id).await?;
+    Ok(format!("🗑️ Список «{}» удален, его задачи перенесены в «{}»", name, MAIN_LIST_NAME))
+}
+
+/// Делает текущим список с указанным названием. Возвращает текст подтверждения.
+pub(crate) async fn switch_list(storage: &SharedStorage, chat_id: ChatId, name: &str) -> Result<String, String> {
+    let lists = storage.get_lists(chat_id).await;
+    let list = resolve_list(&lists, name)?;
+
+    storage.set_current_list(chat_id, list.map(|l| l.id.as_str())).await?;
+    Ok(format!("🗂 Текущий список: «{}»", list.map_or(MAIN_LIST_NAME, |l| l.name.as_str())))
+}
+
+/// Переносит задачу текущего списка в список с указанным названием. Возвращает текст подтверждения.
+pub(crate) async fn move_task_to_named_list(
+    storage: &SharedStorage,
+    chat_id: ChatId,
+    task_ref: &str,
+    list_name: &str,
+) -> Result<String, String> {
+    let lists = storage.get_lists(chat_id).await;
+    let list = resolve_list(&lists, list_name)?;
+    let tasks = storage.get_current_tasks(chat_id).await;
+    let task = resolve_task(&tasks, task_ref)?;
+
+    let task_text = storage.move_task_to_list(chat_id, &task.id, list.map(|l| l.id.as_str())).await?;
+    Ok(format!("📦 Задача \"{}\" перенесена в «{}»", task_text, list.map_or(MAIN_LIST_NAME, |l| l.name.as_str())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::models::Priority;
+    use crate::storage::memory::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_edit_task_parses_tags_and_priority() {
+        let storage: SharedStorage = Arc::new(MemoryStorage::new());
+        let chat_id = ChatId(1);
+        storage.add_task(chat_id, TodoItem::new("Отчет".to_string())).await.unwrap();
+        let task_id = storage.get_tasks(chat_id).await[0].id.clone();
+
+        edit_task_by_id(&storage, chat_id, &task_id, "!high Квартальный отчет #работа").await.unwrap();
+        let task = &storage.get_tasks(chat_id).await[0];
+        assert_eq!(task.text, "Квартальный отчет");
+        assert_eq!(task.tags, vec!["работа".to_string()]);
+        assert_eq!(task.priority, Priority::High);
+
+        // Без тегов и приоритета в новом тексте прежние сохраняются
+        edit_task_by_id(&storage, chat_id, &task_id, "Годовой отчет").await.unwrap();
+        let task = &storage.get_tasks(chat_id).await[0];
+        assert_eq!((task.tags.len(), task.priority), (1, Priority::High));
     }
 
-    Ok(())
-}
\ No newline at end of file
+    #[tokio::test]
+    async fn test_complete_task_removes_reminders() {
+        let storage: SharedStorage = Arc::new(MemoryStorage::new());
+        let chat_id = ChatId(1);
+        storage.add_task(chat_id, TodoItem::new("Позвонить".to_string())).await.unwrap();
+        let task_id = storage.get_tasks(chat_id).await[0].id.clone();
+        let now = Utc::now();
+        storage.add_task_reminder(TaskReminder::new(chat_id.0, task_id.clone(), now + chrono::Duration::hours(1))).await.unwrap();
+
+        assert_eq!(complete_task_by_id(&storage, chat_id, &task_id).await, Ok("Позвонить".to_string()));
+        assert!(storage.take_due_task_reminders(now + chrono::Duration::days(1)).await.unwrap().is_empty());
+    }
+}
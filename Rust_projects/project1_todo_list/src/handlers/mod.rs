@@ -1,7 +1,11 @@
 pub mod commands;
 pub mod callbacks;
 pub mod messages;
+pub mod counters;
+pub mod dialogue;
 
-pub use commands::handle_command;
-pub use callbacks::handle_callback;
-pub use messages::handle_text_message;
\ No newline at end of file
+pub use dialogue::schema;
+
+/// Ошибка обработчика: запрос к Telegram или сохранение состояния диалога
+pub type HandlerError = Box<dyn std::error::Error + Send + Sync>;
+pub type HandlerResult = Result<(), HandlerError>;
@@ -0,0 +1,32 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Показание счетчика в одной тарифной зоне. Показания счетчика образуют временной ряд;
+/// у показаний одной отправки по разным зонам общее время `taken_at`
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct MeterReading {
+    pub id: String,
+    pub counter_id: String,
+    /// Тарифная зона («день», «ночь»); пустая строка — счетчик без зон
+    pub zone: String,
+    pub value: f64,
+    pub taken_at: DateTime<Utc>,
+}
+
+impl MeterReading {
+    pub fn new(counter_id: String, zone: String, value: f64, taken_at: DateTime<Utc>) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            counter_id,
+            zone,
+            value,
+            taken_at,
+        }
+    }
+}
+
+/// Последнее показание в зоне; `readings` — от старых к новым, как их возвращает хранилище
+pub fn last_reading<'a>(readings: &'a [MeterReading], zone: &str) -> Option<&'a MeterReading> {
+    readings.iter().rev().find(|r| r.zone == zone)
+}
@@ -1,7 +1,21 @@
 pub mod todo;
 pub mod user;
 pub mod reminder;
+pub mod meter_reading;
+pub mod tariff;
+pub mod task_reminder;
+pub mod recurrence;
+pub mod priority;
+pub mod task_list;
+pub mod undo;
 
-pub use todo::TodoItem;
-pub use user::{UserState, UserStates, create_user_states};
-pub use reminder::{CounterType, CounterReminder, UserReminders};
\ No newline at end of file
+pub use todo::{TodoItem, TaskEdit};
+pub use user::{UserState, SavedUserState};
+pub use reminder::{CounterType, CounterReminder, UserReminders, DEFAULT_COUNTER_EMOJI};
+pub use meter_reading::{MeterReading, last_reading};
+pub use tariff::{Tariff, MonthlyConsumption, tariff_on, month_of};
+pub use task_reminder::TaskReminder;
+pub use recurrence::Recurrence;
+pub use priority::Priority;
+pub use task_list::{TaskList, MAIN_LIST_NAME};
+pub use undo::{UndoEntry, UndoJournal, create_undo_journal, UNDO_WINDOW_MINUTES};
@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+/// Приоритет задачи. Порядок вариантов — от низкого к срочному
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Urgent,
+}
+
+impl Priority {
+    pub const ALL: [Priority; 4] = [Priority::Urgent, Priority::High, Priority::Normal, Priority::Low];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Normal => "normal",
+            Priority::High => "high",
+            Priority::Urgent => "urgent",
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Priority::Low => "🔽 Низкий",
+            Priority::Normal => "▫️ Обычный",
+            Priority::High => "🔼 Высокий",
+            Priority::Urgent => "‼️ Срочно",
+        }
+    }
+
+    /// Значок в списке задач; у обычного приоритета его нет
+    pub fn marker(&self) -> Option<&'static str> {
+        match self {
+            Priority::Low => Some("🔽"),
+            Priority::Normal => None,
+            Priority::High => Some("🔼"),
+            Priority::Urgent => Some("‼️"),
+        }
+    }
+
+    /// Разбирает приоритет по-английски или по-русски: `high`, `высокий`, `срочно`
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "low" | "низкий" | "низ" => Some(Priority::Low),
+            "normal" | "обычный" | "обыч" => Some(Priority::Normal),
+            "high" | "высокий" | "выс" | "важно" => Some(Priority::High),
+            "urgent" | "срочно" | "срочный" => Some(Priority::Urgent),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+/// Короткие названия дней недели, начиная с понедельника
+pub const WEEKDAY_NAMES: [&str; 7] = ["пн", "вт", "ср", "чт", "пт", "сб", "вс"];
+
+/// Правило повторения задачи
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum Recurrence {
+    Daily,
+    /// По выбранным дням недели (номера с понедельника: 0..=6)
+    Weekly(Vec<u32>),
+    /// Каждый месяц в указанный день (в коротких месяцах — последний день)
+    Monthly(u32),
+    EveryNDays(u32),
+}
+
+impl Recurrence {
+    /// Компактная строка для хранения в базе: `daily`, `weekly:0,2,4`, `monthly:5`, `every:3`
+    pub fn as_rule(&self) -> String {
+        match self {
+            Recurrence::Daily => "daily".to_string(),
+            Recurrence::Weekly(days) => format!(
+                "weekly:{}",
+                days.iter().map(|d| d.to_string()).collect::<Vec<_>>().join(",")
+            ),
+            Recurrence::Monthly(day) => format!("monthly:{}", day),
+            Recurrence::EveryNDays(n) => format!("every:{}", n),
+        }
+    }
+
+    pub fn from_rule(rule: &str) -> Option<Self> {
+        let (kind, arg) = rule.split_once(':').unwrap_or((rule, ""));
+        let recurrence = match kind {
+            "daily" => Recurrence::Daily,
+            "weekly" => {
+                let days = arg.split(',').map(|d| d.parse().ok()).collect::<Option<Vec<u32>>>()?;
+                Recurrence::Weekly(days)
+            }
+            "monthly" => Recurrence::Monthly(arg.parse().ok()?),
+            "every" => Recurrence::EveryNDays(arg.parse().ok()?),
+            _ => return None,
+        };
+        recurrence.is_valid().then_some(recurrence)
+    }
+
+    pub fn is_valid(&self) -> bool {
+        match self {
+            Recurrence::Daily => true,
+            Recurrence::Weekly(days) => !days.is_empty() && days.iter().all(|d| *d < 7),
+            Recurrence::Monthly(day) => (1..=31).contains(day),
+            Recurrence::EveryNDays(n) => (1..=365).contains(n),
+        }
+    }
+
+    /// Описание для пользователя: «каждый день», «по пн, чт», «5 числа каждого месяца»
+    pub fn describe(&self) -> String {
+        match self {
+            Recurrence::Daily => "каждый день".to_string(),
+            Recurrence::Weekly(days) => format!(
+                "по {}",
+                days.iter()
+                    .filter_map(|d| WEEKDAY_NAMES.get(*d as usize).copied())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Recurrence::Monthly(day) => format!("{} числа каждого месяца", day),
+            Recurrence::EveryNDays(n) => format!("каждые {} дн.", n),
+        }
+    }
+
+    /// Ближайшая дата повторения строго после `date`
+    pub fn next_after(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Recurrence::Daily => date + Duration::days(1),
+            Recurrence::EveryNDays(n) => date + Duration::days(*n as i64),
+            Recurrence::Weekly(days) => {
+                (1..=7)
+                    .map(|offset| date + Duration::days(offset))
+                    .find(|d| days.contains(&d.weekday().num_days_from_monday()))
+                    .unwrap_or(date + Duration::days(7))
+            }
+            Recurrence::Monthly(day) => {
+                let this_month = clamp_to_month(date.year(), date.month(), *day);
+                if this_month > date {
+                    this_month
+                } else if date.month() == 12 {
+                    clamp_to_month(date.year() + 1, 1, *day)
+                } else {
+                    clamp_to_month(date.year(), date.month() + 1, *day)
+                }
+            }
+        }
+    }
+}
+
+/// День месяца, ограниченный длиной месяца (31 февраля -> 28/29 февраля)
+fn clamp_to_month(year: i32, month: u32, day: u32) -> NaiveDate {
+    (1..=day)
+        .rev()
+        .find_map(|d| NaiveDate::from_ymd_opt(year, month, d))
+        .expect("every month has a first day")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_next_after() {
+        // 2024-05-01 — среда
+        assert_eq!(Recurrence::Daily.next_after(date(2024, 12, 31)), date(2025, 1, 1));
+        assert_eq!(Recurrence::EveryNDays(3).next_after(date(2024, 5, 1)), date(2024, 5, 4));
+        assert_eq!(Recurrence::Weekly(vec![0, 4]).next_after(date(2024, 5, 1)), date(2024, 5, 3));
+        assert_eq!(Recurrence::Weekly(vec![2]).next_after(date(2024, 5, 1)), date(2024, 5, 8));
+        assert_eq!(Recurrence::Monthly(5).next_after(date(2024, 5, 1)), date(2024, 5, 5));
+        assert_eq!(Recurrence::Monthly(5).next_after(date(2024, 12, 5)), date(2025, 1, 5));
+        assert_eq!(Recurrence::Monthly(31).next_after(date(2024, 1, 31)), date(2024, 2, 29));
+    }
+
+    #[test]
+    fn test_rule_roundtrip() {
+        for recurrence in [Recurrence::Daily, Recurrence::Weekly(vec![0, 2, 4]), Recurrence::Monthly(5), Recurrence::EveryNDays(3)] {
+            assert_eq!(Recurrence::from_rule(&recurrence.as_rule()), Some(recurrence));
+        }
+        assert_eq!(Recurrence::from_rule("weekly:9"), None);
+        assert_eq!(Recurrence::from_rule("monthly:0"), None);
+        assert_eq!(Recurrence::from_rule("yearly"), None);
+    }
+}
@@ -1,35 +1,82 @@
 use chrono::{NaiveDate, Datelike};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-
-
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub enum CounterType {
-    Water,
-    Electricity,
+use uuid::Uuid;
+
+/// Эмодзи счетчика, если пользователь его не указал
+pub const DEFAULT_COUNTER_EMOJI: &str = "📟";
+
+/// Счетчик, о показаниях которого напоминает бот («💧 Вода», «🔥 Газ», «🌐 Интернет»).
+/// Счетчики заводит пользователь; вода и электричество из прежних версий
+/// сохраняют id `water` и `electricity`
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(from = "StoredCounterType")]
+pub struct CounterType {
+    /// Короткий id: попадает в данные кнопок, а они ограничены 64 байтами
+    pub id: String,
+    pub name: String,
+    pub emoji: String,
+    /// Тарифные зоны («день», «ночь» или «горячая», «холодная»); пусто — одно показание
+    pub zones: Vec<String>,
 }
 
 impl CounterType {
-    pub fn as_str(&self) -> &'static str {
-        match self {
-            CounterType::Water => "water",
-            CounterType::Electricity => "electricity",
+    pub fn new(name: String, emoji: String) -> Self {
+        Self {
+            id: Uuid::new_v4().simple().to_string()[..8].to_string(),
+            name,
+            emoji,
+            zones: Vec::new(),
         }
     }
 
-    pub fn display_name(&self) -> &'static str {
-        match self {
-            CounterType::Water => "💧 Вода",
-            CounterType::Electricity => "⚡ Электричество",
+    /// Зоны, по которым передаются показания; у счетчика без зон — одна безымянная зона `""`
+    pub fn reading_zones(&self) -> Vec<&str> {
+        if self.zones.is_empty() {
+            vec![""]
+        } else {
+            self.zones.iter().map(String::as_str).collect()
         }
     }
 
-    pub fn from_str(s: &str) -> Option<Self> {
-        match s {
-            "water" => Some(CounterType::Water),
-            "electricity" => Some(CounterType::Electricity),
-            _ => None,
-        }
+    pub fn display_name(&self) -> String {
+        format!("{} {}", self.emoji, self.name)
+    }
+
+    /// Совпадение названия без учета регистра
+    pub fn has_name(&self, name: &str) -> bool {
+        self.name.to_lowercase() == name.trim().to_lowercase()
+    }
+}
+
+/// Счетчик в JSON-хранилище: прежние версии записывали вариант перечисления (`"Water"`)
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredCounterType {
+    Legacy(LegacyCounterType),
+    Custom {
+        id: String,
+        name: String,
+        emoji: String,
+        #[serde(default)]
+        zones: Vec<String>,
+    },
+}
+
+#[derive(Deserialize)]
+enum LegacyCounterType {
+    Water,
+    Electricity,
+}
+
+impl From<StoredCounterType> for CounterType {
+    fn from(stored: StoredCounterType) -> Self {
+        let (id, name, emoji, zones) = match stored {
+            StoredCounterType::Legacy(LegacyCounterType::Water) => ("water".to_string(), "Вода".to_string(), "💧".to_string(), Vec::new()),
+            StoredCounterType::Legacy(LegacyCounterType::Electricity) => ("electricity".to_string(), "Электричество".to_string(), "⚡".to_string(), Vec::new()),
+            StoredCounterType::Custom { id, name, emoji, zones } => (id, name, emoji, zones),
+        };
+        Self { id, name, emoji, zones }
     }
 }
 
@@ -121,7 +168,7 @@ impl CounterReminder {
 
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub struct UserReminders {
-    pub reminders: HashMap<String, CounterReminder>, // ключ = counter_type.as_str()
+    pub reminders: HashMap<String, CounterReminder>, // ключ = counter_type.id
     pub global_enabled: bool,
 }
 
@@ -135,11 +182,29 @@ impl UserReminders {
     }
 
     pub fn add_reminder(&mut self, reminder: CounterReminder) {
-        self.reminders.insert(reminder.counter_type.as_str().to_string(), reminder);
+        self.reminders.insert(reminder.counter_type.id.clone(), reminder);
+    }
+
+    pub fn get_reminder(&self, counter_id: &str) -> Option<&CounterReminder> {
+        self.reminders.get(counter_id)
+    }
+
+    pub fn get_reminder_mut(&mut self, counter_id: &str) -> Option<&mut CounterReminder> {
+        self.reminders.get_mut(counter_id)
+    }
+
+    /// Счетчики пользователя по алфавиту: в этом порядке они показываются в меню
+    pub fn counters(&self) -> Vec<&CounterReminder> {
+        let mut counters: Vec<&CounterReminder> = self.reminders.values().collect();
+        counters.sort_by_key(|r| r.counter_type.name.to_lowercase());
+        counters
     }
 
-    pub fn get_reminder_mut(&mut self, counter_type: &CounterType) -> Option<&mut CounterReminder> {
-        self.reminders.get_mut(counter_type.as_str())
+    /// Счетчик с таким названием (без учета регистра), кроме `except_id`
+    pub fn find_by_name(&self, name: &str, except_id: Option<&str>) -> Option<&CounterReminder> {
+        self.reminders
+            .values()
+            .find(|r| r.counter_type.has_name(name) && Some(r.counter_type.id.as_str()) != except_id)
     }
 
     pub fn toggle_global(&mut self) -> bool {
@@ -151,12 +216,20 @@ impl UserReminders {
 #[allow(dead_code)]
 pub type RemindersStorage = HashMap<String, UserReminders>; // ключ = chat_id.to_string()
 
-#[allow(dead_code)]
-#[derive(Clone, Debug)]
-pub enum ReminderState {
-    WaitingForWaterPeriod,
-    WaitingForElectricityPeriod,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_legacy_counter_type_deserializes() {
+        let reminder: CounterReminder = serde_json::from_str(
+            r#"{"counter_type":"Water","start_day":16,"end_day":25,"enabled":true,"last_sent_month":null,"completed_this_month":false}"#,
+        ).unwrap();
+        assert_eq!(reminder.counter_type.id, "water");
+        assert_eq!(reminder.counter_type.display_name(), "💧 Вода");
+
+        let gas = CounterReminder::new(CounterType::new("Газ".to_string(), "🔥".to_string()), 20, 25);
+        let restored: CounterReminder = serde_json::from_str(&serde_json::to_string(&gas).unwrap()).unwrap();
+        assert_eq!(restored.counter_type, gas.counter_type);
+    }
 }
-
-#[allow(dead_code)]
-pub type ReminderStates = HashMap<String, ReminderState>; // ключ = chat_id.to_string()
\ No newline at end of file
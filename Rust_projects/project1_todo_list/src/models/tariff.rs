@@ -0,0 +1,135 @@
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Utc};
+use chrono_tz::Europe::Moscow;
+use serde::{Deserialize, Serialize};
+
+use super::meter_reading::MeterReading;
+
+/// Цена единицы расхода (кубометра, кВт·ч) в тарифной зоне счетчика, действующая с даты `effective_from`.
+/// У зоны может быть несколько тарифов: действует последний начавшийся
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Tariff {
+    pub counter_id: String,
+    /// Тарифная зона, как у показаний; пустая строка — счетчик без зон
+    pub zone: String,
+    pub price: f64,
+    pub effective_from: NaiveDate,
+}
+
+impl Tariff {
+    pub fn new(counter_id: String, zone: String, price: f64, effective_from: NaiveDate) -> Self {
+        Self {
+            counter_id,
+            zone,
+            price,
+            effective_from,
+        }
+    }
+}
+
+/// Тариф зоны, действующий на дату `date`; `tariffs` — по возрастанию даты начала, как их возвращает хранилище
+pub fn tariff_on<'a>(tariffs: &'a [Tariff], zone: &str, date: NaiveDate) -> Option<&'a Tariff> {
+    tariffs.iter().rev().find(|t| t.zone == zone && t.effective_from <= date)
+}
+
+/// Первое число месяца, к которому относится момент времени (по Москве)
+pub fn month_of(moment: DateTime<Utc>) -> NaiveDate {
+    Moscow.from_utc_datetime(&moment.naive_utc()).date_naive().with_day(1).expect("first day of month exists")
+}
+
+/// Расход в одной зоне за месяц
+#[derive(Clone, Debug, PartialEq)]
+pub struct ZoneConsumption {
+    pub zone: String,
+    pub amount: f64,
+    /// Цена единицы; `None` — тариф на дату показаний не задан
+    pub price: Option<f64>,
+}
+
+impl ZoneConsumption {
+    pub fn cost(&self) -> Option<f64> {
+        self.price.map(|price| self.amount * price)
+    }
+}
+
+/// Расход счетчика за месяц по зонам
+#[derive(Clone, Debug, PartialEq)]
+pub struct MonthlyConsumption {
+    /// Первое число месяца
+    pub month: NaiveDate,
+    pub zones: Vec<ZoneConsumption>,
+}
+
+impl MonthlyConsumption {
+    /// Стоимость за месяц; `None`, если хотя бы для одной зоны нет тарифа
+    pub fn cost(&self) -> Option<f64> {
+        self.zones.iter().map(ZoneConsumption::cost).sum()
+    }
+
+    /// Расход за месяц в зонах `zones` (`[""]` у счетчика без зон).
+    /// Расход зоны — разница последнего показания месяца и последнего показания до начала месяца;
+    /// цена — по тарифу, действующему на день показания месяца.
+    /// `None`, если ни в одной зоне расход посчитать не из чего.
+    pub fn calculate(zones: &[&str], readings: &[MeterReading], tariffs: &[Tariff], month: NaiveDate) -> Option<Self> {
+        let zones: Vec<ZoneConsumption> = zones
+            .iter()
+            .filter_map(|zone| {
+                let latest_first = || readings.iter().rev().filter(move |r| r.zone == *zone);
+                let current = latest_first().find(|r| month_of(r.taken_at) == month)?;
+                let previous = latest_first().find(|r| month_of(r.taken_at) < month)?;
+                let reading_date = Moscow.from_utc_datetime(&current.taken_at.naive_utc()).date_naive();
+                Some(ZoneConsumption {
+                    zone: zone.to_string(),
+                    amount: current.value - previous.value,
+                    price: tariff_on(tariffs, zone, reading_date).map(|t| t.price),
+                })
+            })
+            .collect();
+
+        if zones.is_empty() {
+            None
+        } else {
+            Some(Self { month, zones })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(zone: &str, value: f64, date: &str) -> MeterReading {
+        let taken_at = format!("{}T09:00:00Z", date).parse().unwrap();
+        MeterReading::new("power".to_string(), zone.to_string(), value, taken_at)
+    }
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_monthly_consumption_with_tariff_change() {
+        let readings = vec![
+            reading("день", 1000.0, "2025-05-22"),
+            reading("ночь", 400.0, "2025-05-22"),
+            reading("день", 1100.0, "2025-06-10"),
+            reading("день", 1150.0, "2025-06-21"),
+            reading("ночь", 430.0, "2025-06-21"),
+        ];
+        let tariffs = vec![
+            Tariff::new("power".to_string(), "день".to_string(), 6.0, date("2025-01-01")),
+            Tariff::new("power".to_string(), "ночь".to_string(), 2.5, date("2025-01-01")),
+            Tariff::new("power".to_string(), "день".to_string(), 7.0, date("2025-07-01")),
+        ];
+
+        let june = MonthlyConsumption::calculate(&["день", "ночь"], &readings, &tariffs, date("2025-06-01")).unwrap();
+        assert_eq!(june.zones[0], ZoneConsumption { zone: "день".to_string(), amount: 150.0, price: Some(6.0) });
+        assert_eq!(june.zones[1].amount, 30.0);
+        assert_eq!(june.cost(), Some(150.0 * 6.0 + 30.0 * 2.5));
+
+        // В первом месяце показаний расход считать не из чего
+        assert_eq!(MonthlyConsumption::calculate(&["день", "ночь"], &readings, &tariffs, date("2025-05-01")), None);
+        // Без тарифа зоны стоимость за месяц неизвестна
+        let june = MonthlyConsumption::calculate(&["день", "ночь"], &readings, &tariffs[..1], date("2025-06-01")).unwrap();
+        assert_eq!(june.cost(), None);
+    }
+}
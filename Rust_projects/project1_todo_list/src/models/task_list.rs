@@ -0,0 +1,30 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Название списка по умолчанию: задачи без `list_id` лежат в нём
+pub const MAIN_LIST_NAME: &str = "Основной";
+
+/// Именованный список задач чата («Дом», «Работа», «Покупки»)
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct TaskList {
+    /// Короткий id: попадает в данные кнопок, а они ограничены 64 байтами
+    pub id: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TaskList {
+    pub fn new(name: String) -> Self {
+        Self {
+            id: Uuid::new_v4().simple().to_string()[..8].to_string(),
+            name,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Совпадение названия без учета регистра
+    pub fn has_name(&self, name: &str) -> bool {
+        self.name.to_lowercase() == name.trim().to_lowercase()
+    }
+}
@@ -0,0 +1,66 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Сколько раз пробуем отправить напоминание, прежде чем отказаться от него
+pub const MAX_SEND_ATTEMPTS: i32 = 5;
+
+/// Разовое напоминание о конкретной задаче
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct TaskReminder {
+    pub id: String,
+    pub chat_id: i64,
+    pub task_id: String,
+    pub remind_at: DateTime<Utc>,
+    /// Неудачные попытки отправки
+    #[serde(default)]
+    pub attempts: i32,
+}
+
+impl TaskReminder {
+    pub fn new(chat_id: i64, task_id: String, remind_at: DateTime<Utc>) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            chat_id,
+            task_id,
+            remind_at,
+            attempts: 0,
+        }
+    }
+
+    /// Напоминание для повтора после неудачной отправки: следующая попытка через 1, 2, 4… минуты.
+    /// `None`, если попытки исчерпаны
+    pub fn retry(self, now: DateTime<Utc>) -> Option<Self> {
+        let attempts = self.attempts + 1;
+        if attempts >= MAX_SEND_ATTEMPTS {
+            return None;
+        }
+        Some(Self {
+            remind_at: now + Duration::minutes(1 << (attempts - 1)),
+            attempts,
+            ..self
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_backs_off_and_gives_up() {
+        let now = Utc::now();
+        let reminder = TaskReminder::new(1, "task".to_string(), now);
+
+        let first = reminder.retry(now).unwrap();
+        assert_eq!(first.attempts, 1);
+        assert_eq!(first.remind_at, now + Duration::minutes(1));
+
+        let second = first.retry(now).unwrap();
+        assert_eq!(second.remind_at, now + Duration::minutes(2));
+
+        let mut last = second;
+        last.attempts = MAX_SEND_ATTEMPTS - 1;
+        assert_eq!(last.retry(now), None);
+    }
+}
@@ -1,13 +1,48 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Europe::Moscow;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use super::{Priority, Recurrence};
+
+/// Длина короткого псевдонима задачи, который видит пользователь
+pub const SHORT_ID_LEN: usize = 6;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TodoItem {
     pub id: String,
     pub text: String,
     pub completed: bool,
     pub created_at: DateTime<Utc>,
+    /// Срок выполнения (если задан)
+    #[serde(default)]
+    pub due_at: Option<DateTime<Utc>>,
+    /// Правило повторения: после выполнения создаётся следующая задача
+    #[serde(default)]
+    pub recurrence: Option<Recurrence>,
+    #[serde(default)]
+    pub priority: Priority,
+    /// Теги из текста (`#работа`), в нижнем регистре и по алфавиту
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Родительская задача, если это пункт чек-листа
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    /// Именованный список задачи; `None` — основной список
+    #[serde(default)]
+    pub list_id: Option<String>,
+    /// Место задачи в списке чата; назначается хранилищем при добавлении
+    #[serde(default)]
+    pub position: i64,
+    /// Когда задача отмечена выполненной; по этому времени она уходит в архив
+    #[serde(default)]
+    pub completed_at: Option<DateTime<Utc>>,
+    /// Когда задача перенесена в архив
+    #[serde(default)]
+    pub archived_at: Option<DateTime<Utc>>,
+    /// Когда задача удалена в корзину
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 impl TodoItem {
@@ -17,14 +52,136 @@ impl TodoItem {
             text,
             completed: false,
             created_at: Utc::now(),
+            due_at: None,
+            recurrence: None,
+            priority: Priority::default(),
+            tags: Vec::new(),
+            parent_id: None,
+            list_id: None,
+            position: 0,
+            completed_at: None,
+            archived_at: None,
+            deleted_at: None,
         }
     }
 
-    pub fn mark_completed(&mut self) {
+    pub fn with_due(mut self, due_at: Option<DateTime<Utc>>) -> Self {
+        self.due_at = due_at;
+        self
+    }
+
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    pub fn with_parent(mut self, parent_id: Option<String>) -> Self {
+        self.parent_id = parent_id;
+        self
+    }
+
+    pub fn with_list(mut self, list_id: Option<String>) -> Self {
+        self.list_id = list_id;
+        self
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    pub fn mark_completed(&mut self, now: DateTime<Utc>) {
         self.completed = true;
+        self.completed_at = Some(now);
     }
 
     pub fn is_completed(&self) -> bool {
         self.completed
     }
-}
\ No newline at end of file
+
+    /// Задача в списке, а не в архиве или корзине
+    pub fn is_active(&self) -> bool {
+        self.archived_at.is_none() && self.deleted_at.is_none()
+    }
+
+    /// Невыполненная задача, срок которой уже прошел
+    pub fn is_overdue(&self, now: DateTime<Utc>) -> bool {
+        !self.completed && self.due_at.is_some_and(|due| due < now)
+    }
+
+    /// Следующее повторение задачи, которое нужно создать после её выполнения.
+    /// Срок отсчитывается от текущего срока (время суток сохраняется), а без срока — от сегодняшнего дня;
+    /// пропущенные повторения не создаются, следующая дата не раньше сегодняшней.
+    pub fn next_occurrence(&self, now: DateTime<Utc>) -> Option<TodoItem> {
+        let recurrence = self.recurrence.as_ref()?;
+        let today = Moscow.from_utc_datetime(&now.naive_utc()).date_naive();
+        let (base, time) = match self.due_at {
+            Some(due_at) => {
+                let local = Moscow.from_utc_datetime(&due_at.naive_utc());
+                (local.date_naive(), local.time())
+            }
+            None => (today, NaiveTime::from_hms_opt(23, 59, 0).expect("valid end of day")),
+        };
+
+        let mut next = recurrence.next_after(base);
+        while next < today {
+            next = recurrence.next_after(next);
+        }
+        let due_at = Moscow.from_local_datetime(&next.and_time(time)).earliest()?.with_timezone(&Utc);
+
+        Some(TodoItem {
+            due_at: Some(due_at),
+            recurrence: Some(recurrence.clone()),
+            priority: self.priority,
+            tags: self.tags.clone(),
+            parent_id: self.parent_id.clone(),
+            list_id: self.list_id.clone(),
+            ..TodoItem::new(self.text.clone())
+        })
+    }
+
+    /// Короткий псевдоним задачи (начало UUID), например `3f2a9c`
+    pub fn short_id(&self) -> &str {
+        &self.id[..SHORT_ID_LEN.min(self.id.len())]
+    }
+}
+
+/// Новый текст задачи с приоритетом, тегами и сроком, указанными в нем.
+/// Указанное заменяет прежнее значение задачи, неуказанное (`None`) сохраняется
+#[derive(Clone, Debug, PartialEq)]
+pub struct TaskEdit {
+    pub text: String,
+    pub priority: Option<Priority>,
+    pub tags: Option<Vec<String>>,
+    pub due_at: Option<DateTime<Utc>>,
+}
+
+impl TaskEdit {
+    /// Только новый текст, остальное без изменений
+    #[cfg(test)]
+    pub fn text(text: String) -> Self {
+        Self {
+            text,
+            priority: None,
+            tags: None,
+            due_at: None,
+        }
+    }
+
+    pub fn apply(&self, todo: &mut TodoItem) {
+        todo.text = self.text.clone();
+        if let Some(priority) = self.priority {
+            todo.priority = priority;
+        }
+        if let Some(tags) = &self.tags {
+            todo.tags = tags.clone();
+        }
+        if self.due_at.is_some() {
+            todo.due_at = self.due_at;
+        }
+    }
+}
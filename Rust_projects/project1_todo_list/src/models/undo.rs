@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use chrono::{DateTime, Duration, Utc};
+use tokio::sync::Mutex;
+use teloxide::types::ChatId;
+
+use super::TodoItem;
+
+/// Сколько минут после удаления его можно отменить
+pub const UNDO_WINDOW_MINUTES: i64 = 10;
+
+/// Последняя разрушающая операция чата: удаленные задачи, которые можно вернуть
+#[derive(Clone, Debug)]
+pub struct UndoEntry {
+    /// Что было сделано, для сообщения об отмене: «удаление задачи "…"»
+    pub description: String,
+    pub tasks: Vec<TodoItem>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl UndoEntry {
+    pub fn new(description: String, tasks: Vec<TodoItem>) -> Self {
+        Self {
+            description,
+            tasks,
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        now - self.created_at > Duration::minutes(UNDO_WINDOW_MINUTES)
+    }
+}
+
+/// Журнал отмены: хранится в памяти, для каждого чата — только последняя операция
+pub type UndoJournal = Arc<Mutex<HashMap<ChatId, UndoEntry>>>;
+
+pub fn create_undo_journal() -> UndoJournal {
+    Arc::new(Mutex::new(HashMap::new()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_undo_entry_expires() {
+        let entry = UndoEntry::new("удаление".to_string(), Vec::new());
+        assert!(!entry.is_expired(entry.created_at + Duration::minutes(UNDO_WINDOW_MINUTES)));
+        assert!(entry.is_expired(entry.created_at + Duration::minutes(UNDO_WINDOW_MINUTES + 1)));
+    }
+}
@@ -1,27 +1,41 @@
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::Mutex;
-use teloxide::types::ChatId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+/// Шаг диалога: какой ввод бот ждет от пользователя.
+/// Хранится в хранилище, поэтому переживает перезапуск бота
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub enum UserState {
+    #[default]
     Default,
     WaitingForTask,
     WaitingForTaskList,
     WaitingForTaskNumber,
     WaitingForRemovalNumber,
-    WaitingForWaterPeriod,
-    WaitingForElectricityPeriod,
+    WaitingForTaskReminder,
+    WaitingForTaskRecurrence,
+    WaitingForTaskEdit,
+    /// Новый текст задачи с этим id (после кнопки ✏️ в списке)
+    WaitingForNewTaskText(String),
+    WaitingForListName,
+    WaitingForListRename,
+    WaitingForSearchQuery,
+    /// Название и период нового счетчика
+    WaitingForNewCounter,
+    /// Период подачи показаний счетчика с этим id
+    WaitingForPeriod(String),
+    /// Новое название счетчика с этим id
+    WaitingForCounterRename(String),
+    /// Тарифные зоны счетчика с этим id
+    WaitingForCounterZones(String),
+    /// Показания счетчика с этим id
+    WaitingForReading(String),
+    /// Тариф счетчика с этим id
+    WaitingForTariff(String),
 }
 
-impl Default for UserState {
-    fn default() -> Self {
-        UserState::Default
-    }
-}
-
-pub type UserStates = Arc<Mutex<HashMap<ChatId, UserState>>>;
-
-pub fn create_user_states() -> UserStates {
-    Arc::new(Mutex::new(HashMap::new()))
+/// Состояние диалога в хранилище вместе со временем последнего изменения
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedUserState {
+    pub state: UserState,
+    pub updated_at: DateTime<Utc>,
 }
\ No newline at end of file
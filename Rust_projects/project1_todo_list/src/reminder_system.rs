@@ -2,32 +2,51 @@ use std::time::Duration;
 use chrono::{Utc, TimeZone, Timelike, Datelike};
 use chrono_tz::Europe::Moscow;
 use teloxide::prelude::*;
+use teloxide::{ApiError, RequestError};
 use tokio::time::interval;
 
-use crate::storage::StorageType;
-use crate::utils::create_reminder_response_keyboard;
+use crate::config::RetentionPolicy;
+use crate::storage::SharedStorage;
+use crate::utils::{create_reminder_response_keyboard, create_task_reminder_keyboard, format_task_title};
 
 pub struct ReminderSystem {
     bot: Bot,
-    storage: StorageType,
+    storage: SharedStorage,
+    retention: RetentionPolicy,
 }
 
 impl ReminderSystem {
-    pub fn new(bot: Bot, storage: StorageType) -> Self {
-        Self { bot, storage }
+    pub fn new(bot: Bot, storage: SharedStorage, retention: RetentionPolicy) -> Self {
+        Self { bot, storage, retention }
     }
 
     pub async fn start(&self) {
         log::info!("Starting reminder system...");
         
-        // Запускаем периодическую проверку чаще, чтобы не пропускать окно отправки
-        let mut interval = interval(Duration::from_secs(15 * 60)); // каждые 15 минут
+        // Напоминания о задачах проверяем каждую минуту, чтобы не опаздывать к заданному времени
+        let mut task_interval = interval(Duration::from_secs(60));
+        // Счетчики проверяем чаще окна отправки, чтобы его не пропускать
+        let mut counter_interval = interval(Duration::from_secs(15 * 60)); // каждые 15 минут
+        // Архив и корзина не требуют точности, достаточно раза в час
+        let mut cleanup_interval = interval(Duration::from_secs(60 * 60));
         
         loop {
-            interval.tick().await;
-            
-            if let Err(e) = self.check_and_send_reminders().await {
-                log::error!("Error in reminder system: {}", e);
+            tokio::select! {
+                _ = task_interval.tick() => {
+                    if let Err(e) = self.send_due_task_reminders().await {
+                        log::error!("Error sending task reminders: {}", e);
+                    }
+                }
+                _ = counter_interval.tick() => {
+                    if let Err(e) = self.check_and_send_reminders().await {
+                        log::error!("Error in reminder system: {}", e);
+                    }
+                }
+                _ = cleanup_interval.tick() => {
+                    if let Err(e) = self.clean_up_tasks().await {
+                        log::error!("Error cleaning up tasks: {}", e);
+                    }
+                }
             }
         }
     }
@@ -46,7 +65,7 @@ impl ReminderSystem {
         let current_hour = moscow_now.hour();
 
         // Отправляем напоминания около 20:00 по Москве, избегая дублей в сутки
-        if current_hour < 19 || current_hour > 21 {
+        if !(19..=21).contains(&current_hour) {
             log::debug!("Not reminder window (current hour: {})", current_hour);
             return Ok(());
         }
@@ -66,7 +85,7 @@ impl ReminderSystem {
             };
 
             // Проверяем каждый тип счетчика
-            for (_, reminder) in &user_reminders.reminders {
+            for reminder in user_reminders.reminders.values() {
                 if reminder.should_remind_today(today) {
                     // проверка на дубли в пределах суток
                     if let Some(last) = &reminder.last_sent_date {
@@ -78,7 +97,7 @@ impl ReminderSystem {
                         log::error!("Failed to send reminder to {}: {}", chat_id, e);
                     } else {
                         // Отмечаем, что напоминание отправлено в этом месяце
-                        if let Err(e) = self.mark_reminder_sent(chat_id, &reminder.counter_type, today).await {
+                        if let Err(e) = self.mark_reminder_sent(chat_id, &reminder.counter_type.id, today).await {
                             log::error!("Failed to mark reminder as sent: {}", e);
                         }
                     }
@@ -89,6 +108,72 @@ impl ReminderSystem {
         Ok(())
     }
 
+    /// Переносит давно выполненные задачи в архив, очищает корзину от старых задач
+    /// и забывает брошенные диалоги
+    async fn clean_up_tasks(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let now = Utc::now();
+
+        if let Some(archive_after) = self.retention.archive_after {
+            let archived = self.storage.archive_completed_tasks(now - archive_after).await?;
+            if archived > 0 {
+                log::info!("Archived {} completed tasks", archived);
+            }
+        }
+
+        let purged = self.storage.purge_trash(now - self.retention.trash_retention).await?;
+        if purged > 0 {
+            log::info!("Purged {} tasks from trash", purged);
+        }
+
+        let expired = self.storage.purge_user_states(now - self.retention.dialogue_ttl).await?;
+        if expired > 0 {
+            log::info!("Dropped {} expired dialogue states", expired);
+        }
+        Ok(())
+    }
+
+    async fn send_due_task_reminders(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let due_reminders = self.storage.take_due_task_reminders(Utc::now()).await?;
+
+        for reminder in due_reminders {
+            let chat_id = ChatId(reminder.chat_id);
+
+            // Задачу могли удалить или выполнить после настройки напоминания
+            let tasks = self.storage.get_tasks(chat_id).await;
+            let task = match tasks.iter().find(|t| t.id == reminder.task_id) {
+                Some(task) if !task.is_completed() => task,
+                _ => continue,
+            };
+
+            let result = self.bot
+                .send_message(chat_id, format!("🔔 Напоминание о задаче:\n\n{}", format_task_title(task)))
+                .reply_markup(create_task_reminder_keyboard(&task.id))
+                .await;
+
+            match result {
+                Ok(_) => log::info!("Sent task reminder {} to {}", reminder.id, chat_id),
+                Err(e) if is_chat_unreachable(&e) => {
+                    log::warn!("Dropping task reminder {}: chat {} is unreachable: {}", reminder.id, chat_id, e);
+                }
+                Err(e) => {
+                    log::error!("Failed to send task reminder to {}: {}", chat_id, e);
+                    // Возвращаем напоминание с отсрочкой, пока не исчерпаны попытки
+                    let reminder_id = reminder.id.clone();
+                    match reminder.retry(Utc::now()) {
+                        Some(retry) => {
+                            if let Err(e) = self.storage.add_task_reminder(retry).await {
+                                log::error!("Failed to restore task reminder: {}", e);
+                            }
+                        }
+                        None => log::warn!("Giving up on task reminder {} after repeated failures", reminder_id),
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     async fn send_reminder(
         &self,
         chat_id: ChatId,
@@ -122,22 +207,22 @@ impl ReminderSystem {
 
         self.bot
             .send_message(chat_id, message)
-            .reply_markup(create_reminder_response_keyboard(reminder.counter_type.as_str()))
+            .reply_markup(create_reminder_response_keyboard(&reminder.counter_type))
             .await?;
 
-        log::info!("Sent reminder for {} to {}", reminder.counter_type.as_str(), chat_id);
+        log::info!("Sent reminder for {} to {}", reminder.counter_type.id, chat_id);
         Ok(())
     }
 
     async fn mark_reminder_sent(
         &self,
         chat_id: ChatId,
-        counter_type: &crate::models::CounterType,
+        counter_id: &str,
         date: chrono::NaiveDate,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let mut user_reminders = self.storage.get_user_reminders(chat_id).await;
         
-        if let Some(reminder) = user_reminders.get_reminder_mut(counter_type) {
+        if let Some(reminder) = user_reminders.get_reminder_mut(counter_id) {
             reminder.mark_sent(date);
             self.storage.save_user_reminders(chat_id, user_reminders).await?;
         }
@@ -165,14 +250,30 @@ impl ReminderSystem {
                 Err(_) => continue,
             };
 
-            for (_, reminder) in &user_reminders.reminders {
+            for reminder in user_reminders.reminders.values() {
                 if reminder.should_remind_today(today) {
                     self.send_reminder(chat_id, reminder).await?;
-                    self.mark_reminder_sent(chat_id, &reminder.counter_type, today).await?;
+                    self.mark_reminder_sent(chat_id, &reminder.counter_type.id, today).await?;
                 }
             }
         }
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Ошибки, после которых писать в чат бессмысленно: бот заблокирован, удален из чата или чата больше нет
+fn is_chat_unreachable(error: &RequestError) -> bool {
+    matches!(
+        error,
+        RequestError::Api(
+            ApiError::BotBlocked
+                | ApiError::ChatNotFound
+                | ApiError::UserDeactivated
+                | ApiError::BotKicked
+                | ApiError::BotKickedFromSupergroup
+                | ApiError::CantInitiateConversation
+                | ApiError::CantTalkWithBots
+        )
+    )
+}
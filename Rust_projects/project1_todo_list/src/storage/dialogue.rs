@@ -0,0 +1,95 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use chrono::{DateTime, Duration, Utc};
+use teloxide::dispatching::dialogue;
+use teloxide::types::ChatId;
+
+use crate::models::{SavedUserState, UserState};
+use super::SharedStorage;
+
+type StorageError = Box<dyn std::error::Error + Send + Sync>;
+type StorageFuture<T> = Pin<Box<dyn Future<Output = Result<T, StorageError>> + Send>>;
+
+/// Состояния диалогов поверх общего хранилища: незаконченный ввод переживает перезапуск бота.
+/// Состояние старше `ttl` считается брошенным и не возвращается.
+pub struct DialogueStorage {
+    storage: SharedStorage,
+    ttl: Duration,
+}
+
+/// Состояния диалогов, разделяемые между обработчиками
+pub type UserStates = Arc<DialogueStorage>;
+
+impl DialogueStorage {
+    pub fn new(storage: SharedStorage, ttl: Duration) -> UserStates {
+        Arc::new(Self { storage, ttl })
+    }
+
+    /// Текущее состояние чата; брошенный диалог считается законченным
+    async fn get(&self, chat_id: ChatId) -> UserState {
+        match self.storage.get_user_state(chat_id).await {
+            Some(saved) if saved.updated_at > Utc::now() - self.ttl => saved.state,
+            _ => UserState::Default,
+        }
+    }
+}
+
+/// Состояние из строки базы данных; состояние, которое больше не разбирается
+/// (например, шаг удалили в новой версии), считается отсутствующим
+pub(crate) fn saved_user_state(state: String, updated_at: DateTime<Utc>) -> Option<SavedUserState> {
+    match serde_json::from_str(&state) {
+        Ok(state) => Some(SavedUserState { state, updated_at }),
+        Err(e) => {
+            log::warn!("Dropping unreadable dialogue state {}: {}", state, e);
+            None
+        }
+    }
+}
+
+/// Позволяет использовать хранилище с `teloxide::dispatching::dialogue::Dialogue`
+impl dialogue::Storage<UserState> for DialogueStorage {
+    type Error = StorageError;
+
+    fn remove_dialogue(self: Arc<Self>, chat_id: ChatId) -> StorageFuture<()> {
+        Box::pin(async move { self.storage.save_user_state(chat_id, UserState::Default).await })
+    }
+
+    fn update_dialogue(self: Arc<Self>, chat_id: ChatId, state: UserState) -> StorageFuture<()> {
+        Box::pin(async move { self.storage.save_user_state(chat_id, state).await })
+    }
+
+    fn get_dialogue(self: Arc<Self>, chat_id: ChatId) -> StorageFuture<Option<UserState>> {
+        Box::pin(async move {
+            let state = self.get(chat_id).await;
+            Ok((state != UserState::Default).then_some(state))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use teloxide::dispatching::dialogue::Dialogue;
+    use crate::storage::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_dialogue_state_expires() {
+        let storage: SharedStorage = Arc::new(MemoryStorage::new());
+        let chat_id = ChatId(1);
+        let state = UserState::WaitingForNewTaskText("id".to_string());
+
+        let dialogue = Dialogue::new(DialogueStorage::new(storage.clone(), Duration::minutes(30)), chat_id);
+        dialogue.update(state.clone()).await.unwrap();
+        assert_eq!(dialogue.get().await.unwrap(), Some(state));
+
+        // Состояние, сохраненное раньше срока жизни, уже не действует
+        let expired = Dialogue::new(DialogueStorage::new(storage.clone(), Duration::zero()), chat_id);
+        assert_eq!(expired.get().await.unwrap(), None);
+        assert_eq!(storage.purge_user_states(Utc::now()).await.unwrap(), 1);
+
+        dialogue.update(UserState::WaitingForTask).await.unwrap();
+        dialogue.exit().await.unwrap();
+        assert!(storage.get_user_state(chat_id).await.is_none());
+    }
+}
@@ -1,18 +1,49 @@
+use std::cmp::Reverse;
 use std::collections::HashMap;
 use std::path::Path;
 use std::sync::Arc;
 use tokio::fs;
 use tokio::sync::Mutex;
-use chrono::TimeZone;
+use chrono::{DateTime, TimeZone, Utc};
 use teloxide::types::ChatId;
 use serde::{Deserialize, Serialize};
+use async_trait::async_trait;
 
-use crate::models::{TodoItem, UserReminders, CounterReminder, CounterType};
+use crate::models::{TodoItem, TaskEdit, UserReminders, CounterReminder, MeterReading, Tariff, TaskReminder, Recurrence, Priority, TaskList, UserState, SavedUserState};
+use super::Storage;
+use super::tasks::{self, complete_task, next_position};
 
 #[derive(Serialize, Deserialize, Default)]
 struct JsonData {
     todos: HashMap<String, Vec<TodoItem>>,
     reminders: HashMap<String, UserReminders>,
+    #[serde(default)]
+    task_reminders: Vec<TaskReminder>,
+    /// Показания счетчиков чата в порядке отправки
+    #[serde(default)]
+    meter_readings: HashMap<String, Vec<MeterReading>>,
+    /// Тарифы счетчиков чата
+    #[serde(default)]
+    tariffs: HashMap<String, Vec<Tariff>>,
+    #[serde(default)]
+    lists: HashMap<String, Vec<TaskList>>,
+    /// Текущий список чата; нет записи — основной список
+    #[serde(default)]
+    current_lists: HashMap<String, String>,
+    /// Незаконченные диалоги (`SavedUserState`); нет записи — `UserState::Default`.
+    /// Хранятся как есть, чтобы состояние из старой версии не мешало прочитать остальной файл
+    #[serde(default)]
+    user_states: HashMap<String, serde_json::Value>,
+}
+
+impl JsonData {
+    fn current_list_id(&self, chat_key: &str) -> Option<String> {
+        self.current_lists.get(chat_key).cloned()
+    }
+
+    fn has_list(&self, chat_key: &str, list_id: &str) -> bool {
+        self.lists.get(chat_key).is_some_and(|lists| lists.iter().any(|l| l.id == list_id))
+    }
 }
 
 #[derive(Clone)]
@@ -49,91 +80,361 @@ impl JsonStorage {
         fs::rename(&tmp_path, &self.file_path).await?;
         Ok(())
     }
+}
 
-    pub async fn add_task(&self, chat_id: ChatId, text: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+#[async_trait]
+impl Storage for JsonStorage {
+    async fn add_task(&self, chat_id: ChatId, mut todo_item: TodoItem) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let _guard = self.lock.lock().await;
         let mut data = self.load_data().await;
         let chat_key = chat_id.0.to_string();
         
-        let todo_item = TodoItem::new(text.to_string());
         let todos = data.todos.entry(chat_key).or_insert_with(Vec::new);
+        todo_item.position = next_position(todos);
         todos.push(todo_item);
         
         self.save_data(&data).await?;
         Ok(())
     }
 
-    pub async fn get_tasks(&self, chat_id: ChatId) -> Vec<TodoItem> {
+    async fn get_tasks(&self, chat_id: ChatId) -> Vec<TodoItem> {
+        let _guard = self.lock.lock().await;
+        let data = self.load_data().await;
+        let chat_key = chat_id.0.to_string();
+        data.todos
+            .get(&chat_key)
+            .map(|todos| todos.iter().filter(|t| t.is_active()).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    async fn get_tags(&self, chat_id: ChatId) -> Vec<String> {
         let _guard = self.lock.lock().await;
         let data = self.load_data().await;
         let chat_key = chat_id.0.to_string();
-        data.todos.get(&chat_key).cloned().unwrap_or_default()
+        let list_id = data.current_list_id(&chat_key);
+        let mut tags: Vec<String> = data
+            .todos
+            .get(&chat_key)
+            .map(|todos| {
+                todos.iter()
+                    .filter(|t| t.list_id == list_id && t.is_active())
+                    .flat_map(|t| t.tags.iter().cloned())
+                    .collect()
+            })
+            .unwrap_or_default();
+        tags.sort();
+        tags.dedup();
+        tags
     }
 
-    pub async fn mark_task_completed(&self, chat_id: ChatId, task_index: usize) -> Result<String, String> {
+    async fn mark_task_completed(&self, chat_id: ChatId, task_id: &str) -> Result<String, String> {
         let _guard = self.lock.lock().await;
         let mut data = self.load_data().await;
         let chat_key = chat_id.0.to_string();
         
         if let Some(todos) = data.todos.get_mut(&chat_key) {
-            if task_index < todos.len() {
-                todos[task_index].mark_completed();
-                let task_text = todos[task_index].text.clone();
+            let completion = complete_task(todos, task_id, Utc::now())?;
+            
+            if self.save_data(&data).await.is_err() {
+                return Err("Ошибка сохранения".to_string());
+            }
+            
+            Ok(completion.text)
+        } else {
+            Err("У вас нет задач".to_string())
+        }
+    }
+
+    async fn set_task_recurrence(&self, chat_id: ChatId, task_id: &str, recurrence: Option<Recurrence>) -> Result<String, String> {
+        let _guard = self.lock.lock().await;
+        let mut data = self.load_data().await;
+        let chat_key = chat_id.0.to_string();
+        
+        if let Some(todos) = data.todos.get_mut(&chat_key) {
+            if let Some(todo) = todos.iter_mut().find(|t| t.id == task_id && t.is_active()) {
+                todo.recurrence = recurrence;
+                let task_text = todo.text.clone();
                 
-                if let Err(_) = self.save_data(&data).await {
+                if self.save_data(&data).await.is_err() {
                     return Err("Ошибка сохранения".to_string());
                 }
                 
                 Ok(task_text)
             } else {
-                Err("Задача с таким номером не найдена".to_string())
+                Err("Задача не найдена".to_string())
+            }
+        } else {
+            Err("У вас нет задач".to_string())
+        }
+    }
+
+    async fn edit_task(&self, chat_id: ChatId, task_id: &str, edit: &TaskEdit) -> Result<String, String> {
+        let _guard = self.lock.lock().await;
+        let mut data = self.load_data().await;
+        let chat_key = chat_id.0.to_string();
+        
+        if let Some(todos) = data.todos.get_mut(&chat_key) {
+            if let Some(todo) = todos.iter_mut().find(|t| t.id == task_id && t.is_active()) {
+                let old_text = todo.text.clone();
+                edit.apply(todo);
+                
+                if self.save_data(&data).await.is_err() {
+                    return Err("Ошибка сохранения".to_string());
+                }
+                
+                Ok(old_text)
+            } else {
+                Err("Задача не найдена".to_string())
             }
         } else {
             Err("У вас нет задач".to_string())
         }
     }
 
-    pub async fn remove_task(&self, chat_id: ChatId, task_index: usize) -> Result<String, String> {
+    async fn set_task_priority(&self, chat_id: ChatId, task_id: &str, priority: Priority) -> Result<String, String> {
         let _guard = self.lock.lock().await;
         let mut data = self.load_data().await;
         let chat_key = chat_id.0.to_string();
         
         if let Some(todos) = data.todos.get_mut(&chat_key) {
-            if task_index < todos.len() {
-                let removed_task = todos.remove(task_index);
+            if let Some(todo) = todos.iter_mut().find(|t| t.id == task_id && t.is_active()) {
+                todo.priority = priority;
+                let task_text = todo.text.clone();
                 
-                if let Err(_) = self.save_data(&data).await {
+                if self.save_data(&data).await.is_err() {
                     return Err("Ошибка сохранения".to_string());
                 }
                 
-                Ok(removed_task.text)
+                Ok(task_text)
             } else {
-                Err("Задача с таким номером не найдена".to_string())
+                Err("Задача не найдена".to_string())
             }
         } else {
             Err("У вас нет задач".to_string())
         }
     }
 
-    pub async fn clear_tasks(&self, chat_id: ChatId) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn reorder_tasks(&self, chat_id: ChatId, task_ids: &[String]) -> Result<(), String> {
         let _guard = self.lock.lock().await;
         let mut data = self.load_data().await;
         let chat_key = chat_id.0.to_string();
         
-        data.todos.insert(chat_key, Vec::new());
+        // Задачи в файле хранятся в порядке списка, позиции пересчитываются вместе с ним
+        if let Some(todos) = data.todos.get_mut(&chat_key) {
+            tasks::reorder_tasks(todos, task_ids)?;
+            self.save_data(&data).await.map_err(|_| "Ошибка сохранения".to_string())
+        } else {
+            Err("У вас нет задач".to_string())
+        }
+    }
+
+    async fn remove_task(&self, chat_id: ChatId, task_id: &str) -> Result<String, String> {
+        let _guard = self.lock.lock().await;
+        let mut data = self.load_data().await;
+        let chat_key = chat_id.0.to_string();
+        
+        if let Some(todos) = data.todos.get_mut(&chat_key) {
+            let task_text = tasks::trash_task(todos, task_id, Utc::now())?;
+            
+            if self.save_data(&data).await.is_err() {
+                return Err("Ошибка сохранения".to_string());
+            }
+            
+            Ok(task_text)
+        } else {
+            Err("У вас нет задач".to_string())
+        }
+    }
+
+    async fn clear_tasks(&self, chat_id: ChatId) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let _guard = self.lock.lock().await;
+        let mut data = self.load_data().await;
+        let chat_key = chat_id.0.to_string();
+        let list_id = data.current_list_id(&chat_key);
+        
+        if let Some(todos) = data.todos.get_mut(&chat_key) {
+            let now = Utc::now();
+            for todo in todos.iter_mut().filter(|t| t.list_id == list_id && t.is_active()) {
+                todo.deleted_at = Some(now);
+            }
+        }
         self.save_data(&data).await?;
         Ok(())
     }
 
+    async fn restore_tasks(&self, chat_id: ChatId, todos: Vec<TodoItem>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let _guard = self.lock.lock().await;
+        let mut data = self.load_data().await;
+        tasks::restore_tasks(data.todos.entry(chat_id.0.to_string()).or_default(), todos);
+        self.save_data(&data).await?;
+        Ok(())
+    }
+
+    // Методы для работы с архивом и корзиной
+    async fn get_archived_tasks(&self, chat_id: ChatId) -> Vec<TodoItem> {
+        let _guard = self.lock.lock().await;
+        let data = self.load_data().await;
+        let mut archived: Vec<TodoItem> = data
+            .todos
+            .get(&chat_id.0.to_string())
+            .map(|todos| todos.iter().filter(|t| t.archived_at.is_some()).cloned().collect())
+            .unwrap_or_default();
+        archived.sort_by_key(|t| Reverse(t.archived_at));
+        archived
+    }
+
+    async fn get_trashed_tasks(&self, chat_id: ChatId) -> Vec<TodoItem> {
+        let _guard = self.lock.lock().await;
+        let data = self.load_data().await;
+        let mut trashed: Vec<TodoItem> = data
+            .todos
+            .get(&chat_id.0.to_string())
+            .map(|todos| todos.iter().filter(|t| t.deleted_at.is_some()).cloned().collect())
+            .unwrap_or_default();
+        trashed.sort_by_key(|t| Reverse(t.deleted_at));
+        trashed
+    }
+
+    async fn restore_task(&self, chat_id: ChatId, task_id: &str) -> Result<String, String> {
+        let _guard = self.lock.lock().await;
+        let mut data = self.load_data().await;
+        let todos = data.todos.entry(chat_id.0.to_string()).or_default();
+        let task_text = tasks::restore_task(todos, task_id)?;
+        self.save_data(&data).await.map_err(|_| "Ошибка сохранения".to_string())?;
+        Ok(task_text)
+    }
+
+    async fn empty_trash(&self, chat_id: ChatId) -> Result<usize, String> {
+        let _guard = self.lock.lock().await;
+        let mut data = self.load_data().await;
+        let todos = data.todos.entry(chat_id.0.to_string()).or_default();
+        let purged = tasks::purge_trash(todos, Utc::now());
+        self.save_data(&data).await.map_err(|_| "Ошибка сохранения".to_string())?;
+        Ok(purged)
+    }
+
+    async fn archive_completed_tasks(&self, completed_before: DateTime<Utc>) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let _guard = self.lock.lock().await;
+        let mut data = self.load_data().await;
+        let now = Utc::now();
+        let archived = data.todos.values_mut().map(|todos| tasks::archive_completed(todos, completed_before, now)).sum();
+        // Файл перезаписывается, только если что-то изменилось
+        if archived > 0 {
+            self.save_data(&data).await?;
+        }
+        Ok(archived)
+    }
+
+    async fn purge_trash(&self, deleted_before: DateTime<Utc>) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let _guard = self.lock.lock().await;
+        let mut data = self.load_data().await;
+        let purged = data.todos.values_mut().map(|todos| tasks::purge_trash(todos, deleted_before)).sum();
+        if purged > 0 {
+            self.save_data(&data).await?;
+        }
+        Ok(purged)
+    }
+
+    // Методы для работы со списками
+    async fn get_lists(&self, chat_id: ChatId) -> Vec<TaskList> {
+        let _guard = self.lock.lock().await;
+        let data = self.load_data().await;
+        data.lists.get(&chat_id.0.to_string()).cloned().unwrap_or_default()
+    }
+
+    async fn create_list(&self, chat_id: ChatId, name: &str) -> Result<TaskList, String> {
+        let _guard = self.lock.lock().await;
+        let mut data = self.load_data().await;
+        
+        let list = tasks::create_list(data.lists.entry(chat_id.0.to_string()).or_default(), name)?;
+        self.save_data(&data).await.map_err(|_| "Ошибка сохранения".to_string())?;
+        Ok(list)
+    }
+
+    async fn rename_list(&self, chat_id: ChatId, list_id: &str, name: &str) -> Result<String, String> {
+        let _guard = self.lock.lock().await;
+        let mut data = self.load_data().await;
+        
+        let old_name = tasks::rename_list(data.lists.entry(chat_id.0.to_string()).or_default(), list_id, name)?;
+        self.save_data(&data).await.map_err(|_| "Ошибка сохранения".to_string())?;
+        Ok(old_name)
+    }
+
+    async fn delete_list(&self, chat_id: ChatId, list_id: &str) -> Result<String, String> {
+        let _guard = self.lock.lock().await;
+        let mut data = self.load_data().await;
+        let chat_key = chat_id.0.to_string();
+        
+        let lists = data.lists.entry(chat_key.clone()).or_default();
+        let position = lists.iter().position(|l| l.id == list_id).ok_or_else(|| "Список не найден".to_string())?;
+        let removed_list = lists.remove(position);
+        
+        for todo in data.todos.entry(chat_key.clone()).or_default().iter_mut() {
+            if todo.list_id.as_deref() == Some(list_id) {
+                todo.list_id = None;
+            }
+        }
+        if data.current_lists.get(&chat_key).map(String::as_str) == Some(list_id) {
+            data.current_lists.remove(&chat_key);
+        }
+        
+        self.save_data(&data).await.map_err(|_| "Ошибка сохранения".to_string())?;
+        Ok(removed_list.name)
+    }
+
+    async fn get_current_list(&self, chat_id: ChatId) -> Option<TaskList> {
+        let _guard = self.lock.lock().await;
+        let data = self.load_data().await;
+        let chat_key = chat_id.0.to_string();
+        let list_id = data.current_list_id(&chat_key)?;
+        data.lists.get(&chat_key)?.iter().find(|l| l.id == list_id).cloned()
+    }
+
+    async fn set_current_list(&self, chat_id: ChatId, list_id: Option<&str>) -> Result<(), String> {
+        let _guard = self.lock.lock().await;
+        let mut data = self.load_data().await;
+        let chat_key = chat_id.0.to_string();
+        
+        match list_id {
+            Some(list_id) => {
+                if !data.has_list(&chat_key, list_id) {
+                    return Err("Список не найден".to_string());
+                }
+                data.current_lists.insert(chat_key, list_id.to_string());
+            }
+            None => {
+                data.current_lists.remove(&chat_key);
+            }
+        }
+        self.save_data(&data).await.map_err(|_| "Ошибка сохранения".to_string())
+    }
+
+    async fn move_task_to_list(&self, chat_id: ChatId, task_id: &str, list_id: Option<&str>) -> Result<String, String> {
+        let _guard = self.lock.lock().await;
+        let mut data = self.load_data().await;
+        let chat_key = chat_id.0.to_string();
+        
+        if list_id.is_some_and(|list_id| !data.has_list(&chat_key, list_id)) {
+            return Err("Список не найден".to_string());
+        }
+        if let Some(todos) = data.todos.get_mut(&chat_key) {
+            let task_text = tasks::move_task(todos, task_id, list_id)?;
+            self.save_data(&data).await.map_err(|_| "Ошибка сохранения".to_string())?;
+            Ok(task_text)
+        } else {
+            Err("У вас нет задач".to_string())
+        }
+    }
+
     // Методы для работы с напоминаниями
-    pub async fn get_user_reminders(&self, chat_id: ChatId) -> UserReminders {
+    async fn get_user_reminders(&self, chat_id: ChatId) -> UserReminders {
         let _guard = self.lock.lock().await;
         let data = self.load_data().await;
         let chat_key = chat_id.0.to_string();
         data.reminders.get(&chat_key).cloned().unwrap_or_default()
     }
 
-    pub async fn save_user_reminders(&self, chat_id: ChatId, reminders: UserReminders) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn save_user_reminders(&self, chat_id: ChatId, reminders: UserReminders) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let _guard = self.lock.lock().await;
         let mut data = self.load_data().await;
         let chat_key = chat_id.0.to_string();
@@ -141,7 +442,7 @@ impl JsonStorage {
         self.save_data(&data).await
     }
 
-    pub async fn add_counter_reminder(&self, chat_id: ChatId, reminder: CounterReminder) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn add_counter_reminder(&self, chat_id: ChatId, reminder: CounterReminder) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let _guard = self.lock.lock().await;
         let mut data = self.load_data().await;
         let chat_key = chat_id.0.to_string();
@@ -150,7 +451,23 @@ impl JsonStorage {
         self.save_data(&data).await
     }
 
-    pub async fn toggle_global_reminders(&self, chat_id: ChatId) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    async fn delete_counter_reminder(&self, chat_id: ChatId, counter_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let _guard = self.lock.lock().await;
+        let mut data = self.load_data().await;
+        let chat_key = chat_id.0.to_string();
+        if let Some(user_reminders) = data.reminders.get_mut(&chat_key) {
+            user_reminders.reminders.remove(counter_id);
+        }
+        if let Some(readings) = data.meter_readings.get_mut(&chat_key) {
+            readings.retain(|r| r.counter_id != counter_id);
+        }
+        if let Some(tariffs) = data.tariffs.get_mut(&chat_key) {
+            tariffs.retain(|t| t.counter_id != counter_id);
+        }
+        self.save_data(&data).await
+    }
+
+    async fn toggle_global_reminders(&self, chat_id: ChatId) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
         let _guard = self.lock.lock().await;
         let mut data = self.load_data().await;
         let chat_key = chat_id.0.to_string();
@@ -160,35 +477,149 @@ impl JsonStorage {
         Ok(new_state)
     }
 
-    pub async fn mark_counter_completed(&self, chat_id: ChatId, counter_type: CounterType) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn mark_counter_completed(&self, chat_id: ChatId, counter_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let _guard = self.lock.lock().await;
         let mut data = self.load_data().await;
         let chat_key = chat_id.0.to_string();
         let user_reminders = data.reminders.entry(chat_key).or_insert_with(UserReminders::default);
-        if let Some(reminder) = user_reminders.get_reminder_mut(&counter_type) {
+        if let Some(reminder) = user_reminders.get_reminder_mut(counter_id) {
             reminder.mark_completed();
         }
         self.save_data(&data).await
     }
 
-    pub async fn get_all_reminders(&self) -> HashMap<String, UserReminders> {
+    async fn get_all_reminders(&self) -> HashMap<String, UserReminders> {
         let _guard = self.lock.lock().await;
         let data = self.load_data().await;
         data.reminders
     }
 
     // Метод для сброса статусов в новом месяце
-    pub async fn reset_monthly_statuses(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn reset_monthly_statuses(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let _guard = self.lock.lock().await;
         let mut data = self.load_data().await;
         let moscow_now = chrono_tz::Europe::Moscow.from_utc_datetime(&chrono::Utc::now().naive_utc());
         let current_month = moscow_now.format("%Y-%m").to_string();
         
-        for (_, user_reminders) in data.reminders.iter_mut() {
-            for (_, reminder) in user_reminders.reminders.iter_mut() {
+        for user_reminders in data.reminders.values_mut() {
+            for reminder in user_reminders.reminders.values_mut() {
                 reminder.reset_for_new_month(&current_month);
             }
         }
         
         self.save_data(&data).await
-    }}
+    }
+
+    // Показания счетчиков
+    async fn add_meter_readings(&self, chat_id: ChatId, readings: Vec<MeterReading>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let _guard = self.lock.lock().await;
+        let mut data = self.load_data().await;
+        data.meter_readings.entry(chat_id.0.to_string()).or_default().extend(readings);
+        self.save_data(&data).await
+    }
+
+    async fn get_meter_readings(&self, chat_id: ChatId, counter_id: &str) -> Vec<MeterReading> {
+        let _guard = self.lock.lock().await;
+        let data = self.load_data().await;
+        let mut readings: Vec<MeterReading> = data.meter_readings
+            .get(&chat_id.0.to_string())
+            .map(|readings| readings.iter().filter(|r| r.counter_id == counter_id).cloned().collect())
+            .unwrap_or_default();
+        readings.sort_by_key(|r| r.taken_at);
+        readings
+    }
+
+    // Тарифы
+    async fn set_tariffs(&self, chat_id: ChatId, tariffs: Vec<Tariff>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let _guard = self.lock.lock().await;
+        let mut data = self.load_data().await;
+        let saved = data.tariffs.entry(chat_id.0.to_string()).or_default();
+        for tariff in tariffs {
+            saved.retain(|t| !(t.counter_id == tariff.counter_id && t.zone == tariff.zone && t.effective_from == tariff.effective_from));
+            saved.push(tariff);
+        }
+        self.save_data(&data).await
+    }
+
+    async fn get_tariffs(&self, chat_id: ChatId, counter_id: &str) -> Vec<Tariff> {
+        let _guard = self.lock.lock().await;
+        let data = self.load_data().await;
+        let mut tariffs: Vec<Tariff> = data.tariffs
+            .get(&chat_id.0.to_string())
+            .map(|tariffs| tariffs.iter().filter(|t| t.counter_id == counter_id).cloned().collect())
+            .unwrap_or_default();
+        tariffs.sort_by_key(|t| t.effective_from);
+        tariffs
+    }
+
+    // Методы для напоминаний о задачах
+    async fn add_task_reminder(&self, reminder: TaskReminder) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let _guard = self.lock.lock().await;
+        let mut data = self.load_data().await;
+        data.task_reminders.push(reminder);
+        self.save_data(&data).await
+    }
+
+    async fn take_due_task_reminders(&self, now: DateTime<Utc>) -> Result<Vec<TaskReminder>, Box<dyn std::error::Error + Send + Sync>> {
+        let _guard = self.lock.lock().await;
+        let mut data = self.load_data().await;
+        let (due, pending): (Vec<_>, Vec<_>) = data.task_reminders
+            .into_iter()
+            .partition(|r| r.remind_at <= now);
+        if due.is_empty() {
+            return Ok(due);
+        }
+
+        data.task_reminders = pending;
+        self.save_data(&data).await?;
+        Ok(due)
+    }
+
+    async fn remove_task_reminders(&self, chat_id: ChatId, task_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let _guard = self.lock.lock().await;
+        let mut data = self.load_data().await;
+        data.task_reminders.retain(|r| !(r.chat_id == chat_id.0 && r.task_id == task_id));
+        self.save_data(&data).await
+    }
+
+    async fn get_user_state(&self, chat_id: ChatId) -> Option<SavedUserState> {
+        let _guard = self.lock.lock().await;
+        let data = self.load_data().await;
+        let saved = data.user_states.get(&chat_id.0.to_string())?;
+        serde_json::from_value(saved.clone())
+            .map_err(|e| log::warn!("Dropping unreadable dialogue state {}: {}", saved, e))
+            .ok()
+    }
+
+    async fn save_user_state(&self, chat_id: ChatId, state: UserState) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let _guard = self.lock.lock().await;
+        let mut data = self.load_data().await;
+        let chat_key = chat_id.0.to_string();
+
+        if state == UserState::Default {
+            // Без изменений файл не переписываем: сброс состояния происходит почти на каждом сообщении
+            if data.user_states.remove(&chat_key).is_none() {
+                return Ok(());
+            }
+        } else {
+            let saved = SavedUserState { state, updated_at: Utc::now() };
+            data.user_states.insert(chat_key, serde_json::to_value(saved)?);
+        }
+        self.save_data(&data).await
+    }
+
+    async fn purge_user_states(&self, updated_before: DateTime<Utc>) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let _guard = self.lock.lock().await;
+        let mut data = self.load_data().await;
+        let before = data.user_states.len();
+        data.user_states.retain(|_, saved| {
+            serde_json::from_value::<SavedUserState>(saved.clone()).is_ok_and(|saved| saved.updated_at > updated_before)
+        });
+
+        let purged = before - data.user_states.len();
+        if purged > 0 {
+            self.save_data(&data).await?;
+        }
+        Ok(purged)
+    }
+}
@@ -1,67 +1,598 @@
+use std::cmp::Reverse;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
+use chrono::{DateTime, TimeZone, Utc};
 use teloxide::types::ChatId;
+use async_trait::async_trait;
 
-use crate::models::TodoItem;
+use crate::models::{TodoItem, TaskEdit, UserReminders, CounterReminder, MeterReading, Tariff, TaskReminder, Recurrence, Priority, TaskList, UserState, SavedUserState};
+use super::Storage;
+use super::tasks::{self, complete_task, next_position};
 
-pub type TodoStorage = Arc<Mutex<HashMap<ChatId, Vec<TodoItem>>>>;
+/// Данные хранилища в памяти, по структуре повторяют `JsonData`
+#[derive(Default)]
+struct MemoryData {
+    todos: HashMap<String, Vec<TodoItem>>,
+    reminders: HashMap<String, UserReminders>,
+    task_reminders: Vec<TaskReminder>,
+    meter_readings: HashMap<String, Vec<MeterReading>>,
+    tariffs: HashMap<String, Vec<Tariff>>,
+    lists: HashMap<String, Vec<TaskList>>,
+    current_lists: HashMap<String, String>,
+    user_states: HashMap<String, SavedUserState>,
+}
+
+impl MemoryData {
+    fn current_list_id(&self, chat_key: &str) -> Option<String> {
+        self.current_lists.get(chat_key).cloned()
+    }
+
+    fn has_list(&self, chat_key: &str, list_id: &str) -> bool {
+        self.lists.get(chat_key).is_some_and(|lists| lists.iter().any(|l| l.id == list_id))
+    }
+}
 
-#[derive(Clone)]
+/// Хранилище без диска и базы данных: всё теряется при перезапуске.
+/// Удобно для локального запуска и тестов.
+#[derive(Clone, Default)]
 pub struct MemoryStorage {
-    storage: TodoStorage,
+    data: Arc<Mutex<MemoryData>>,
 }
 
 impl MemoryStorage {
     pub fn new() -> Self {
-        Self {
-            storage: Arc::new(Mutex::new(HashMap::new())),
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl Storage for MemoryStorage {
+    async fn add_task(&self, chat_id: ChatId, mut todo_item: TodoItem) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut data = self.data.lock().await;
+        let chat_key = chat_id.0.to_string();
+
+        let todos = data.todos.entry(chat_key).or_default();
+        todo_item.position = next_position(todos);
+        todos.push(todo_item);
+        Ok(())
+    }
+
+    async fn get_tasks(&self, chat_id: ChatId) -> Vec<TodoItem> {
+        let data = self.data.lock().await;
+        let chat_key = chat_id.0.to_string();
+        data.todos
+            .get(&chat_key)
+            .map(|todos| todos.iter().filter(|t| t.is_active()).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    async fn get_tags(&self, chat_id: ChatId) -> Vec<String> {
+        let data = self.data.lock().await;
+        let chat_key = chat_id.0.to_string();
+        let list_id = data.current_list_id(&chat_key);
+        let mut tags: Vec<String> = data
+            .todos
+            .get(&chat_key)
+            .map(|todos| {
+                todos.iter()
+                    .filter(|t| t.list_id == list_id && t.is_active())
+                    .flat_map(|t| t.tags.iter().cloned())
+                    .collect()
+            })
+            .unwrap_or_default();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+
+    async fn mark_task_completed(&self, chat_id: ChatId, task_id: &str) -> Result<String, String> {
+        let mut data = self.data.lock().await;
+        let chat_key = chat_id.0.to_string();
+
+        if let Some(todos) = data.todos.get_mut(&chat_key) {
+            complete_task(todos, task_id, Utc::now()).map(|completion| completion.text)
+        } else {
+            Err("У вас нет задач".to_string())
         }
     }
 
-    #[allow(dead_code)]
-    pub fn get_storage(&self) -> TodoStorage {
-        self.storage.clone()
+    async fn set_task_recurrence(&self, chat_id: ChatId, task_id: &str, recurrence: Option<Recurrence>) -> Result<String, String> {
+        let mut data = self.data.lock().await;
+        let chat_key = chat_id.0.to_string();
+
+        if let Some(todos) = data.todos.get_mut(&chat_key) {
+            if let Some(todo) = todos.iter_mut().find(|t| t.id == task_id && t.is_active()) {
+                todo.recurrence = recurrence;
+                Ok(todo.text.clone())
+            } else {
+                Err("Задача не найдена".to_string())
+            }
+        } else {
+            Err("У вас нет задач".to_string())
+        }
     }
 
-    pub async fn add_task(&self, chat_id: ChatId, text: &str) {
-        let todo_item = TodoItem::new(text.to_string());
-        let mut storage = self.storage.lock().await;
-        let todos = storage.entry(chat_id).or_insert_with(Vec::new);
-        todos.push(todo_item);
+    async fn edit_task(&self, chat_id: ChatId, task_id: &str, edit: &TaskEdit) -> Result<String, String> {
+        let mut data = self.data.lock().await;
+        let chat_key = chat_id.0.to_string();
+
+        if let Some(todos) = data.todos.get_mut(&chat_key) {
+            if let Some(todo) = todos.iter_mut().find(|t| t.id == task_id && t.is_active()) {
+                let old_text = todo.text.clone();
+                edit.apply(todo);
+                Ok(old_text)
+            } else {
+                Err("Задача не найдена".to_string())
+            }
+        } else {
+            Err("У вас нет задач".to_string())
+        }
+    }
+
+    async fn set_task_priority(&self, chat_id: ChatId, task_id: &str, priority: Priority) -> Result<String, String> {
+        let mut data = self.data.lock().await;
+        let chat_key = chat_id.0.to_string();
+
+        if let Some(todos) = data.todos.get_mut(&chat_key) {
+            if let Some(todo) = todos.iter_mut().find(|t| t.id == task_id && t.is_active()) {
+                todo.priority = priority;
+                Ok(todo.text.clone())
+            } else {
+                Err("Задача не найдена".to_string())
+            }
+        } else {
+            Err("У вас нет задач".to_string())
+        }
+    }
+
+    async fn reorder_tasks(&self, chat_id: ChatId, task_ids: &[String]) -> Result<(), String> {
+        let mut data = self.data.lock().await;
+        let chat_key = chat_id.0.to_string();
+
+        if let Some(todos) = data.todos.get_mut(&chat_key) {
+            tasks::reorder_tasks(todos, task_ids)
+        } else {
+            Err("У вас нет задач".to_string())
+        }
+    }
+
+    async fn remove_task(&self, chat_id: ChatId, task_id: &str) -> Result<String, String> {
+        let mut data = self.data.lock().await;
+        let chat_key = chat_id.0.to_string();
+
+        if let Some(todos) = data.todos.get_mut(&chat_key) {
+            tasks::trash_task(todos, task_id, Utc::now())
+        } else {
+            Err("У вас нет задач".to_string())
+        }
+    }
+
+    async fn clear_tasks(&self, chat_id: ChatId) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut data = self.data.lock().await;
+        let chat_key = chat_id.0.to_string();
+        let list_id = data.current_list_id(&chat_key);
+        if let Some(todos) = data.todos.get_mut(&chat_key) {
+            let now = Utc::now();
+            for todo in todos.iter_mut().filter(|t| t.list_id == list_id && t.is_active()) {
+                todo.deleted_at = Some(now);
+            }
+        }
+        Ok(())
+    }
+
+    async fn restore_tasks(&self, chat_id: ChatId, todos: Vec<TodoItem>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut data = self.data.lock().await;
+        tasks::restore_tasks(data.todos.entry(chat_id.0.to_string()).or_default(), todos);
+        Ok(())
+    }
+
+    // Методы для работы с архивом и корзиной
+    async fn get_archived_tasks(&self, chat_id: ChatId) -> Vec<TodoItem> {
+        let data = self.data.lock().await;
+        let mut archived: Vec<TodoItem> = data
+            .todos
+            .get(&chat_id.0.to_string())
+            .map(|todos| todos.iter().filter(|t| t.archived_at.is_some()).cloned().collect())
+            .unwrap_or_default();
+        archived.sort_by_key(|t| Reverse(t.archived_at));
+        archived
+    }
+
+    async fn get_trashed_tasks(&self, chat_id: ChatId) -> Vec<TodoItem> {
+        let data = self.data.lock().await;
+        let mut trashed: Vec<TodoItem> = data
+            .todos
+            .get(&chat_id.0.to_string())
+            .map(|todos| todos.iter().filter(|t| t.deleted_at.is_some()).cloned().collect())
+            .unwrap_or_default();
+        trashed.sort_by_key(|t| Reverse(t.deleted_at));
+        trashed
+    }
+
+    async fn restore_task(&self, chat_id: ChatId, task_id: &str) -> Result<String, String> {
+        let mut data = self.data.lock().await;
+        let todos = data.todos.entry(chat_id.0.to_string()).or_default();
+        tasks::restore_task(todos, task_id)
+    }
+
+    async fn empty_trash(&self, chat_id: ChatId) -> Result<usize, String> {
+        let mut data = self.data.lock().await;
+        let todos = data.todos.entry(chat_id.0.to_string()).or_default();
+        Ok(tasks::purge_trash(todos, Utc::now()))
+    }
+
+    async fn archive_completed_tasks(&self, completed_before: DateTime<Utc>) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let mut data = self.data.lock().await;
+        let now = Utc::now();
+        Ok(data.todos.values_mut().map(|todos| tasks::archive_completed(todos, completed_before, now)).sum())
     }
 
-    pub async fn get_tasks(&self, chat_id: ChatId) -> Vec<TodoItem> {
-        let storage = self.storage.lock().await;
-        storage.get(&chat_id).cloned().unwrap_or_default()
+    async fn purge_trash(&self, deleted_before: DateTime<Utc>) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let mut data = self.data.lock().await;
+        Ok(data.todos.values_mut().map(|todos| tasks::purge_trash(todos, deleted_before)).sum())
+    }
+
+    // Методы для работы со списками
+    async fn get_lists(&self, chat_id: ChatId) -> Vec<TaskList> {
+        let data = self.data.lock().await;
+        data.lists.get(&chat_id.0.to_string()).cloned().unwrap_or_default()
+    }
+
+    async fn create_list(&self, chat_id: ChatId, name: &str) -> Result<TaskList, String> {
+        let mut data = self.data.lock().await;
+        tasks::create_list(data.lists.entry(chat_id.0.to_string()).or_default(), name)
+    }
+
+    async fn rename_list(&self, chat_id: ChatId, list_id: &str, name: &str) -> Result<String, String> {
+        let mut data = self.data.lock().await;
+        tasks::rename_list(data.lists.entry(chat_id.0.to_string()).or_default(), list_id, name)
+    }
+
+    async fn delete_list(&self, chat_id: ChatId, list_id: &str) -> Result<String, String> {
+        let mut data = self.data.lock().await;
+        let chat_key = chat_id.0.to_string();
+
+        let lists = data.lists.entry(chat_key.clone()).or_default();
+        let position = lists.iter().position(|l| l.id == list_id).ok_or_else(|| "Список не найден".to_string())?;
+        let removed_list = lists.remove(position);
+
+        for todo in data.todos.entry(chat_key.clone()).or_default().iter_mut() {
+            if todo.list_id.as_deref() == Some(list_id) {
+                todo.list_id = None;
+            }
+        }
+        if data.current_lists.get(&chat_key).map(String::as_str) == Some(list_id) {
+            data.current_lists.remove(&chat_key);
+        }
+        Ok(removed_list.name)
+    }
+
+    async fn get_current_list(&self, chat_id: ChatId) -> Option<TaskList> {
+        let data = self.data.lock().await;
+        let chat_key = chat_id.0.to_string();
+        let list_id = data.current_list_id(&chat_key)?;
+        data.lists.get(&chat_key)?.iter().find(|l| l.id == list_id).cloned()
+    }
+
+    async fn set_current_list(&self, chat_id: ChatId, list_id: Option<&str>) -> Result<(), String> {
+        let mut data = self.data.lock().await;
+        let chat_key = chat_id.0.to_string();
+
+        match list_id {
+            Some(list_id) => {
+                if !data.has_list(&chat_key, list_id) {
+                    return Err("Список не найден".to_string());
+                }
+                data.current_lists.insert(chat_key, list_id.to_string());
+            }
+            None => {
+                data.current_lists.remove(&chat_key);
+            }
+        }
+        Ok(())
     }
 
-    pub async fn mark_task_completed(&self, chat_id: ChatId, task_index: usize) -> Result<String, String> {
-        let mut storage = self.storage.lock().await;
-        let todos = storage.entry(chat_id).or_insert_with(Vec::new);
+    async fn move_task_to_list(&self, chat_id: ChatId, task_id: &str, list_id: Option<&str>) -> Result<String, String> {
+        let mut data = self.data.lock().await;
+        let chat_key = chat_id.0.to_string();
 
-        if task_index < todos.len() {
-            todos[task_index].mark_completed();
-            Ok(todos[task_index].text.clone())
+        if list_id.is_some_and(|list_id| !data.has_list(&chat_key, list_id)) {
+            return Err("Список не найден".to_string());
+        }
+        if let Some(todos) = data.todos.get_mut(&chat_key) {
+            tasks::move_task(todos, task_id, list_id)
         } else {
-            Err("Задача с таким номером не найдена".to_string())
+            Err("У вас нет задач".to_string())
+        }
+    }
+
+    // Методы для работы с напоминаниями
+    async fn get_user_reminders(&self, chat_id: ChatId) -> UserReminders {
+        let data = self.data.lock().await;
+        let chat_key = chat_id.0.to_string();
+        data.reminders.get(&chat_key).cloned().unwrap_or_default()
+    }
+
+    async fn save_user_reminders(&self, chat_id: ChatId, reminders: UserReminders) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut data = self.data.lock().await;
+        data.reminders.insert(chat_id.0.to_string(), reminders);
+        Ok(())
+    }
+
+    async fn add_counter_reminder(&self, chat_id: ChatId, reminder: CounterReminder) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut data = self.data.lock().await;
+        let user_reminders = data.reminders.entry(chat_id.0.to_string()).or_default();
+        user_reminders.add_reminder(reminder);
+        Ok(())
+    }
+
+    async fn delete_counter_reminder(&self, chat_id: ChatId, counter_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut data = self.data.lock().await;
+        let chat_key = chat_id.0.to_string();
+        if let Some(user_reminders) = data.reminders.get_mut(&chat_key) {
+            user_reminders.reminders.remove(counter_id);
         }
+        if let Some(readings) = data.meter_readings.get_mut(&chat_key) {
+            readings.retain(|r| r.counter_id != counter_id);
+        }
+        if let Some(tariffs) = data.tariffs.get_mut(&chat_key) {
+            tariffs.retain(|t| t.counter_id != counter_id);
+        }
+        Ok(())
+    }
+
+    async fn toggle_global_reminders(&self, chat_id: ChatId) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut data = self.data.lock().await;
+        let user_reminders = data.reminders.entry(chat_id.0.to_string()).or_default();
+        Ok(user_reminders.toggle_global())
+    }
+
+    async fn mark_counter_completed(&self, chat_id: ChatId, counter_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut data = self.data.lock().await;
+        let user_reminders = data.reminders.entry(chat_id.0.to_string()).or_default();
+        if let Some(reminder) = user_reminders.get_reminder_mut(counter_id) {
+            reminder.mark_completed();
+        }
+        Ok(())
+    }
+
+    async fn get_all_reminders(&self) -> HashMap<String, UserReminders> {
+        let data = self.data.lock().await;
+        data.reminders.clone()
+    }
+
+    // Метод для сброса статусов в новом месяце
+    async fn reset_monthly_statuses(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut data = self.data.lock().await;
+        let moscow_now = chrono_tz::Europe::Moscow.from_utc_datetime(&chrono::Utc::now().naive_utc());
+        let current_month = moscow_now.format("%Y-%m").to_string();
+
+        for user_reminders in data.reminders.values_mut() {
+            for reminder in user_reminders.reminders.values_mut() {
+                reminder.reset_for_new_month(&current_month);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Показания счетчиков
+    async fn add_meter_readings(&self, chat_id: ChatId, readings: Vec<MeterReading>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut data = self.data.lock().await;
+        data.meter_readings.entry(chat_id.0.to_string()).or_default().extend(readings);
+        Ok(())
+    }
+
+    async fn get_meter_readings(&self, chat_id: ChatId, counter_id: &str) -> Vec<MeterReading> {
+        let data = self.data.lock().await;
+        let mut readings: Vec<MeterReading> = data.meter_readings
+            .get(&chat_id.0.to_string())
+            .map(|readings| readings.iter().filter(|r| r.counter_id == counter_id).cloned().collect())
+            .unwrap_or_default();
+        readings.sort_by_key(|r| r.taken_at);
+        readings
+    }
+
+    // Тарифы
+    async fn set_tariffs(&self, chat_id: ChatId, tariffs: Vec<Tariff>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut data = self.data.lock().await;
+        let saved = data.tariffs.entry(chat_id.0.to_string()).or_default();
+        for tariff in tariffs {
+            saved.retain(|t| !(t.counter_id == tariff.counter_id && t.zone == tariff.zone && t.effective_from == tariff.effective_from));
+            saved.push(tariff);
+        }
+        Ok(())
+    }
+
+    async fn get_tariffs(&self, chat_id: ChatId, counter_id: &str) -> Vec<Tariff> {
+        let data = self.data.lock().await;
+        let mut tariffs: Vec<Tariff> = data.tariffs
+            .get(&chat_id.0.to_string())
+            .map(|tariffs| tariffs.iter().filter(|t| t.counter_id == counter_id).cloned().collect())
+            .unwrap_or_default();
+        tariffs.sort_by_key(|t| t.effective_from);
+        tariffs
+    }
+
+    // Методы для напоминаний о задачах
+    async fn add_task_reminder(&self, reminder: TaskReminder) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut data = self.data.lock().await;
+        data.task_reminders.push(reminder);
+        Ok(())
+    }
+
+    async fn take_due_task_reminders(&self, now: DateTime<Utc>) -> Result<Vec<TaskReminder>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut data = self.data.lock().await;
+        let (due, pending) = std::mem::take(&mut data.task_reminders)
+            .into_iter()
+            .partition(|r| r.remind_at <= now);
+        data.task_reminders = pending;
+        Ok(due)
     }
 
-    pub async fn remove_task(&self, chat_id: ChatId, task_index: usize) -> Result<String, String> {
-        let mut storage = self.storage.lock().await;
-        let todos = storage.entry(chat_id).or_insert_with(Vec::new);
+    async fn remove_task_reminders(&self, chat_id: ChatId, task_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut data = self.data.lock().await;
+        data.task_reminders.retain(|r| !(r.chat_id == chat_id.0 && r.task_id == task_id));
+        Ok(())
+    }
+
+    async fn get_user_state(&self, chat_id: ChatId) -> Option<SavedUserState> {
+        let data = self.data.lock().await;
+        data.user_states.get(&chat_id.0.to_string()).cloned()
+    }
 
-        if task_index < todos.len() {
-            let removed_task = todos.remove(task_index);
-            Ok(removed_task.text)
+    async fn save_user_state(&self, chat_id: ChatId, state: UserState) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut data = self.data.lock().await;
+        let chat_key = chat_id.0.to_string();
+        if state == UserState::Default {
+            data.user_states.remove(&chat_key);
         } else {
-            Err("Задача с таким номером не найдена".to_string())
+            data.user_states.insert(chat_key, SavedUserState { state, updated_at: Utc::now() });
         }
+        Ok(())
     }
 
-    pub async fn clear_tasks(&self, chat_id: ChatId) {
-        let mut storage = self.storage.lock().await;
-        storage.insert(chat_id, Vec::new());
+    async fn purge_user_states(&self, updated_before: DateTime<Utc>) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let mut data = self.data.lock().await;
+        let before = data.user_states.len();
+        data.user_states.retain(|_, saved| saved.updated_at > updated_before);
+        Ok(before - data.user_states.len())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CounterType;
+
+    #[tokio::test]
+    async fn test_task_lifecycle() {
+        let storage = MemoryStorage::new();
+        let chat_id = ChatId(1);
+
+        assert_eq!(storage.mark_task_completed(chat_id, "missing").await, Err("У вас нет задач".to_string()));
+
+        storage.add_task(chat_id, TodoItem::new("Купить молоко".to_string())).await.unwrap();
+        storage.add_task(chat_id, TodoItem::new("Позвонить маме".to_string())).await.unwrap();
+        let tasks = storage.get_tasks(chat_id).await;
+        assert_eq!(tasks.len(), 2);
+
+        assert_eq!(storage.mark_task_completed(chat_id, &tasks[0].id).await, Ok("Купить молоко".to_string()));
+        assert!(storage.get_tasks(chat_id).await[0].is_completed());
+        assert!(storage.mark_task_completed(chat_id, "missing").await.is_err());
+        // Чужой чат не может изменить задачу по её id
+        assert!(storage.remove_task(ChatId(2), &tasks[1].id).await.is_err());
+        assert!(storage.edit_task(ChatId(2), &tasks[1].id, &TaskEdit::text("Чужая".to_string())).await.is_err());
+
+        assert_eq!(storage.edit_task(chat_id, &tasks[0].id, &TaskEdit::text("Купить кефир".to_string())).await, Ok("Купить молоко".to_string()));
+        assert_eq!(storage.get_tasks(chat_id).await[0].text, "Купить кефир");
+        let edit = TaskEdit { tags: Some(vec!["еда".to_string()]), ..TaskEdit::text("Купить кефир".to_string()) };
+        storage.edit_task(chat_id, &tasks[0].id, &edit).await.unwrap();
+        assert_eq!(storage.get_tasks(chat_id).await[0].tags, vec!["еда".to_string()]);
+
+        assert_eq!(storage.remove_task(chat_id, &tasks[1].id).await, Ok("Позвонить маме".to_string()));
+        storage.restore_tasks(chat_id, vec![tasks[1].clone()]).await.unwrap();
+        assert_eq!(storage.get_tasks(chat_id).await[1].id, tasks[1].id);
+        storage.clear_tasks(chat_id).await.unwrap();
+        assert!(storage.get_tasks(chat_id).await.is_empty());
+        assert!(storage.get_tasks(ChatId(2)).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_recurring_task_spawns_next_occurrence() {
+        let storage = MemoryStorage::new();
+        let chat_id = ChatId(1);
+
+        storage.add_task(chat_id, TodoItem::new("Уборка".to_string())).await.unwrap();
+        let task_id = storage.get_tasks(chat_id).await[0].id.clone();
+        storage.set_task_recurrence(chat_id, &task_id, Some(Recurrence::Weekly(vec![5]))).await.unwrap();
+
+        storage.mark_task_completed(chat_id, &task_id).await.unwrap();
+        // Повторное выполнение той же задачи не плодит новые повторения
+        storage.mark_task_completed(chat_id, &task_id).await.unwrap();
+
+        let tasks = storage.get_tasks(chat_id).await;
+        assert_eq!(tasks.len(), 2);
+        assert!(!tasks[1].is_completed());
+        assert_eq!(tasks[1].text, "Уборка");
+        assert_eq!(tasks[1].recurrence, Some(Recurrence::Weekly(vec![5])));
+        assert!(tasks[1].due_at.is_some_and(|due| due > Utc::now()));
+    }
+
+    #[tokio::test]
+    async fn test_task_lists() {
+        let storage = MemoryStorage::new();
+        let chat_id = ChatId(1);
+
+        let work = storage.create_list(chat_id, "Работа").await.unwrap();
+        assert!(storage.create_list(chat_id, "работа").await.is_err());
+        assert!(storage.set_current_list(ChatId(2), Some(&work.id)).await.is_err());
+
+        storage.add_task(chat_id, TodoItem::new("Купить молоко".to_string())).await.unwrap();
+        storage.set_current_list(chat_id, Some(&work.id)).await.unwrap();
+        storage.add_task(chat_id, TodoItem::new("Отчет".to_string()).with_tags(vec!["q3".to_string()]).with_list(Some(work.id.clone()))).await.unwrap();
+        assert_eq!(storage.get_current_tasks(chat_id).await.len(), 1);
+        assert_eq!(storage.get_tags(chat_id).await, vec!["q3".to_string()]);
+
+        // Перенос в основной список и обратно
+        let milk_id = storage.get_tasks(chat_id).await[0].id.clone();
+        storage.move_task_to_list(chat_id, &milk_id, Some(&work.id)).await.unwrap();
+        assert_eq!(storage.get_current_tasks(chat_id).await.len(), 2);
+        assert!(storage.move_task_to_list(chat_id, &milk_id, Some("missing")).await.is_err());
+
+        assert_eq!(storage.rename_list(chat_id, &work.id, "Офис").await, Ok("Работа".to_string()));
+        assert_eq!(storage.get_current_list(chat_id).await.unwrap().name, "Офис");
+
+        // Удаление списка: задачи уходят в основной, текущим снова становится основной
+        assert_eq!(storage.delete_list(chat_id, &work.id).await, Ok("Офис".to_string()));
+        assert!(storage.get_current_list(chat_id).await.is_none());
+        assert!(storage.get_lists(chat_id).await.is_empty());
+        assert_eq!(storage.get_current_tasks(chat_id).await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_reminders() {
+        let storage = MemoryStorage::new();
+        let chat_id = ChatId(1);
+
+        let gas = CounterType::new("Газ".to_string(), "🔥".to_string());
+        storage.add_counter_reminder(chat_id, CounterReminder::new(gas.clone(), 16, 25)).await.unwrap();
+        storage.mark_counter_completed(chat_id, &gas.id).await.unwrap();
+        storage.add_meter_readings(chat_id, vec![MeterReading::new(gas.id.clone(), String::new(), 42.5, Utc::now())]).await.unwrap();
+        assert_eq!(storage.get_meter_readings(chat_id, &gas.id).await[0].value, 42.5);
+
+        let reminders = storage.get_user_reminders(chat_id).await;
+        assert!(reminders.reminders[&gas.id].completed_this_month);
+
+        // Как и в JsonStorage, запись по умолчанию создается с выключенным глобальным флагом
+        assert!(storage.toggle_global_reminders(chat_id).await.unwrap());
+        assert!(!storage.toggle_global_reminders(chat_id).await.unwrap());
+
+        let all = storage.get_all_reminders().await;
+        assert_eq!(all.len(), 1);
+        assert!(all.contains_key("1"));
+
+        storage.delete_counter_reminder(chat_id, &gas.id).await.unwrap();
+        assert!(storage.get_user_reminders(chat_id).await.reminders.is_empty());
+        assert!(storage.get_meter_readings(chat_id, &gas.id).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_task_reminders() {
+        let storage = MemoryStorage::new();
+        let now = Utc::now();
+
+        storage.add_task_reminder(TaskReminder::new(1, "a".to_string(), now - chrono::Duration::minutes(1))).await.unwrap();
+        storage.add_task_reminder(TaskReminder::new(1, "b".to_string(), now + chrono::Duration::hours(1))).await.unwrap();
+        storage.add_task_reminder(TaskReminder::new(2, "c".to_string(), now)).await.unwrap();
+
+        let due = storage.take_due_task_reminders(now).await.unwrap();
+        assert_eq!(due.iter().map(|r| r.task_id.as_str()).collect::<Vec<_>>(), vec!["a", "c"]);
+        // Выданные напоминания больше не возвращаются
+        assert!(storage.take_due_task_reminders(now).await.unwrap().is_empty());
+
+        storage.remove_task_reminders(ChatId(1), "b").await.unwrap();
+        assert!(storage.take_due_task_reminders(now + chrono::Duration::days(1)).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::env;
+use sqlx::migrate::{Migrate, MigrateError, Migrator};
+use sqlx::PgPool;
+
+/// Миграции PostgreSQL, встроенные в бинарник из `migrations/postgres`.
+/// Применённые версии записываются в таблицу `_sqlx_migrations`.
+pub static POSTGRES_MIGRATOR: Migrator = sqlx::migrate!("./migrations/postgres");
+
+/// Состояние отдельной миграции относительно базы данных
+#[derive(Debug, PartialEq)]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    /// Файл миграции изменился после применения
+    ChecksumMismatch,
+}
+
+#[derive(Debug)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub state: MigrationState,
+}
+
+/// Применяет все ещё не применённые миграции
+pub async fn migrate_up(pool: &PgPool) -> Result<(), MigrateError> {
+    POSTGRES_MIGRATOR.run(pool).await
+}
+
+/// Откатывает миграции до версии `target` (сама `target` остаётся применённой).
+/// Без `target` откатывается только последняя применённая миграция.
+pub async fn migrate_down(pool: &PgPool, target: Option<i64>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let target = match target {
+        Some(target) => target,
+        None => {
+            let applied = applied_versions(pool).await?;
+            let last = applied.keys().max().copied().ok_or("Нет применённых миграций")?;
+            POSTGRES_MIGRATOR
+                .iter()
+                .filter(|m| m.migration_type.is_up_migration() && m.version < last)
+                .map(|m| m.version)
+                .max()
+                .unwrap_or(0)
+        }
+    };
+
+    POSTGRES_MIGRATOR.undo(pool, target).await?;
+    Ok(())
+}
+
+/// Сравнивает встроенные миграции с записанными в базе
+pub async fn migration_status(pool: &PgPool) -> Result<Vec<MigrationStatus>, MigrateError> {
+    let applied = applied_versions(pool).await?;
+
+    Ok(POSTGRES_MIGRATOR
+        .iter()
+        .filter(|m| m.migration_type.is_up_migration())
+        .map(|m| {
+            let state = match applied.get(&m.version) {
+                Some(checksum) if checksum.as_slice() == &*m.checksum => MigrationState::Applied,
+                Some(_) => MigrationState::ChecksumMismatch,
+                None => MigrationState::Pending,
+            };
+            MigrationStatus {
+                version: m.version,
+                description: m.description.to_string(),
+                state,
+            }
+        })
+        .collect())
+}
+
+/// Проверяет, что схема базы совпадает с ожидаемой бинарником
+pub async fn verify(pool: &PgPool) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let problems: Vec<String> = migration_status(pool)
+        .await?
+        .into_iter()
+        .filter(|s| s.state != MigrationState::Applied)
+        .map(|s| format!("{} {} ({:?})", s.version, s.description, s.state))
+        .collect();
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Схема базы не соответствует миграциям: {}", problems.join(", ")).into())
+    }
+}
+
+async fn applied_versions(pool: &PgPool) -> Result<HashMap<i64, Vec<u8>>, MigrateError> {
+    let mut conn = pool.acquire().await?;
+    conn.ensure_migrations_table().await?;
+
+    Ok(conn
+        .list_applied_migrations()
+        .await?
+        .into_iter()
+        .map(|m| (m.version, m.checksum.into_owned()))
+        .collect())
+}
+
+/// Обработка подкоманды `migrate`:
+/// `telegram_todo_bot migrate [up | down [версия] | status | verify]`
+pub async fn run_cli(args: &[String]) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let database_url = env::var("DATABASE_URL").map_err(|_| "DATABASE_URL not set for migrations")?;
+    let pool = PgPool::connect(&database_url).await?;
+
+    match args.first().map(String::as_str).unwrap_or("up") {
+        "up" => {
+            migrate_up(&pool).await?;
+            println!("✅ Миграции применены");
+        }
+        "down" => {
+            let target = match args.get(1) {
+                Some(version) => Some(version.parse::<i64>().map_err(|_| "Версия должна быть числом")?),
+                None => None,
+            };
+            migrate_down(&pool, target).await?;
+            println!("↩️ Миграции откачены");
+        }
+        "status" => {
+            for status in migration_status(&pool).await? {
+                let mark = match status.state {
+                    MigrationState::Applied => "✅",
+                    MigrationState::Pending => "⏳",
+                    MigrationState::ChecksumMismatch => "❌",
+                };
+                println!("{} {:04} {}", mark, status.version, status.description);
+            }
+        }
+        "verify" => {
+            verify(&pool).await?;
+            println!("✅ Схема базы соответствует миграциям");
+        }
+        other => {
+            return Err(format!("Неизвестная подкоманда migrate: {} (up, down, status, verify)", other).into());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrations_are_reversible_and_ordered() {
+        let up: Vec<i64> = POSTGRES_MIGRATOR.iter().filter(|m| m.migration_type.is_up_migration()).map(|m| m.version).collect();
+        let down: Vec<i64> = POSTGRES_MIGRATOR.iter().filter(|m| m.migration_type.is_down_migration()).map(|m| m.version).collect();
+
+        assert!(!up.is_empty());
+        assert_eq!(up, down);
+        assert!(up.windows(2).all(|w| w[0] < w[1]));
+    }
+}
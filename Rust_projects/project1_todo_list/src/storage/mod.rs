@@ -1,121 +1,197 @@
 pub mod json;
 pub mod postgres;
-
-#[allow(dead_code)]
 pub mod memory;
+pub mod sqlite;
+pub mod migrations;
+pub mod dialogue;
+mod tasks; // логика над списком задач, общая для бэкендов
 
 pub use json::JsonStorage;
 pub use postgres::PostgresStorage;
+pub use memory::MemoryStorage;
+pub use sqlite::SqliteStorage;
+pub use dialogue::DialogueStorage;
 
+use std::collections::HashMap;
 use std::env;
+use std::sync::Arc;
+use async_trait::async_trait;
 use teloxide::types::ChatId;
-use crate::models::{TodoItem, UserReminders, CounterReminder, CounterType};
-
-#[derive(Clone)]
-pub enum StorageType {
-    Json(JsonStorage),
-    Postgres(PostgresStorage),
-}
-
-impl StorageType {
-    pub async fn new() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
-        let storage_type = env::var("STORAGE_TYPE").unwrap_or_else(|_| "json".to_string());
-        
-        match storage_type.as_str() {
-            "postgres" => {
-                let database_url = env::var("DATABASE_URL")
-                    .map_err(|_| "DATABASE_URL not set for PostgreSQL storage")?;
-                let postgres_storage = PostgresStorage::new(&database_url).await?;
-                Ok(StorageType::Postgres(postgres_storage))
-            }
-            _ => {
-                let json_storage = JsonStorage::new("data/todos.json");
-                Ok(StorageType::Json(json_storage))
-            }
-        }
-    }
-
-    pub async fn add_task(&self, chat_id: ChatId, text: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        match self {
-            StorageType::Json(storage) => storage.add_task(chat_id, text).await,
-            StorageType::Postgres(storage) => storage.add_task(chat_id, text).await,
-        }
-    }
-
-    pub async fn get_tasks(&self, chat_id: ChatId) -> Vec<TodoItem> {
-        match self {
-            StorageType::Json(storage) => storage.get_tasks(chat_id).await,
-            StorageType::Postgres(storage) => storage.get_tasks(chat_id).await,
-        }
-    }
-
-    pub async fn mark_task_completed(&self, chat_id: ChatId, task_index: usize) -> Result<String, String> {
-        match self {
-            StorageType::Json(storage) => storage.mark_task_completed(chat_id, task_index).await,
-            StorageType::Postgres(storage) => storage.mark_task_completed(chat_id, task_index).await,
-        }
-    }
-
-    pub async fn remove_task(&self, chat_id: ChatId, task_index: usize) -> Result<String, String> {
-        match self {
-            StorageType::Json(storage) => storage.remove_task(chat_id, task_index).await,
-            StorageType::Postgres(storage) => storage.remove_task(chat_id, task_index).await,
-        }
+use chrono::{DateTime, Utc};
+use crate::models::{TodoItem, TaskEdit, UserReminders, CounterReminder, MeterReading, Tariff, TaskReminder, Recurrence, Priority, TaskList, UserState, SavedUserState};
+
+/// Общий интерфейс хранилища задач и напоминаний.
+///
+/// Обработчики и система напоминаний работают только через этот трейт,
+/// поэтому новый бэкенд (или обертку над существующим) достаточно
+/// реализовать здесь, не трогая handlers.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    // Задачи
+    async fn add_task(&self, chat_id: ChatId, item: TodoItem) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+    /// Задачи чата без архива и корзины, в порядке списка
+    async fn get_tasks(&self, chat_id: ChatId) -> Vec<TodoItem>;
+    /// Теги задач текущего списка, по алфавиту
+    async fn get_tags(&self, chat_id: ChatId) -> Vec<String>;
+    /// Задачи адресуются по `TodoItem::id`, а не по позиции в списке,
+    /// чтобы параллельные изменения не приводили к действию над чужой задачей.
+    /// Вместе с задачей выполняются её подзадачи, а родитель — когда выполнены все его подзадачи.
+    /// Для повторяющейся задачи при первом выполнении добавляется следующее повторение.
+    async fn mark_task_completed(&self, chat_id: ChatId, task_id: &str) -> Result<String, String>;
+    /// Задает или снимает (`None`) правило повторения, возвращает текст задачи
+    async fn set_task_recurrence(&self, chat_id: ChatId, task_id: &str, recurrence: Option<Recurrence>) -> Result<String, String>;
+    /// Меняет текст задачи и указанные в `edit` приоритет, теги и срок. Возвращает прежний текст
+    async fn edit_task(&self, chat_id: ChatId, task_id: &str, edit: &TaskEdit) -> Result<String, String>;
+    async fn set_task_priority(&self, chat_id: ChatId, task_id: &str, priority: Priority) -> Result<String, String>;
+    /// Ставит задачи в заданном порядке на занимаемые ими места; остальные задачи не сдвигаются
+    async fn reorder_tasks(&self, chat_id: ChatId, task_ids: &[String]) -> Result<(), String>;
+    /// Переносит задачу вместе с подзадачами в корзину
+    async fn remove_task(&self, chat_id: ChatId, task_id: &str) -> Result<String, String>;
+    /// Переносит задачи текущего списка в корзину
+    async fn clear_tasks(&self, chat_id: ChatId) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+    /// Возвращает удаленные задачи с прежними id и позициями (для отмены удаления)
+    async fn restore_tasks(&self, chat_id: ChatId, todos: Vec<TodoItem>) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Архив и корзина
+    /// Задачи в архиве, сначала убранные последними
+    async fn get_archived_tasks(&self, chat_id: ChatId) -> Vec<TodoItem>;
+    /// Задачи в корзине, сначала удаленные последними
+    async fn get_trashed_tasks(&self, chat_id: ChatId) -> Vec<TodoItem>;
+    /// Возвращает задачу из архива или корзины вместе с подзадачами, убранными вместе с ней.
+    /// Задача из архива снова становится невыполненной. Возвращает текст задачи
+    async fn restore_task(&self, chat_id: ChatId, task_id: &str) -> Result<String, String>;
+    /// Окончательно удаляет задачи из корзины чата, возвращает их число
+    async fn empty_trash(&self, chat_id: ChatId) -> Result<usize, String>;
+    /// Переносит в архив задачи всех чатов, выполненные не позже `completed_before`
+    async fn archive_completed_tasks(&self, completed_before: DateTime<Utc>) -> Result<usize, Box<dyn std::error::Error + Send + Sync>>;
+    /// Окончательно удаляет из корзин всех чатов задачи, удаленные не позже `deleted_before`
+    async fn purge_trash(&self, deleted_before: DateTime<Utc>) -> Result<usize, Box<dyn std::error::Error + Send + Sync>>;
+
+    // Списки задач
+    /// Именованные списки чата; основной список (задачи без `list_id`) сюда не входит
+    async fn get_lists(&self, chat_id: ChatId) -> Vec<TaskList>;
+    async fn create_list(&self, chat_id: ChatId, name: &str) -> Result<TaskList, String>;
+    /// Возвращает прежнее название
+    async fn rename_list(&self, chat_id: ChatId, list_id: &str, name: &str) -> Result<String, String>;
+    /// Удаляет список, его задачи переносятся в основной. Возвращает название
+    async fn delete_list(&self, chat_id: ChatId, list_id: &str) -> Result<String, String>;
+    /// Текущий список чата; `None` — основной
+    async fn get_current_list(&self, chat_id: ChatId) -> Option<TaskList>;
+    async fn set_current_list(&self, chat_id: ChatId, list_id: Option<&str>) -> Result<(), String>;
+    /// Переносит задачу вместе с подзадачами в другой список (`None` — в основной)
+    async fn move_task_to_list(&self, chat_id: ChatId, task_id: &str, list_id: Option<&str>) -> Result<String, String>;
+
+    /// Задачи текущего списка: с ними работают номера в `/list`, `/done` и т.д.
+    async fn get_current_tasks(&self, chat_id: ChatId) -> Vec<TodoItem> {
+        let list_id = self.get_current_list(chat_id).await.map(|list| list.id);
+        self.get_tasks(chat_id)
+            .await
+            .into_iter()
+            .filter(|t| t.list_id == list_id)
+            .collect()
     }
 
-    pub async fn clear_tasks(&self, chat_id: ChatId) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        match self {
-            StorageType::Json(storage) => storage.clear_tasks(chat_id).await,
-            StorageType::Postgres(storage) => storage.clear_tasks(chat_id).await,
-        }
+    /// Поиск по всем спискам и архиву (корзина не просматривается): задачи, в тексте или тегах
+    /// которых есть все слова запроса. Сначала задачи списков в их порядке, затем архив
+    async fn search_tasks(&self, chat_id: ChatId, query: &str) -> Vec<TodoItem> {
+        let words = tasks::query_words(query);
+        let mut found: Vec<TodoItem> = self.get_tasks(chat_id).await;
+        found.extend(self.get_archived_tasks(chat_id).await);
+        found.retain(|t| tasks::matches_query(t, &words));
+        found
     }
 
-    pub async fn get_user_reminders(&self, chat_id: ChatId) -> UserReminders {
-        match self {
-            StorageType::Json(storage) => storage.get_user_reminders(chat_id).await,
-            StorageType::Postgres(storage) => storage.get_user_reminders(chat_id).await,
-        }
-    }
+    // Напоминания
+    async fn get_user_reminders(&self, chat_id: ChatId) -> UserReminders;
+    async fn save_user_reminders(&self, chat_id: ChatId, reminders: UserReminders) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+    /// Добавляет счетчик или заменяет счетчик с тем же id
+    async fn add_counter_reminder(&self, chat_id: ChatId, reminder: CounterReminder) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+    /// Удаляет счетчик вместе с его напоминаниями, показаниями и тарифами
+    async fn delete_counter_reminder(&self, chat_id: ChatId, counter_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+    async fn toggle_global_reminders(&self, chat_id: ChatId) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+    async fn mark_counter_completed(&self, chat_id: ChatId, counter_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+    async fn get_all_reminders(&self) -> HashMap<String, UserReminders>;
+    async fn reset_monthly_statuses(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Показания счетчиков
+    /// Сохраняет показания одной отправки (по одному на тарифную зону)
+    async fn add_meter_readings(&self, chat_id: ChatId, readings: Vec<MeterReading>) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+    /// Показания счетчика от старых к новым
+    async fn get_meter_readings(&self, chat_id: ChatId, counter_id: &str) -> Vec<MeterReading>;
+
+    // Тарифы
+    /// Сохраняет тарифы счетчика; тариф той же зоны с той же датой начала заменяется
+    async fn set_tariffs(&self, chat_id: ChatId, tariffs: Vec<Tariff>) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+    /// Тарифы счетчика по возрастанию даты начала действия
+    async fn get_tariffs(&self, chat_id: ChatId, counter_id: &str) -> Vec<Tariff>;
+
+    // Напоминания о задачах
+    async fn add_task_reminder(&self, reminder: TaskReminder) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+    /// Забирает (удаляет и возвращает) все напоминания со временем не позже `now`
+    async fn take_due_task_reminders(&self, now: DateTime<Utc>) -> Result<Vec<TaskReminder>, Box<dyn std::error::Error + Send + Sync>>;
+    async fn remove_task_reminders(&self, chat_id: ChatId, task_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    // Состояние диалога
+    async fn get_user_state(&self, chat_id: ChatId) -> Option<SavedUserState>;
+    /// Сохраняет состояние диалога; `UserState::Default` удаляет запись
+    async fn save_user_state(&self, chat_id: ChatId, state: UserState) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+    /// Удаляет состояния диалога всех чатов, не менявшиеся с `updated_before`
+    async fn purge_user_states(&self, updated_before: DateTime<Utc>) -> Result<usize, Box<dyn std::error::Error + Send + Sync>>;
+}
 
-    pub async fn save_user_reminders(&self, chat_id: ChatId, reminders: UserReminders) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        match self {
-            StorageType::Json(storage) => storage.save_user_reminders(chat_id, reminders).await,
-            StorageType::Postgres(storage) => storage.save_user_reminders(chat_id, reminders).await,
-        }
-    }
+/// Хранилище, разделяемое между обработчиками
+pub type SharedStorage = Arc<dyn Storage>;
 
-    pub async fn add_counter_reminder(&self, chat_id: ChatId, reminder: CounterReminder) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        match self {
-            StorageType::Json(storage) => storage.add_counter_reminder(chat_id, reminder).await,
-            StorageType::Postgres(storage) => storage.add_counter_reminder(chat_id, reminder).await,
-        }
-    }
+/// Тип бэкенда, выбираемый через переменную окружения `STORAGE_TYPE`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StorageType {
+    Json,
+    Postgres,
+    Sqlite,
+    Memory,
+}
 
-    pub async fn toggle_global_reminders(&self, chat_id: ChatId) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
-        match self {
-            StorageType::Json(storage) => storage.toggle_global_reminders(chat_id).await,
-            StorageType::Postgres(storage) => storage.toggle_global_reminders(chat_id).await,
-        }
-    }
+impl StorageType {
+    pub fn from_env() -> Self {
+        let storage_type = env::var("STORAGE_TYPE").unwrap_or_else(|_| "json".to_string());
 
-    pub async fn mark_counter_completed(&self, chat_id: ChatId, counter_type: CounterType) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        match self {
-            StorageType::Json(storage) => storage.mark_counter_completed(chat_id, counter_type).await,
-            StorageType::Postgres(storage) => storage.mark_counter_completed(chat_id, counter_type).await,
+        match storage_type.as_str() {
+            "postgres" => StorageType::Postgres,
+            "sqlite" => StorageType::Sqlite,
+            "memory" => StorageType::Memory,
+            _ => StorageType::Json,
         }
     }
 
-    pub async fn get_all_reminders(&self) -> std::collections::HashMap<String, UserReminders> {
-        match self {
-            StorageType::Json(storage) => storage.get_all_reminders().await,
-            StorageType::Postgres(storage) => storage.get_all_reminders().await,
-        }
+    /// Создает хранилище согласно `STORAGE_TYPE`
+    #[allow(clippy::new_ret_no_self)]
+    pub async fn new() -> Result<SharedStorage, Box<dyn std::error::Error + Send + Sync>> {
+        Self::from_env().build().await
     }
 
-    pub async fn reset_monthly_statuses(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    pub async fn build(self) -> Result<SharedStorage, Box<dyn std::error::Error + Send + Sync>> {
         match self {
-            StorageType::Json(storage) => storage.reset_monthly_statuses().await,
-            StorageType::Postgres(storage) => storage.reset_monthly_statuses().await,
+            StorageType::Postgres => {
+                let database_url = env::var("DATABASE_URL")
+                    .map_err(|_| "DATABASE_URL not set for PostgreSQL storage")?;
+                // DB_AUTO_MIGRATE=false — схему обновляют вручную через `migrate up`
+                let auto_migrate = env::var("DB_AUTO_MIGRATE").map(|v| v != "false").unwrap_or(true);
+                let postgres_storage = PostgresStorage::new(&database_url, auto_migrate).await?;
+                Ok(Arc::new(postgres_storage))
+            }
+            StorageType::Sqlite => {
+                let sqlite_path = env::var("SQLITE_PATH").unwrap_or_else(|_| "data/todos.db".to_string());
+                let sqlite_storage = SqliteStorage::new(&sqlite_path).await?;
+                Ok(Arc::new(sqlite_storage))
+            }
+            StorageType::Memory => {
+                log::warn!("Using in-memory storage: data will be lost on restart");
+                Ok(Arc::new(MemoryStorage::new()))
+            }
+            StorageType::Json => {
+                let json_storage = JsonStorage::new("data/todos.json");
+                Ok(Arc::new(json_storage))
+            }
         }
     }
 }
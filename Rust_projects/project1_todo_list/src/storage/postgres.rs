@@ -1,8 +1,12 @@
 use sqlx::{PgPool, Row};
 use teloxide::types::ChatId;
-use chrono::TimeZone;
+use chrono::{DateTime, TimeZone, Utc};
+use async_trait::async_trait;
 
-use crate::models::{TodoItem, UserReminders, CounterReminder, CounterType};
+use crate::models::{TodoItem, TaskEdit, UserReminders, CounterReminder, CounterType, MeterReading, Tariff, TaskReminder, Recurrence, Priority, TaskList, UserState, SavedUserState};
+use super::{migrations, Storage};
+use super::dialogue::saved_user_state;
+use super::tasks::{self, complete_task};
 
 #[derive(Clone)]
 pub struct PostgresStorage {
@@ -10,133 +14,651 @@ pub struct PostgresStorage {
 }
 
 impl PostgresStorage {
-    pub async fn new(database_url: &str) -> Result<Self, sqlx::Error> {
+    /// Подключается к базе и приводит схему к актуальной версии.
+    /// При `auto_migrate = false` миграции не применяются, а только проверяются.
+    pub async fn new(database_url: &str, auto_migrate: bool) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
         let pool = PgPool::connect(database_url).await?;
-        
-        // Создаем таблицы если их нет
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS todos (
-                id VARCHAR PRIMARY KEY,
-                chat_id BIGINT NOT NULL,
-                text TEXT NOT NULL,
-                completed BOOLEAN NOT NULL DEFAULT FALSE,
-                created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
-            )
-            "#
-        ).execute(&pool).await?;
-
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS user_reminders (
-                chat_id BIGINT PRIMARY KEY,
-                global_enabled BOOLEAN NOT NULL DEFAULT TRUE
-            )
-            "#
-        ).execute(&pool).await?;
 
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS counter_reminders (
-                chat_id BIGINT NOT NULL,
-                counter_type VARCHAR NOT NULL,
-                start_day INTEGER NOT NULL,
-                end_day INTEGER NOT NULL,
-                enabled BOOLEAN NOT NULL DEFAULT TRUE,
-                last_sent_month VARCHAR,
-                last_sent_date VARCHAR,
-                completed_this_month BOOLEAN NOT NULL DEFAULT FALSE,
-                PRIMARY KEY (chat_id, counter_type)
-            )
-            "#
-        ).execute(&pool).await?;
+        if auto_migrate {
+            migrations::migrate_up(&pool).await?;
+        } else {
+            migrations::verify(&pool).await?;
+        }
 
         Ok(Self { pool })
     }
+}
 
-    // Методы для работы с задачами
-    pub async fn add_task(&self, chat_id: ChatId, text: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let todo_item = TodoItem::new(text.to_string());
-        
-        sqlx::query(
-            "INSERT INTO todos (id, chat_id, text, completed, created_at) VALUES ($1, $2, $3, $4, $5)"
-        )
-        .bind(&todo_item.id)
+/// Колонки задачи для SELECT и RETURNING; теги собираются из `todo_tags`
+const TODO_COLUMNS: &str = "id, text, completed, created_at, due_at, recurrence, priority, parent_id, list_id, position, \
+    completed_at, archived_at, deleted_at, ARRAY(SELECT tag FROM todo_tags WHERE todo_id = todos.id ORDER BY tag) AS tags";
+
+fn todo_from_row(row: &sqlx::postgres::PgRow) -> TodoItem {
+    TodoItem {
+        id: row.get("id"),
+        text: row.get("text"),
+        completed: row.get("completed"),
+        created_at: row.get("created_at"),
+        due_at: row.get("due_at"),
+        recurrence: row
+            .get::<Option<String>, _>("recurrence")
+            .and_then(|rule| Recurrence::from_rule(&rule)),
+        priority: Priority::from_str(row.get("priority")).unwrap_or_default(),
+        parent_id: row.get("parent_id"),
+        list_id: row.get("list_id"),
+        position: row.get("position"),
+        tags: row.get("tags"),
+        completed_at: row.get("completed_at"),
+        archived_at: row.get("archived_at"),
+        deleted_at: row.get("deleted_at"),
+    }
+}
+
+/// Условие «задача в списке, а не в архиве или корзине»
+const ACTIVE: &str = "archived_at IS NULL AND deleted_at IS NULL";
+
+/// Условие «задача из текущего списка чата»; `$1` — chat_id
+const IN_CURRENT_LIST: &str = "list_id IS NOT DISTINCT FROM (SELECT list_id FROM current_lists WHERE chat_id = $1)";
+
+fn task_list_from_row(row: &sqlx::postgres::PgRow) -> TaskList {
+    TaskList {
+        id: row.get("id"),
+        name: row.get("name"),
+        created_at: row.get("created_at"),
+    }
+}
+
+/// Списки чата; названия сравниваются в Rust: `LOWER()` зависит от локали базы
+/// и в локали C не знает кириллицу
+async fn fetch_lists(conn: &mut sqlx::PgConnection, chat_id: ChatId) -> Result<Vec<TaskList>, sqlx::Error> {
+    let rows = sqlx::query("SELECT id, name, created_at FROM task_lists WHERE chat_id = $1 ORDER BY created_at")
         .bind(chat_id.0)
-        .bind(&todo_item.text)
-        .bind(todo_item.completed)
-        .bind(todo_item.created_at)
-        .execute(&self.pool)
+        .fetch_all(&mut *conn)
         .await?;
-        
+
+    Ok(rows.iter().map(task_list_from_row).collect())
+}
+
+/// Ошибка записи списка: нарушение уникальности названия показываем пользователю
+fn list_error(e: sqlx::Error) -> String {
+    match e {
+        sqlx::Error::Database(e) if e.is_unique_violation() => "Список с таким названием уже есть".to_string(),
+        _ => "Ошибка сохранения".to_string(),
+    }
+}
+
+async fn insert_todo(conn: &mut sqlx::PgConnection, chat_id: ChatId, todo_item: &TodoItem) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO todos (id, chat_id, text, completed, created_at, due_at, recurrence, priority, parent_id, list_id, position, completed_at, archived_at, deleted_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)"
+    )
+    .bind(&todo_item.id)
+    .bind(chat_id.0)
+    .bind(&todo_item.text)
+    .bind(todo_item.completed)
+    .bind(todo_item.created_at)
+    .bind(todo_item.due_at)
+    .bind(todo_item.recurrence.as_ref().map(Recurrence::as_rule))
+    .bind(todo_item.priority.as_str())
+    .bind(&todo_item.parent_id)
+    .bind(&todo_item.list_id)
+    .bind(todo_item.position)
+    .bind(todo_item.completed_at)
+    .bind(todo_item.archived_at)
+    .bind(todo_item.deleted_at)
+    .execute(&mut *conn)
+    .await?;
+
+    if !todo_item.tags.is_empty() {
+        sqlx::query("INSERT INTO todo_tags (todo_id, tag) SELECT $1, UNNEST($2::VARCHAR[])")
+            .bind(&todo_item.id)
+            .bind(&todo_item.tags)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Окончательно удаляет задачи корзины, подходящие под `condition` (с параметром `$1`).
+/// Сначала удаляются подзадачи: удаленные каскадом строки не попали бы в счетчик.
+async fn delete_trashed<T>(conn: &mut sqlx::PgConnection, condition: &str, param: T) -> Result<usize, sqlx::Error>
+where
+    T: for<'q> sqlx::Encode<'q, sqlx::Postgres> + sqlx::Type<sqlx::Postgres> + Send + Clone,
+{
+    let mut purged = 0;
+    for level in ["parent_id IS NOT NULL", "parent_id IS NULL"] {
+        let result = sqlx::query(&format!("DELETE FROM todos WHERE deleted_at IS NOT NULL AND {} AND {}", condition, level))
+            .bind(param.clone())
+            .execute(&mut *conn)
+            .await?;
+        purged += result.rows_affected() as usize;
+    }
+    Ok(purged)
+}
+
+fn meter_reading_from_row(row: &sqlx::postgres::PgRow) -> MeterReading {
+    MeterReading {
+        id: row.get("id"),
+        counter_id: row.get("counter_id"),
+        zone: row.get("zone"),
+        value: row.get("value"),
+        taken_at: row.get("taken_at"),
+    }
+}
+
+fn tariff_from_row(row: &sqlx::postgres::PgRow) -> Tariff {
+    Tariff {
+        counter_id: row.get("counter_id"),
+        zone: row.get("zone"),
+        price: row.get("price"),
+        effective_from: row.get("effective_from"),
+    }
+}
+
+fn task_reminder_from_row(row: &sqlx::postgres::PgRow) -> TaskReminder {
+    TaskReminder {
+        id: row.get("id"),
+        chat_id: row.get("chat_id"),
+        task_id: row.get("task_id"),
+        remind_at: row.get("remind_at"),
+        attempts: row.get("attempts"),
+    }
+}
+
+#[async_trait]
+impl Storage for PostgresStorage {
+    // Методы для работы с задачами
+    async fn add_task(&self, chat_id: ChatId, mut todo_item: TodoItem) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut tx = self.pool.begin().await?;
+        todo_item.position = sqlx::query_scalar("SELECT COALESCE(MAX(position), 0) + 1 FROM todos WHERE chat_id = $1")
+            .bind(chat_id.0)
+            .fetch_one(&mut *tx)
+            .await?;
+        insert_todo(&mut tx, chat_id, &todo_item).await?;
+        tx.commit().await?;
         Ok(())
     }
 
-    pub async fn get_tasks(&self, chat_id: ChatId) -> Vec<TodoItem> {
+    async fn get_tasks(&self, chat_id: ChatId) -> Vec<TodoItem> {
         let rows = sqlx::query(
-            "SELECT id, text, completed, created_at FROM todos WHERE chat_id = $1 ORDER BY created_at"
+            &format!("SELECT {} FROM todos WHERE chat_id = $1 AND {} ORDER BY position, created_at", TODO_COLUMNS, ACTIVE)
         )
         .bind(chat_id.0)
         .fetch_all(&self.pool)
         .await
         .unwrap_or_default();
 
-        rows.into_iter().map(|row| TodoItem {
-            id: row.get("id"),
-            text: row.get("text"),
-            completed: row.get("completed"),
-            created_at: row.get("created_at"),
-        }).collect()
+        rows.iter().map(todo_from_row).collect()
     }
 
-    pub async fn mark_task_completed(&self, chat_id: ChatId, task_index: usize) -> Result<String, String> {
-        let tasks = self.get_tasks(chat_id).await;
-        
-        if task_index >= tasks.len() {
-            return Err("Задача с таким номером не найдена".to_string());
+    async fn get_tags(&self, chat_id: ChatId) -> Vec<String> {
+        sqlx::query_scalar(&format!(
+            "SELECT DISTINCT g.tag FROM todo_tags g JOIN todos t ON t.id = g.todo_id
+             WHERE t.chat_id = $1 AND t.{} AND t.archived_at IS NULL AND t.deleted_at IS NULL ORDER BY g.tag",
+            IN_CURRENT_LIST
+        ))
+        .bind(chat_id.0)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+    }
+
+    async fn mark_task_completed(&self, chat_id: ChatId, task_id: &str) -> Result<String, String> {
+        let mut tx = self.pool.begin().await.map_err(|_| "Ошибка сохранения".to_string())?;
+
+        // Задачи чата блокируются до конца транзакции, чтобы параллельная отметка
+        // не создала повторение или не пересчитала родителя второй раз
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM todos WHERE chat_id = $1 ORDER BY position, created_at FOR UPDATE",
+            TODO_COLUMNS
+        ))
+        .bind(chat_id.0)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|_| "Ошибка сохранения".to_string())?;
+        let mut todos: Vec<TodoItem> = rows.iter().map(todo_from_row).collect();
+
+        let now = Utc::now();
+        let completion = complete_task(&mut todos, task_id, now)?;
+
+        sqlx::query("UPDATE todos SET completed = TRUE, completed_at = $3 WHERE id = ANY($1) AND chat_id = $2")
+            .bind(&completion.completed)
+            .bind(chat_id.0)
+            .bind(now)
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| "Ошибка сохранения".to_string())?;
+        for todo in &completion.spawned {
+            insert_todo(&mut tx, chat_id, todo)
+                .await
+                .map_err(|_| "Ошибка сохранения".to_string())?;
         }
 
-        let task = &tasks[task_index];
-        
-        sqlx::query("UPDATE todos SET completed = TRUE WHERE id = $1")
-            .bind(&task.id)
-            .execute(&self.pool)
+        tx.commit().await.map_err(|_| "Ошибка сохранения".to_string())?;
+        Ok(completion.text)
+    }
+
+    async fn set_task_recurrence(&self, chat_id: ChatId, task_id: &str, recurrence: Option<Recurrence>) -> Result<String, String> {
+        let row = sqlx::query(&format!("UPDATE todos SET recurrence = $1 WHERE id = $2 AND chat_id = $3 AND {} RETURNING text", ACTIVE))
+            .bind(recurrence.as_ref().map(Recurrence::as_rule))
+            .bind(task_id)
+            .bind(chat_id.0)
+            .fetch_optional(&self.pool)
             .await
             .map_err(|_| "Ошибка сохранения".to_string())?;
 
-        Ok(task.text.clone())
+        row.map(|row| row.get("text"))
+            .ok_or_else(|| "Задача не найдена".to_string())
     }
 
-    pub async fn remove_task(&self, chat_id: ChatId, task_index: usize) -> Result<String, String> {
-        let tasks = self.get_tasks(chat_id).await;
-        
-        if task_index >= tasks.len() {
-            return Err("Задача с таким номером не найдена".to_string());
+    async fn edit_task(&self, chat_id: ChatId, task_id: &str, edit: &TaskEdit) -> Result<String, String> {
+        let mut tx = self.pool.begin().await.map_err(|_| "Ошибка сохранения".to_string())?;
+
+        // Прежний текст берем из той же строки до обновления
+        let row = sqlx::query(
+            "UPDATE todos t SET text = $1, priority = COALESCE($2, t.priority), due_at = COALESCE($3, t.due_at)
+             FROM todos old
+             WHERE old.id = t.id AND t.id = $4 AND t.chat_id = $5
+               AND t.archived_at IS NULL AND t.deleted_at IS NULL RETURNING old.text"
+        )
+        .bind(&edit.text)
+        .bind(edit.priority.map(|p| p.as_str()))
+        .bind(edit.due_at)
+        .bind(task_id)
+        .bind(chat_id.0)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|_| "Ошибка сохранения".to_string())?;
+        let old_text: String = row.map(|row| row.get("text")).ok_or_else(|| "Задача не найдена".to_string())?;
+
+        if let Some(tags) = &edit.tags {
+            sqlx::query("DELETE FROM todo_tags WHERE todo_id = $1")
+                .bind(task_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|_| "Ошибка сохранения".to_string())?;
+            sqlx::query("INSERT INTO todo_tags (todo_id, tag) SELECT $1, UNNEST($2::VARCHAR[])")
+                .bind(task_id)
+                .bind(tags)
+                .execute(&mut *tx)
+                .await
+                .map_err(|_| "Ошибка сохранения".to_string())?;
         }
 
-        let task = &tasks[task_index];
-        let task_text = task.text.clone();
-        
-        sqlx::query("DELETE FROM todos WHERE id = $1")
-            .bind(&task.id)
-            .execute(&self.pool)
+        tx.commit().await.map_err(|_| "Ошибка сохранения".to_string())?;
+        Ok(old_text)
+    }
+
+    async fn set_task_priority(&self, chat_id: ChatId, task_id: &str, priority: Priority) -> Result<String, String> {
+        let row = sqlx::query(&format!("UPDATE todos SET priority = $1 WHERE id = $2 AND chat_id = $3 AND {} RETURNING text", ACTIVE))
+            .bind(priority.as_str())
+            .bind(task_id)
+            .bind(chat_id.0)
+            .fetch_optional(&self.pool)
             .await
             .map_err(|_| "Ошибка сохранения".to_string())?;
 
-        Ok(task_text)
+        row.map(|row| row.get("text"))
+            .ok_or_else(|| "Задача не найдена".to_string())
     }
 
-    pub async fn clear_tasks(&self, chat_id: ChatId) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        sqlx::query("DELETE FROM todos WHERE chat_id = $1")
+    async fn reorder_tasks(&self, chat_id: ChatId, task_ids: &[String]) -> Result<(), String> {
+        let mut tx = self.pool.begin().await.map_err(|_| "Ошибка сохранения".to_string())?;
+
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM todos WHERE chat_id = $1 ORDER BY position, created_at FOR UPDATE",
+            TODO_COLUMNS
+        ))
+        .bind(chat_id.0)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|_| "Ошибка сохранения".to_string())?;
+        let mut todos: Vec<TodoItem> = rows.iter().map(todo_from_row).collect();
+
+        tasks::reorder_tasks(&mut todos, task_ids)?;
+
+        let ids: Vec<&str> = todos.iter().map(|t| t.id.as_str()).collect();
+        let positions: Vec<i64> = todos.iter().map(|t| t.position).collect();
+        sqlx::query(
+            "UPDATE todos SET position = v.position
+             FROM UNNEST($1::VARCHAR[], $2::BIGINT[]) AS v(id, position)
+             WHERE todos.id = v.id AND todos.chat_id = $3"
+        )
+        .bind(&ids)
+        .bind(&positions)
+        .bind(chat_id.0)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| "Ошибка сохранения".to_string())?;
+
+        tx.commit().await.map_err(|_| "Ошибка сохранения".to_string())?;
+        Ok(())
+    }
+
+    async fn remove_task(&self, chat_id: ChatId, task_id: &str) -> Result<String, String> {
+        let mut tx = self.pool.begin().await.map_err(|_| "Ошибка сохранения".to_string())?;
+        let now = Utc::now();
+
+        let row = sqlx::query(&format!(
+            "UPDATE todos SET deleted_at = $3 WHERE id = $1 AND chat_id = $2 AND {} RETURNING text",
+            ACTIVE
+        ))
+        .bind(task_id)
+        .bind(chat_id.0)
+        .bind(now)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|_| "Ошибка сохранения".to_string())?
+        .ok_or_else(|| "Задача не найдена".to_string())?;
+
+        // Подзадачи попадают в корзину с тем же временем, чтобы восстановиться вместе с родителем
+        sqlx::query(&format!("UPDATE todos SET deleted_at = $3 WHERE parent_id = $1 AND chat_id = $2 AND {}", ACTIVE))
+            .bind(task_id)
+            .bind(chat_id.0)
+            .bind(now)
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| "Ошибка сохранения".to_string())?;
+
+        tx.commit().await.map_err(|_| "Ошибка сохранения".to_string())?;
+        Ok(row.get("text"))
+    }
+
+    async fn clear_tasks(&self, chat_id: ChatId) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        sqlx::query(&format!("UPDATE todos SET deleted_at = $2 WHERE chat_id = $1 AND {} AND {}", IN_CURRENT_LIST, ACTIVE))
             .bind(chat_id.0)
+            .bind(Utc::now())
             .execute(&self.pool)
             .await?;
         
         Ok(())
     }
 
+    async fn restore_tasks(&self, chat_id: ChatId, mut todos: Vec<TodoItem>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // Родительские задачи вставляются раньше подзадач, которые на них ссылаются
+        todos.sort_by_key(|t| t.parent_id.is_some());
+
+        let mut tx = self.pool.begin().await?;
+        for todo_item in &todos {
+            // Задача, которая еще лежит в корзине, просто возвращается из неё
+            let restored = sqlx::query("UPDATE todos SET archived_at = $3, deleted_at = $4 WHERE id = $1 AND chat_id = $2")
+                .bind(&todo_item.id)
+                .bind(chat_id.0)
+                .bind(todo_item.archived_at)
+                .bind(todo_item.deleted_at)
+                .execute(&mut *tx)
+                .await?;
+            if restored.rows_affected() == 0 {
+                insert_todo(&mut tx, chat_id, todo_item).await?;
+            }
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // Методы для работы с архивом и корзиной
+    async fn get_archived_tasks(&self, chat_id: ChatId) -> Vec<TodoItem> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM todos WHERE chat_id = $1 AND archived_at IS NOT NULL ORDER BY archived_at DESC, position, created_at",
+            TODO_COLUMNS
+        ))
+        .bind(chat_id.0)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default();
+
+        rows.iter().map(todo_from_row).collect()
+    }
+
+    /// Полнотекстовый поиск с русской морфологией («молока» находит «молоко»),
+    /// а также поиск всех слов как подстрок текста и тегов, чтобы находились части слов
+    async fn search_tasks(&self, chat_id: ChatId, query: &str) -> Vec<TodoItem> {
+        let patterns: Vec<String> = tasks::query_words(query)
+            .iter()
+            .map(|word| format!("%{}%", word.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")))
+            .collect();
+        if patterns.is_empty() {
+            return Vec::new();
+        }
+
+        // Условия объединены через UNION, а не OR: так полнотекстовая часть идет по GIN-индексу
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM todos
+             WHERE deleted_at IS NULL AND id IN (
+                 SELECT id FROM todos
+                 WHERE chat_id = $1 AND to_tsvector('russian', text) @@ plainto_tsquery('russian', $2)
+                 UNION
+                 SELECT id FROM todos
+                 WHERE chat_id = $1
+                   AND LOWER(text || ' ' || COALESCE((SELECT string_agg(tag, ' ') FROM todo_tags WHERE todo_id = todos.id), ''))
+                       LIKE ALL($3)
+             )
+             ORDER BY archived_at IS NOT NULL, archived_at DESC, position, created_at",
+            TODO_COLUMNS
+        ))
+        .bind(chat_id.0)
+        .bind(query)
+        .bind(&patterns)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default();
+
+        rows.iter().map(todo_from_row).collect()
+    }
+
+    async fn get_trashed_tasks(&self, chat_id: ChatId) -> Vec<TodoItem> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM todos WHERE chat_id = $1 AND deleted_at IS NOT NULL ORDER BY deleted_at DESC, position, created_at",
+            TODO_COLUMNS
+        ))
+        .bind(chat_id.0)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default();
+
+        rows.iter().map(todo_from_row).collect()
+    }
+
+    async fn restore_task(&self, chat_id: ChatId, task_id: &str) -> Result<String, String> {
+        let mut tx = self.pool.begin().await.map_err(|_| "Ошибка сохранения".to_string())?;
+
+        let rows = sqlx::query(&format!("SELECT {} FROM todos WHERE chat_id = $1 FOR UPDATE", TODO_COLUMNS))
+            .bind(chat_id.0)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|_| "Ошибка сохранения".to_string())?;
+        let mut todos: Vec<TodoItem> = rows.iter().map(todo_from_row).collect();
+        let hidden: Vec<String> = todos.iter().filter(|t| !t.is_active()).map(|t| t.id.clone()).collect();
+
+        let task_text = tasks::restore_task(&mut todos, task_id)?;
+
+        for todo in todos.iter().filter(|t| t.is_active() && hidden.contains(&t.id)) {
+            sqlx::query(
+                "UPDATE todos SET archived_at = NULL, deleted_at = NULL, completed = $3, completed_at = $4
+                 WHERE id = $1 AND chat_id = $2"
+            )
+            .bind(&todo.id)
+            .bind(chat_id.0)
+            .bind(todo.completed)
+            .bind(todo.completed_at)
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| "Ошибка сохранения".to_string())?;
+        }
+
+        tx.commit().await.map_err(|_| "Ошибка сохранения".to_string())?;
+        Ok(task_text)
+    }
+
+    async fn empty_trash(&self, chat_id: ChatId) -> Result<usize, String> {
+        let mut tx = self.pool.begin().await.map_err(|_| "Ошибка сохранения".to_string())?;
+        let purged = delete_trashed(&mut tx, "chat_id = $1", chat_id.0)
+            .await
+            .map_err(|_| "Ошибка сохранения".to_string())?;
+        tx.commit().await.map_err(|_| "Ошибка сохранения".to_string())?;
+        Ok(purged)
+    }
+
+    async fn archive_completed_tasks(&self, completed_before: DateTime<Utc>) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        // Задачи, выполненные до появления архива, архивируются по времени создания
+        let parents: Vec<Option<String>> = sqlx::query_scalar(&format!(
+            "WITH roots AS (
+                 SELECT id FROM todos
+                 WHERE parent_id IS NULL AND completed AND {active} AND COALESCE(completed_at, created_at) <= $1
+             )
+             UPDATE todos SET archived_at = $2
+             WHERE {active} AND (id IN (SELECT id FROM roots) OR parent_id IN (SELECT id FROM roots))
+             RETURNING parent_id",
+            active = ACTIVE
+        ))
+        .bind(completed_before)
+        .bind(Utc::now())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(parents.iter().filter(|parent_id| parent_id.is_none()).count())
+    }
+
+    async fn purge_trash(&self, deleted_before: DateTime<Utc>) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tx = self.pool.begin().await?;
+        let purged = delete_trashed(&mut tx, "deleted_at <= $1", deleted_before).await?;
+        tx.commit().await?;
+        Ok(purged)
+    }
+
+    // Методы для работы со списками
+    async fn get_lists(&self, chat_id: ChatId) -> Vec<TaskList> {
+        match self.pool.acquire().await {
+            Ok(mut conn) => fetch_lists(&mut conn, chat_id).await.unwrap_or_default(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    async fn create_list(&self, chat_id: ChatId, name: &str) -> Result<TaskList, String> {
+        let mut tx = self.pool.begin().await.map_err(|_| "Ошибка сохранения".to_string())?;
+
+        let mut lists = fetch_lists(&mut tx, chat_id).await.map_err(|_| "Ошибка сохранения".to_string())?;
+        let list = tasks::create_list(&mut lists, name)?;
+        sqlx::query("INSERT INTO task_lists (id, chat_id, name, created_at) VALUES ($1, $2, $3, $4)")
+            .bind(&list.id)
+            .bind(chat_id.0)
+            .bind(&list.name)
+            .bind(list.created_at)
+            .execute(&mut *tx)
+            .await
+            .map_err(list_error)?;
+
+        tx.commit().await.map_err(|_| "Ошибка сохранения".to_string())?;
+        Ok(list)
+    }
+
+    async fn rename_list(&self, chat_id: ChatId, list_id: &str, name: &str) -> Result<String, String> {
+        let mut tx = self.pool.begin().await.map_err(|_| "Ошибка сохранения".to_string())?;
+
+        let mut lists = fetch_lists(&mut tx, chat_id).await.map_err(|_| "Ошибка сохранения".to_string())?;
+        let old_name = tasks::rename_list(&mut lists, list_id, name)?;
+        sqlx::query("UPDATE task_lists SET name = $1 WHERE id = $2 AND chat_id = $3")
+            .bind(name)
+            .bind(list_id)
+            .bind(chat_id.0)
+            .execute(&mut *tx)
+            .await
+            .map_err(list_error)?;
+
+        tx.commit().await.map_err(|_| "Ошибка сохранения".to_string())?;
+        Ok(old_name)
+    }
+
+    async fn delete_list(&self, chat_id: ChatId, list_id: &str) -> Result<String, String> {
+        // Задачи переходят в основной список, а выбор текущего сбрасывается внешними ключами
+        let row = sqlx::query("DELETE FROM task_lists WHERE id = $1 AND chat_id = $2 RETURNING name")
+            .bind(list_id)
+            .bind(chat_id.0)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|_| "Ошибка сохранения".to_string())?;
+
+        row.map(|row| row.get("name"))
+            .ok_or_else(|| "Список не найден".to_string())
+    }
+
+    async fn get_current_list(&self, chat_id: ChatId) -> Option<TaskList> {
+        sqlx::query(
+            "SELECT l.id, l.name, l.created_at FROM current_lists c
+             JOIN task_lists l ON l.id = c.list_id WHERE c.chat_id = $1"
+        )
+        .bind(chat_id.0)
+        .fetch_optional(&self.pool)
+        .await
+        .unwrap_or(None)
+        .map(|row| task_list_from_row(&row))
+    }
+
+    async fn set_current_list(&self, chat_id: ChatId, list_id: Option<&str>) -> Result<(), String> {
+        let Some(list_id) = list_id else {
+            sqlx::query("DELETE FROM current_lists WHERE chat_id = $1")
+                .bind(chat_id.0)
+                .execute(&self.pool)
+                .await
+                .map_err(|_| "Ошибка сохранения".to_string())?;
+            return Ok(());
+        };
+
+        let result = sqlx::query(
+            "INSERT INTO current_lists (chat_id, list_id)
+             SELECT chat_id, id FROM task_lists WHERE id = $2 AND chat_id = $1
+             ON CONFLICT (chat_id) DO UPDATE SET list_id = EXCLUDED.list_id"
+        )
+        .bind(chat_id.0)
+        .bind(list_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|_| "Ошибка сохранения".to_string())?;
+
+        if result.rows_affected() == 0 {
+            return Err("Список не найден".to_string());
+        }
+        Ok(())
+    }
+
+    async fn move_task_to_list(&self, chat_id: ChatId, task_id: &str, list_id: Option<&str>) -> Result<String, String> {
+        if let Some(list_id) = list_id {
+            let exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM task_lists WHERE id = $1 AND chat_id = $2)")
+                .bind(list_id)
+                .bind(chat_id.0)
+                .fetch_one(&self.pool)
+                .await
+                .map_err(|_| "Ошибка сохранения".to_string())?;
+            if !exists {
+                return Err("Список не найден".to_string());
+            }
+        }
+
+        let task = sqlx::query(&format!("SELECT text, parent_id FROM todos WHERE id = $1 AND chat_id = $2 AND {}", ACTIVE))
+            .bind(task_id)
+            .bind(chat_id.0)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|_| "Ошибка сохранения".to_string())?
+            .ok_or_else(|| "Задача не найдена".to_string())?;
+        if task.get::<Option<String>, _>("parent_id").is_some() {
+            return Err("Подзадача переносится вместе с родительской задачей".to_string());
+        }
+
+        sqlx::query("UPDATE todos SET list_id = $1 WHERE chat_id = $2 AND (id = $3 OR parent_id = $3)")
+            .bind(list_id)
+            .bind(chat_id.0)
+            .bind(task_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|_| "Ошибка сохранения".to_string())?;
+
+        Ok(task.get("text"))
+    }
+
     // Методы для работы с напоминаниями
-    pub async fn get_user_reminders(&self, chat_id: ChatId) -> UserReminders {
+    async fn get_user_reminders(&self, chat_id: ChatId) -> UserReminders {
         let user_row = sqlx::query(
             "SELECT global_enabled FROM user_reminders WHERE chat_id = $1"
         )
@@ -148,7 +670,7 @@ impl PostgresStorage {
         let global_enabled = user_row.map(|row| row.get("global_enabled")).unwrap_or(true);
 
         let counter_rows = sqlx::query(
-            "SELECT counter_type, start_day, end_day, enabled, last_sent_month, last_sent_date, completed_this_month 
+            "SELECT counter_type, name, emoji, zones, start_day, end_day, enabled, last_sent_month, last_sent_date, completed_this_month 
              FROM counter_reminders WHERE chat_id = $1"
         )
         .bind(chat_id.0)
@@ -158,11 +680,15 @@ impl PostgresStorage {
 
         let mut reminders = std::collections::HashMap::new();
         for row in counter_rows {
-            let counter_type_str: String = row.get("counter_type");
-            let counter_type = CounterType::from_str(&counter_type_str).unwrap_or(CounterType::Water);
+            let counter_type = CounterType {
+                id: row.get("counter_type"),
+                name: row.get("name"),
+                emoji: row.get("emoji"),
+                zones: row.get("zones"),
+            };
             
             let reminder = CounterReminder {
-                counter_type: counter_type.clone(),
+                counter_type,
                 start_day: row.get::<i32, _>("start_day") as u32,
                 end_day: row.get::<i32, _>("end_day") as u32,
                 enabled: row.get("enabled"),
@@ -171,7 +697,7 @@ impl PostgresStorage {
                 completed_this_month: row.get("completed_this_month"),
             };
             
-            reminders.insert(counter_type.as_str().to_string(), reminder);
+            reminders.insert(reminder.counter_type.id.clone(), reminder);
         }
 
         UserReminders {
@@ -180,7 +706,7 @@ impl PostgresStorage {
         }
     }
 
-    pub async fn save_user_reminders(&self, chat_id: ChatId, reminders: UserReminders) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn save_user_reminders(&self, chat_id: ChatId, reminders: UserReminders) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         // Сохраняем глобальные настройки
         sqlx::query(
             "INSERT INTO user_reminders (chat_id, global_enabled) VALUES ($1, $2) 
@@ -194,19 +720,22 @@ impl PostgresStorage {
         // Сохраняем напоминания по счетчикам
         for (_, reminder) in reminders.reminders {
             sqlx::query(
-                "INSERT INTO counter_reminders (chat_id, counter_type, start_day, end_day, enabled, last_sent_month, last_sent_date, completed_this_month) 
-                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-                 ON CONFLICT (chat_id, counter_type) DO UPDATE SET 
-                 start_day = $3, end_day = $4, enabled = $5, last_sent_month = $6, last_sent_date = $7, completed_this_month = $8"
+                "INSERT INTO counter_reminders (chat_id, counter_type, name, emoji, start_day, end_day, enabled, last_sent_month, last_sent_date, completed_this_month, zones)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                 ON CONFLICT (chat_id, counter_type) DO UPDATE SET
+                 name = $3, emoji = $4, start_day = $5, end_day = $6, enabled = $7, last_sent_month = $8, last_sent_date = $9, completed_this_month = $10, zones = $11"
             )
             .bind(chat_id.0)
-            .bind(reminder.counter_type.as_str())
+            .bind(&reminder.counter_type.id)
+            .bind(&reminder.counter_type.name)
+            .bind(&reminder.counter_type.emoji)
             .bind(reminder.start_day as i32)
             .bind(reminder.end_day as i32)
             .bind(reminder.enabled)
             .bind(&reminder.last_sent_month)
             .bind(&reminder.last_sent_date)
             .bind(reminder.completed_this_month)
+            .bind(&reminder.counter_type.zones)
             .execute(&self.pool)
             .await?;
         }
@@ -214,32 +743,41 @@ impl PostgresStorage {
         Ok(())
     }
 
-    pub async fn add_counter_reminder(&self, chat_id: ChatId, reminder: CounterReminder) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn add_counter_reminder(&self, chat_id: ChatId, reminder: CounterReminder) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let mut user_reminders = self.get_user_reminders(chat_id).await;
         user_reminders.add_reminder(reminder);
         self.save_user_reminders(chat_id, user_reminders).await
     }
 
-    pub async fn toggle_global_reminders(&self, chat_id: ChatId) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    async fn delete_counter_reminder(&self, chat_id: ChatId, counter_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        sqlx::query("DELETE FROM counter_reminders WHERE chat_id = $1 AND counter_type = $2")
+            .bind(chat_id.0)
+            .bind(counter_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn toggle_global_reminders(&self, chat_id: ChatId) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
         let mut user_reminders = self.get_user_reminders(chat_id).await;
         let new_state = user_reminders.toggle_global();
         self.save_user_reminders(chat_id, user_reminders).await?;
         Ok(new_state)
     }
 
-    pub async fn mark_counter_completed(&self, chat_id: ChatId, counter_type: CounterType) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn mark_counter_completed(&self, chat_id: ChatId, counter_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         sqlx::query(
             "UPDATE counter_reminders SET completed_this_month = TRUE WHERE chat_id = $1 AND counter_type = $2"
         )
         .bind(chat_id.0)
-        .bind(counter_type.as_str())
+        .bind(counter_id)
         .execute(&self.pool)
         .await?;
         
         Ok(())
     }
 
-    pub async fn get_all_reminders(&self) -> std::collections::HashMap<String, UserReminders> {
+    async fn get_all_reminders(&self) -> std::collections::HashMap<String, UserReminders> {
         let mut result = std::collections::HashMap::new();
         
         // Получаем всех пользователей с напоминаниями
@@ -259,7 +797,7 @@ impl PostgresStorage {
         result
     }
 
-    pub async fn reset_monthly_statuses(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn reset_monthly_statuses(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let moscow_now = chrono_tz::Europe::Moscow.from_utc_datetime(&chrono::Utc::now().naive_utc());
         let current_month = moscow_now.format("%Y-%m").to_string();
         
@@ -274,4 +812,153 @@ impl PostgresStorage {
         
         Ok(())
     }
+
+    // Показания счетчиков
+    async fn add_meter_readings(&self, chat_id: ChatId, readings: Vec<MeterReading>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // Показания одной отправки сохраняются вместе или не сохраняются вовсе
+        let mut tx = self.pool.begin().await?;
+        for reading in readings {
+            sqlx::query(
+                "INSERT INTO meter_readings (id, chat_id, counter_id, zone, value, taken_at) VALUES ($1, $2, $3, $4, $5, $6)"
+            )
+            .bind(&reading.id)
+            .bind(chat_id.0)
+            .bind(&reading.counter_id)
+            .bind(&reading.zone)
+            .bind(reading.value)
+            .bind(reading.taken_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_meter_readings(&self, chat_id: ChatId, counter_id: &str) -> Vec<MeterReading> {
+        sqlx::query(
+            "SELECT id, counter_id, zone, value, taken_at FROM meter_readings
+             WHERE chat_id = $1 AND counter_id = $2 ORDER BY taken_at, zone"
+        )
+        .bind(chat_id.0)
+        .bind(counter_id)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+        .iter()
+        .map(meter_reading_from_row)
+        .collect()
+    }
+
+    // Тарифы
+    async fn set_tariffs(&self, chat_id: ChatId, tariffs: Vec<Tariff>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut tx = self.pool.begin().await?;
+        for tariff in tariffs {
+            sqlx::query(
+                "INSERT INTO tariffs (chat_id, counter_id, zone, effective_from, price) VALUES ($1, $2, $3, $4, $5)
+                 ON CONFLICT (chat_id, counter_id, zone, effective_from) DO UPDATE SET price = excluded.price"
+            )
+            .bind(chat_id.0)
+            .bind(&tariff.counter_id)
+            .bind(&tariff.zone)
+            .bind(tariff.effective_from)
+            .bind(tariff.price)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_tariffs(&self, chat_id: ChatId, counter_id: &str) -> Vec<Tariff> {
+        sqlx::query(
+            "SELECT counter_id, zone, price, effective_from FROM tariffs
+             WHERE chat_id = $1 AND counter_id = $2 ORDER BY effective_from, zone"
+        )
+        .bind(chat_id.0)
+        .bind(counter_id)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+        .iter()
+        .map(tariff_from_row)
+        .collect()
+    }
+
+    // Методы для напоминаний о задачах
+    async fn add_task_reminder(&self, reminder: TaskReminder) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        sqlx::query(
+            "INSERT INTO task_reminders (id, chat_id, task_id, remind_at, attempts) VALUES ($1, $2, $3, $4, $5)"
+        )
+        .bind(&reminder.id)
+        .bind(reminder.chat_id)
+        .bind(&reminder.task_id)
+        .bind(reminder.remind_at)
+        .bind(reminder.attempts)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn take_due_task_reminders(&self, now: DateTime<Utc>) -> Result<Vec<TaskReminder>, Box<dyn std::error::Error + Send + Sync>> {
+        // Удаление с RETURNING атомарно «забирает» напоминания, даже если их ищут параллельно
+        let rows = sqlx::query(
+            "DELETE FROM task_reminders WHERE remind_at <= $1 RETURNING id, chat_id, task_id, remind_at, attempts"
+        )
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(task_reminder_from_row).collect())
+    }
+
+    async fn remove_task_reminders(&self, chat_id: ChatId, task_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        sqlx::query("DELETE FROM task_reminders WHERE chat_id = $1 AND task_id = $2")
+            .bind(chat_id.0)
+            .bind(task_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_user_state(&self, chat_id: ChatId) -> Option<SavedUserState> {
+        let row = sqlx::query("SELECT state::TEXT AS state, updated_at FROM dialogue_states WHERE chat_id = $1")
+            .bind(chat_id.0)
+            .fetch_optional(&self.pool)
+            .await
+            .unwrap_or(None)?;
+
+        saved_user_state(row.get("state"), row.get("updated_at"))
+    }
+
+    async fn save_user_state(&self, chat_id: ChatId, state: UserState) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if state == UserState::Default {
+            sqlx::query("DELETE FROM dialogue_states WHERE chat_id = $1")
+                .bind(chat_id.0)
+                .execute(&self.pool)
+                .await?;
+            return Ok(());
+        }
+
+        sqlx::query(
+            "INSERT INTO dialogue_states (chat_id, state, updated_at) VALUES ($1, $2::JSONB, NOW())
+             ON CONFLICT (chat_id) DO UPDATE SET state = EXCLUDED.state, updated_at = EXCLUDED.updated_at"
+        )
+        .bind(chat_id.0)
+        .bind(serde_json::to_string(&state)?)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn purge_user_states(&self, updated_before: DateTime<Utc>) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let result = sqlx::query("DELETE FROM dialogue_states WHERE updated_at <= $1")
+            .bind(updated_before)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() as usize)
+    }
 }
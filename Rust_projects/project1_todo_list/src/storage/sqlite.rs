@@ -0,0 +1,1408 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use sqlx::{Row, SqlitePool};
+use sqlx::sqlite::SqliteConnectOptions;
+use teloxide::types::ChatId;
+use chrono::{DateTime, TimeZone, Utc};
+use async_trait::async_trait;
+
+use crate::models::{TodoItem, TaskEdit, UserReminders, CounterReminder, CounterType, MeterReading, Tariff, TaskReminder, Recurrence, Priority, TaskList, UserState, SavedUserState};
+use super::Storage;
+use super::dialogue::saved_user_state;
+use super::tasks::{self, complete_task};
+
+/// Хранилище в файле SQLite: та же схема, что и у `PostgresStorage`,
+/// но без отдельного сервера базы данных
+#[derive(Clone)]
+pub struct SqliteStorage {
+    pool: SqlitePool,
+}
+
+impl SqliteStorage {
+    pub async fn new(file_path: &str) -> Result<Self, sqlx::Error> {
+        // SQLite создает файл базы, но не папку для него
+        if let Some(parent) = std::path::Path::new(file_path).parent().filter(|p| !p.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let options = SqliteConnectOptions::from_str(&format!("sqlite://{}", file_path))?
+            .create_if_missing(true);
+        let pool = SqlitePool::connect_with(options).await?;
+        Self::with_pool(pool).await
+    }
+
+    /// Хранилище в памяти SQLite (одно соединение, иначе у каждого будет своя база)
+    #[cfg(test)]
+    pub async fn in_memory() -> Result<Self, sqlx::Error> {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await?;
+        Self::with_pool(pool).await
+    }
+
+    async fn with_pool(pool: SqlitePool) -> Result<Self, sqlx::Error> {
+        // Создаем таблицы если их нет
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS todos (
+                id TEXT PRIMARY KEY,
+                chat_id INTEGER NOT NULL,
+                text TEXT NOT NULL,
+                completed BOOLEAN NOT NULL DEFAULT FALSE,
+                created_at TEXT NOT NULL
+            )
+            "#
+        ).execute(&pool).await?;
+
+        // Колонки, появившиеся после первой версии схемы
+        add_column_if_missing(&pool, "todos", "due_at", "TEXT").await?;
+        add_column_if_missing(&pool, "todos", "recurrence", "TEXT").await?;
+        add_column_if_missing(&pool, "todos", "priority", "TEXT NOT NULL DEFAULT 'normal'").await?;
+        add_column_if_missing(&pool, "todos", "parent_id", "TEXT REFERENCES todos(id) ON DELETE CASCADE").await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS task_lists (
+                id TEXT PRIMARY KEY,
+                chat_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )
+            "#
+        ).execute(&pool).await?;
+
+        add_column_if_missing(&pool, "todos", "list_id", "TEXT REFERENCES task_lists(id) ON DELETE SET NULL").await?;
+        add_column_if_missing(&pool, "todos", "position", "INTEGER NOT NULL DEFAULT 0").await?;
+        add_column_if_missing(&pool, "todos", "completed_at", "TEXT").await?;
+        add_column_if_missing(&pool, "todos", "archived_at", "TEXT").await?;
+        add_column_if_missing(&pool, "todos", "deleted_at", "TEXT").await?;
+
+        // Задачи, сохраненные до появления позиций, нумеруются по времени добавления
+        sqlx::query(
+            r#"
+            UPDATE todos SET position = (
+                SELECT COUNT(*) FROM todos t
+                WHERE t.chat_id = todos.chat_id
+                  AND (t.created_at < todos.created_at OR (t.created_at = todos.created_at AND t.id <= todos.id))
+            )
+            WHERE position = 0
+            "#
+        ).execute(&pool).await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS current_lists (
+                chat_id INTEGER PRIMARY KEY,
+                list_id TEXT NOT NULL REFERENCES task_lists(id) ON DELETE CASCADE
+            )
+            "#
+        ).execute(&pool).await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS todo_tags (
+                todo_id TEXT NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+                tag TEXT NOT NULL,
+                PRIMARY KEY (todo_id, tag)
+            )
+            "#
+        ).execute(&pool).await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS user_reminders (
+                chat_id INTEGER PRIMARY KEY,
+                global_enabled BOOLEAN NOT NULL DEFAULT TRUE
+            )
+            "#
+        ).execute(&pool).await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS counter_reminders (
+                chat_id INTEGER NOT NULL,
+                counter_type TEXT NOT NULL,
+                start_day INTEGER NOT NULL,
+                end_day INTEGER NOT NULL,
+                enabled BOOLEAN NOT NULL DEFAULT TRUE,
+                last_sent_month TEXT,
+                last_sent_date TEXT,
+                completed_this_month BOOLEAN NOT NULL DEFAULT FALSE,
+                PRIMARY KEY (chat_id, counter_type)
+            )
+            "#
+        ).execute(&pool).await?;
+
+        // Счетчики пользователя; до них были только вода и электричество, им даются прежние названия
+        add_column_if_missing(&pool, "counter_reminders", "name", "TEXT NOT NULL DEFAULT ''").await?;
+        add_column_if_missing(&pool, "counter_reminders", "emoji", "TEXT NOT NULL DEFAULT ''").await?;
+        sqlx::query(
+            r#"
+            UPDATE counter_reminders SET
+                name = CASE counter_type WHEN 'water' THEN 'Вода' WHEN 'electricity' THEN 'Электричество' ELSE counter_type END,
+                emoji = CASE counter_type WHEN 'water' THEN '💧' WHEN 'electricity' THEN '⚡' ELSE '📟' END
+            WHERE name = ''
+            "#
+        ).execute(&pool).await?;
+        add_column_if_missing(&pool, "counter_reminders", "zones", "TEXT NOT NULL DEFAULT ''").await?;
+
+        // Показания счетчиков удаляются вместе со счетчиком
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS meter_readings (
+                id TEXT PRIMARY KEY,
+                chat_id INTEGER NOT NULL,
+                counter_id TEXT NOT NULL,
+                zone TEXT NOT NULL DEFAULT '',
+                value REAL NOT NULL,
+                taken_at TEXT NOT NULL,
+                FOREIGN KEY (chat_id, counter_id) REFERENCES counter_reminders(chat_id, counter_type) ON DELETE CASCADE
+            )
+            "#
+        ).execute(&pool).await?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_meter_readings_counter ON meter_readings(chat_id, counter_id, taken_at)")
+            .execute(&pool)
+            .await?;
+
+        // Тарифы счетчиков: цена единицы в зоне с даты effective_from
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS tariffs (
+                chat_id INTEGER NOT NULL,
+                counter_id TEXT NOT NULL,
+                zone TEXT NOT NULL DEFAULT '',
+                effective_from TEXT NOT NULL,
+                price REAL NOT NULL,
+                PRIMARY KEY (chat_id, counter_id, zone, effective_from),
+                FOREIGN KEY (chat_id, counter_id) REFERENCES counter_reminders(chat_id, counter_type) ON DELETE CASCADE
+            )
+            "#
+        ).execute(&pool).await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS task_reminders (
+                id TEXT PRIMARY KEY,
+                chat_id INTEGER NOT NULL,
+                task_id TEXT NOT NULL,
+                remind_at TEXT NOT NULL
+            )
+            "#
+        ).execute(&pool).await?;
+        add_column_if_missing(&pool, "task_reminders", "attempts", "INTEGER NOT NULL DEFAULT 0").await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS dialogue_states (
+                chat_id INTEGER PRIMARY KEY,
+                state TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )
+            "#
+        ).execute(&pool).await?;
+
+        Ok(Self { pool })
+    }
+}
+
+/// У SQLite нет `ADD COLUMN IF NOT EXISTS`, поэтому сверяемся с `PRAGMA table_info`
+async fn add_column_if_missing(pool: &SqlitePool, table: &str, column: &str, definition: &str) -> Result<(), sqlx::Error> {
+    let exists = sqlx::query(&format!("PRAGMA table_info({})", table))
+        .fetch_all(pool)
+        .await?
+        .iter()
+        .any(|row| row.get::<String, _>("name") == column);
+
+    if !exists {
+        sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+            .execute(pool)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Колонки задачи для SELECT и RETURNING; теги собираются из `todo_tags` через запятую
+const TODO_COLUMNS: &str = "id, text, completed, created_at, due_at, recurrence, priority, parent_id, list_id, position, \
+    completed_at, archived_at, deleted_at, (SELECT group_concat(tag, ',') FROM (SELECT tag FROM todo_tags WHERE todo_id = todos.id ORDER BY tag)) AS tags";
+
+fn todo_from_row(row: &sqlx::sqlite::SqliteRow) -> TodoItem {
+    TodoItem {
+        id: row.get("id"),
+        text: row.get("text"),
+        completed: row.get("completed"),
+        created_at: row.get("created_at"),
+        due_at: row.get("due_at"),
+        recurrence: row
+            .get::<Option<String>, _>("recurrence")
+            .and_then(|rule| Recurrence::from_rule(&rule)),
+        priority: Priority::from_str(row.get("priority")).unwrap_or_default(),
+        parent_id: row.get("parent_id"),
+        list_id: row.get("list_id"),
+        position: row.get("position"),
+        tags: row
+            .get::<Option<String>, _>("tags")
+            .map(|tags| tags.split(',').map(str::to_string).collect())
+            .unwrap_or_default(),
+        completed_at: row.get("completed_at"),
+        archived_at: row.get("archived_at"),
+        deleted_at: row.get("deleted_at"),
+    }
+}
+
+/// Условие «задача в списке, а не в архиве или корзине»
+const ACTIVE: &str = "archived_at IS NULL AND deleted_at IS NULL";
+
+/// Условие «задача из текущего списка чата»; `?1` — chat_id
+const IN_CURRENT_LIST: &str = "list_id IS (SELECT list_id FROM current_lists WHERE chat_id = ?1)";
+
+fn task_list_from_row(row: &sqlx::sqlite::SqliteRow) -> TaskList {
+    TaskList {
+        id: row.get("id"),
+        name: row.get("name"),
+        created_at: row.get("created_at"),
+    }
+}
+
+/// Списки чата; названия сравниваются в Rust, т.к. `lower()` в SQLite не знает кириллицу
+async fn fetch_lists(conn: &mut sqlx::SqliteConnection, chat_id: ChatId) -> Result<Vec<TaskList>, sqlx::Error> {
+    let rows = sqlx::query("SELECT id, name, created_at FROM task_lists WHERE chat_id = ?1 ORDER BY created_at")
+        .bind(chat_id.0)
+        .fetch_all(&mut *conn)
+        .await?;
+
+    Ok(rows.iter().map(task_list_from_row).collect())
+}
+
+async fn insert_todo(conn: &mut sqlx::SqliteConnection, chat_id: ChatId, todo_item: &TodoItem) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO todos (id, chat_id, text, completed, created_at, due_at, recurrence, priority, parent_id, list_id, position, completed_at, archived_at, deleted_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)"
+    )
+    .bind(&todo_item.id)
+    .bind(chat_id.0)
+    .bind(&todo_item.text)
+    .bind(todo_item.completed)
+    .bind(todo_item.created_at)
+    .bind(todo_item.due_at)
+    .bind(todo_item.recurrence.as_ref().map(Recurrence::as_rule))
+    .bind(todo_item.priority.as_str())
+    .bind(&todo_item.parent_id)
+    .bind(&todo_item.list_id)
+    .bind(todo_item.position)
+    .bind(todo_item.completed_at)
+    .bind(todo_item.archived_at)
+    .bind(todo_item.deleted_at)
+    .execute(&mut *conn)
+    .await?;
+
+    for tag in &todo_item.tags {
+        sqlx::query("INSERT INTO todo_tags (todo_id, tag) VALUES (?1, ?2)")
+            .bind(&todo_item.id)
+            .bind(tag)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Окончательно удаляет задачи корзины, подходящие под `condition` (с параметром `?1`).
+/// Сначала удаляются подзадачи: удаленные каскадом строки не попали бы в счетчик.
+async fn delete_trashed<T>(conn: &mut sqlx::SqliteConnection, condition: &str, param: T) -> Result<usize, sqlx::Error>
+where
+    T: for<'q> sqlx::Encode<'q, sqlx::Sqlite> + sqlx::Type<sqlx::Sqlite> + Send + Clone,
+{
+    let mut purged = 0;
+    for level in ["parent_id IS NOT NULL", "parent_id IS NULL"] {
+        let result = sqlx::query(&format!("DELETE FROM todos WHERE deleted_at IS NOT NULL AND {} AND {}", condition, level))
+            .bind(param.clone())
+            .execute(&mut *conn)
+            .await?;
+        purged += result.rows_affected() as usize;
+    }
+    Ok(purged)
+}
+
+fn meter_reading_from_row(row: &sqlx::sqlite::SqliteRow) -> MeterReading {
+    MeterReading {
+        id: row.get("id"),
+        counter_id: row.get("counter_id"),
+        zone: row.get("zone"),
+        value: row.get("value"),
+        taken_at: row.get("taken_at"),
+    }
+}
+
+fn tariff_from_row(row: &sqlx::sqlite::SqliteRow) -> Tariff {
+    Tariff {
+        counter_id: row.get("counter_id"),
+        zone: row.get("zone"),
+        price: row.get("price"),
+        effective_from: row.get("effective_from"),
+    }
+}
+
+fn task_reminder_from_row(row: &sqlx::sqlite::SqliteRow) -> TaskReminder {
+    TaskReminder {
+        id: row.get("id"),
+        chat_id: row.get("chat_id"),
+        task_id: row.get("task_id"),
+        remind_at: row.get("remind_at"),
+        attempts: row.get("attempts"),
+    }
+}
+
+#[async_trait]
+impl Storage for SqliteStorage {
+    // Методы для работы с задачами
+    async fn add_task(&self, chat_id: ChatId, mut todo_item: TodoItem) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut tx = self.pool.begin().await?;
+        todo_item.position = sqlx::query_scalar("SELECT COALESCE(MAX(position), 0) + 1 FROM todos WHERE chat_id = ?1")
+            .bind(chat_id.0)
+            .fetch_one(&mut *tx)
+            .await?;
+        insert_todo(&mut tx, chat_id, &todo_item).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_tasks(&self, chat_id: ChatId) -> Vec<TodoItem> {
+        let rows = sqlx::query(
+            &format!("SELECT {} FROM todos WHERE chat_id = ?1 AND {} ORDER BY position, created_at", TODO_COLUMNS, ACTIVE)
+        )
+        .bind(chat_id.0)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default();
+
+        rows.iter().map(todo_from_row).collect()
+    }
+
+    async fn get_tags(&self, chat_id: ChatId) -> Vec<String> {
+        sqlx::query_scalar(&format!(
+            "SELECT DISTINCT g.tag FROM todo_tags g JOIN todos t ON t.id = g.todo_id
+             WHERE t.chat_id = ?1 AND t.{} AND t.archived_at IS NULL AND t.deleted_at IS NULL ORDER BY g.tag",
+            IN_CURRENT_LIST
+        ))
+        .bind(chat_id.0)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+    }
+
+    async fn mark_task_completed(&self, chat_id: ChatId, task_id: &str) -> Result<String, String> {
+        let mut tx = self.pool.begin().await.map_err(|_| "Ошибка сохранения".to_string())?;
+
+        let rows = sqlx::query(&format!("SELECT {} FROM todos WHERE chat_id = ?1 ORDER BY position, created_at", TODO_COLUMNS))
+            .bind(chat_id.0)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|_| "Ошибка сохранения".to_string())?;
+        let mut todos: Vec<TodoItem> = rows.iter().map(todo_from_row).collect();
+
+        let now = Utc::now();
+        let completion = complete_task(&mut todos, task_id, now)?;
+
+        for id in &completion.completed {
+            sqlx::query("UPDATE todos SET completed = TRUE, completed_at = ?3 WHERE id = ?1 AND chat_id = ?2")
+                .bind(id)
+                .bind(chat_id.0)
+                .bind(now)
+                .execute(&mut *tx)
+                .await
+                .map_err(|_| "Ошибка сохранения".to_string())?;
+        }
+        for todo in &completion.spawned {
+            insert_todo(&mut tx, chat_id, todo)
+                .await
+                .map_err(|_| "Ошибка сохранения".to_string())?;
+        }
+
+        tx.commit().await.map_err(|_| "Ошибка сохранения".to_string())?;
+        Ok(completion.text)
+    }
+
+    async fn set_task_recurrence(&self, chat_id: ChatId, task_id: &str, recurrence: Option<Recurrence>) -> Result<String, String> {
+        let row = sqlx::query(&format!("UPDATE todos SET recurrence = ?1 WHERE id = ?2 AND chat_id = ?3 AND {} RETURNING text", ACTIVE))
+            .bind(recurrence.as_ref().map(Recurrence::as_rule))
+            .bind(task_id)
+            .bind(chat_id.0)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|_| "Ошибка сохранения".to_string())?;
+
+        row.map(|row| row.get("text"))
+            .ok_or_else(|| "Задача не найдена".to_string())
+    }
+
+    async fn edit_task(&self, chat_id: ChatId, task_id: &str, edit: &TaskEdit) -> Result<String, String> {
+        // RETURNING в SQLite отдает только новые значения, поэтому прежний текст читаем отдельно
+        let mut tx = self.pool.begin().await.map_err(|_| "Ошибка сохранения".to_string())?;
+
+        let old_text: String = sqlx::query_scalar(&format!("SELECT text FROM todos WHERE id = ?1 AND chat_id = ?2 AND {}", ACTIVE))
+            .bind(task_id)
+            .bind(chat_id.0)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|_| "Ошибка сохранения".to_string())?
+            .ok_or_else(|| "Задача не найдена".to_string())?;
+        sqlx::query(
+            "UPDATE todos SET text = ?1, priority = COALESCE(?2, priority), due_at = COALESCE(?3, due_at)
+             WHERE id = ?4 AND chat_id = ?5"
+        )
+            .bind(&edit.text)
+            .bind(edit.priority.map(|p| p.as_str()))
+            .bind(edit.due_at)
+            .bind(task_id)
+            .bind(chat_id.0)
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| "Ошибка сохранения".to_string())?;
+        if let Some(tags) = &edit.tags {
+            sqlx::query("DELETE FROM todo_tags WHERE todo_id = ?1")
+                .bind(task_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|_| "Ошибка сохранения".to_string())?;
+            for tag in tags {
+                sqlx::query("INSERT INTO todo_tags (todo_id, tag) VALUES (?1, ?2)")
+                    .bind(task_id)
+                    .bind(tag)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|_| "Ошибка сохранения".to_string())?;
+            }
+        }
+
+        tx.commit().await.map_err(|_| "Ошибка сохранения".to_string())?;
+        Ok(old_text)
+    }
+
+    async fn set_task_priority(&self, chat_id: ChatId, task_id: &str, priority: Priority) -> Result<String, String> {
+        let row = sqlx::query(&format!("UPDATE todos SET priority = ?1 WHERE id = ?2 AND chat_id = ?3 AND {} RETURNING text", ACTIVE))
+            .bind(priority.as_str())
+            .bind(task_id)
+            .bind(chat_id.0)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|_| "Ошибка сохранения".to_string())?;
+
+        row.map(|row| row.get("text"))
+            .ok_or_else(|| "Задача не найдена".to_string())
+    }
+
+    async fn reorder_tasks(&self, chat_id: ChatId, task_ids: &[String]) -> Result<(), String> {
+        let mut tx = self.pool.begin().await.map_err(|_| "Ошибка сохранения".to_string())?;
+
+        let rows = sqlx::query(&format!("SELECT {} FROM todos WHERE chat_id = ?1 ORDER BY position, created_at", TODO_COLUMNS))
+            .bind(chat_id.0)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|_| "Ошибка сохранения".to_string())?;
+        let mut todos: Vec<TodoItem> = rows.iter().map(todo_from_row).collect();
+
+        tasks::reorder_tasks(&mut todos, task_ids)?;
+
+        for todo in &todos {
+            sqlx::query("UPDATE todos SET position = ?1 WHERE id = ?2 AND chat_id = ?3")
+                .bind(todo.position)
+                .bind(&todo.id)
+                .bind(chat_id.0)
+                .execute(&mut *tx)
+                .await
+                .map_err(|_| "Ошибка сохранения".to_string())?;
+        }
+
+        tx.commit().await.map_err(|_| "Ошибка сохранения".to_string())?;
+        Ok(())
+    }
+
+    async fn remove_task(&self, chat_id: ChatId, task_id: &str) -> Result<String, String> {
+        let mut tx = self.pool.begin().await.map_err(|_| "Ошибка сохранения".to_string())?;
+        let now = Utc::now();
+
+        let row = sqlx::query(&format!(
+            "UPDATE todos SET deleted_at = ?3 WHERE id = ?1 AND chat_id = ?2 AND {} RETURNING text",
+            ACTIVE
+        ))
+        .bind(task_id)
+        .bind(chat_id.0)
+        .bind(now)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|_| "Ошибка сохранения".to_string())?
+        .ok_or_else(|| "Задача не найдена".to_string())?;
+
+        // Подзадачи попадают в корзину с тем же временем, чтобы восстановиться вместе с родителем
+        sqlx::query(&format!("UPDATE todos SET deleted_at = ?3 WHERE parent_id = ?1 AND chat_id = ?2 AND {}", ACTIVE))
+            .bind(task_id)
+            .bind(chat_id.0)
+            .bind(now)
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| "Ошибка сохранения".to_string())?;
+
+        tx.commit().await.map_err(|_| "Ошибка сохранения".to_string())?;
+        Ok(row.get("text"))
+    }
+
+    async fn clear_tasks(&self, chat_id: ChatId) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        sqlx::query(&format!("UPDATE todos SET deleted_at = ?2 WHERE chat_id = ?1 AND {} AND {}", IN_CURRENT_LIST, ACTIVE))
+            .bind(chat_id.0)
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn restore_tasks(&self, chat_id: ChatId, mut todos: Vec<TodoItem>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // Родительские задачи вставляются раньше подзадач, которые на них ссылаются
+        todos.sort_by_key(|t| t.parent_id.is_some());
+
+        let mut tx = self.pool.begin().await?;
+        for todo_item in &todos {
+            // Задача, которая еще лежит в корзине, просто возвращается из неё
+            let restored = sqlx::query("UPDATE todos SET archived_at = ?3, deleted_at = ?4 WHERE id = ?1 AND chat_id = ?2")
+                .bind(&todo_item.id)
+                .bind(chat_id.0)
+                .bind(todo_item.archived_at)
+                .bind(todo_item.deleted_at)
+                .execute(&mut *tx)
+                .await?;
+            if restored.rows_affected() == 0 {
+                insert_todo(&mut tx, chat_id, todo_item).await?;
+            }
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // Методы для работы с архивом и корзиной
+    async fn get_archived_tasks(&self, chat_id: ChatId) -> Vec<TodoItem> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM todos WHERE chat_id = ?1 AND archived_at IS NOT NULL ORDER BY archived_at DESC, position, created_at",
+            TODO_COLUMNS
+        ))
+        .bind(chat_id.0)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default();
+
+        rows.iter().map(todo_from_row).collect()
+    }
+
+    async fn get_trashed_tasks(&self, chat_id: ChatId) -> Vec<TodoItem> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM todos WHERE chat_id = ?1 AND deleted_at IS NOT NULL ORDER BY deleted_at DESC, position, created_at",
+            TODO_COLUMNS
+        ))
+        .bind(chat_id.0)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default();
+
+        rows.iter().map(todo_from_row).collect()
+    }
+
+    async fn restore_task(&self, chat_id: ChatId, task_id: &str) -> Result<String, String> {
+        let mut tx = self.pool.begin().await.map_err(|_| "Ошибка сохранения".to_string())?;
+
+        let rows = sqlx::query(&format!("SELECT {} FROM todos WHERE chat_id = ?1", TODO_COLUMNS))
+            .bind(chat_id.0)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|_| "Ошибка сохранения".to_string())?;
+        let mut todos: Vec<TodoItem> = rows.iter().map(todo_from_row).collect();
+        let hidden: Vec<String> = todos.iter().filter(|t| !t.is_active()).map(|t| t.id.clone()).collect();
+
+        let task_text = tasks::restore_task(&mut todos, task_id)?;
+
+        for todo in todos.iter().filter(|t| t.is_active() && hidden.contains(&t.id)) {
+            sqlx::query(
+                "UPDATE todos SET archived_at = NULL, deleted_at = NULL, completed = ?3, completed_at = ?4
+                 WHERE id = ?1 AND chat_id = ?2"
+            )
+            .bind(&todo.id)
+            .bind(chat_id.0)
+            .bind(todo.completed)
+            .bind(todo.completed_at)
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| "Ошибка сохранения".to_string())?;
+        }
+
+        tx.commit().await.map_err(|_| "Ошибка сохранения".to_string())?;
+        Ok(task_text)
+    }
+
+    async fn empty_trash(&self, chat_id: ChatId) -> Result<usize, String> {
+        let mut tx = self.pool.begin().await.map_err(|_| "Ошибка сохранения".to_string())?;
+        let purged = delete_trashed(&mut tx, "chat_id = ?1", chat_id.0)
+            .await
+            .map_err(|_| "Ошибка сохранения".to_string())?;
+        tx.commit().await.map_err(|_| "Ошибка сохранения".to_string())?;
+        Ok(purged)
+    }
+
+    async fn archive_completed_tasks(&self, completed_before: DateTime<Utc>) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        // Задачи, выполненные до появления архива, архивируются по времени создания
+        let parents: Vec<Option<String>> = sqlx::query_scalar(&format!(
+            "WITH roots AS (
+                 SELECT id FROM todos
+                 WHERE parent_id IS NULL AND completed AND {active} AND COALESCE(completed_at, created_at) <= ?1
+             )
+             UPDATE todos SET archived_at = ?2
+             WHERE {active} AND (id IN (SELECT id FROM roots) OR parent_id IN (SELECT id FROM roots))
+             RETURNING parent_id",
+            active = ACTIVE
+        ))
+        .bind(completed_before)
+        .bind(Utc::now())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(parents.iter().filter(|parent_id| parent_id.is_none()).count())
+    }
+
+    async fn purge_trash(&self, deleted_before: DateTime<Utc>) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tx = self.pool.begin().await?;
+        let purged = delete_trashed(&mut tx, "deleted_at <= ?1", deleted_before).await?;
+        tx.commit().await?;
+        Ok(purged)
+    }
+
+    // Методы для работы со списками
+    async fn get_lists(&self, chat_id: ChatId) -> Vec<TaskList> {
+        match self.pool.acquire().await {
+            Ok(mut conn) => fetch_lists(&mut conn, chat_id).await.unwrap_or_default(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    async fn create_list(&self, chat_id: ChatId, name: &str) -> Result<TaskList, String> {
+        let mut tx = self.pool.begin().await.map_err(|_| "Ошибка сохранения".to_string())?;
+
+        let mut lists = fetch_lists(&mut tx, chat_id).await.map_err(|_| "Ошибка сохранения".to_string())?;
+        let list = tasks::create_list(&mut lists, name)?;
+        sqlx::query("INSERT INTO task_lists (id, chat_id, name, created_at) VALUES (?1, ?2, ?3, ?4)")
+            .bind(&list.id)
+            .bind(chat_id.0)
+            .bind(&list.name)
+            .bind(list.created_at)
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| "Ошибка сохранения".to_string())?;
+
+        tx.commit().await.map_err(|_| "Ошибка сохранения".to_string())?;
+        Ok(list)
+    }
+
+    async fn rename_list(&self, chat_id: ChatId, list_id: &str, name: &str) -> Result<String, String> {
+        let mut tx = self.pool.begin().await.map_err(|_| "Ошибка сохранения".to_string())?;
+
+        let mut lists = fetch_lists(&mut tx, chat_id).await.map_err(|_| "Ошибка сохранения".to_string())?;
+        let old_name = tasks::rename_list(&mut lists, list_id, name)?;
+        sqlx::query("UPDATE task_lists SET name = ?1 WHERE id = ?2 AND chat_id = ?3")
+            .bind(name)
+            .bind(list_id)
+            .bind(chat_id.0)
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| "Ошибка сохранения".to_string())?;
+
+        tx.commit().await.map_err(|_| "Ошибка сохранения".to_string())?;
+        Ok(old_name)
+    }
+
+    async fn delete_list(&self, chat_id: ChatId, list_id: &str) -> Result<String, String> {
+        // Задачи переходят в основной список, а выбор текущего сбрасывается внешними ключами
+        let row = sqlx::query("DELETE FROM task_lists WHERE id = ?1 AND chat_id = ?2 RETURNING name")
+            .bind(list_id)
+            .bind(chat_id.0)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|_| "Ошибка сохранения".to_string())?;
+
+        row.map(|row| row.get("name"))
+            .ok_or_else(|| "Список не найден".to_string())
+    }
+
+    async fn get_current_list(&self, chat_id: ChatId) -> Option<TaskList> {
+        sqlx::query(
+            "SELECT l.id, l.name, l.created_at FROM current_lists c
+             JOIN task_lists l ON l.id = c.list_id WHERE c.chat_id = ?1"
+        )
+        .bind(chat_id.0)
+        .fetch_optional(&self.pool)
+        .await
+        .unwrap_or(None)
+        .map(|row| task_list_from_row(&row))
+    }
+
+    async fn set_current_list(&self, chat_id: ChatId, list_id: Option<&str>) -> Result<(), String> {
+        let Some(list_id) = list_id else {
+            sqlx::query("DELETE FROM current_lists WHERE chat_id = ?1")
+                .bind(chat_id.0)
+                .execute(&self.pool)
+                .await
+                .map_err(|_| "Ошибка сохранения".to_string())?;
+            return Ok(());
+        };
+
+        let result = sqlx::query(
+            "INSERT INTO current_lists (chat_id, list_id)
+             SELECT chat_id, id FROM task_lists WHERE id = ?2 AND chat_id = ?1
+             ON CONFLICT (chat_id) DO UPDATE SET list_id = excluded.list_id"
+        )
+        .bind(chat_id.0)
+        .bind(list_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|_| "Ошибка сохранения".to_string())?;
+
+        if result.rows_affected() == 0 {
+            return Err("Список не найден".to_string());
+        }
+        Ok(())
+    }
+
+    async fn move_task_to_list(&self, chat_id: ChatId, task_id: &str, list_id: Option<&str>) -> Result<String, String> {
+        if let Some(list_id) = list_id {
+            let exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM task_lists WHERE id = ?1 AND chat_id = ?2)")
+                .bind(list_id)
+                .bind(chat_id.0)
+                .fetch_one(&self.pool)
+                .await
+                .map_err(|_| "Ошибка сохранения".to_string())?;
+            if !exists {
+                return Err("Список не найден".to_string());
+            }
+        }
+
+        let task = sqlx::query(&format!("SELECT text, parent_id FROM todos WHERE id = ?1 AND chat_id = ?2 AND {}", ACTIVE))
+            .bind(task_id)
+            .bind(chat_id.0)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|_| "Ошибка сохранения".to_string())?
+            .ok_or_else(|| "Задача не найдена".to_string())?;
+        if task.get::<Option<String>, _>("parent_id").is_some() {
+            return Err("Подзадача переносится вместе с родительской задачей".to_string());
+        }
+
+        sqlx::query("UPDATE todos SET list_id = ?1 WHERE chat_id = ?2 AND (id = ?3 OR parent_id = ?3)")
+            .bind(list_id)
+            .bind(chat_id.0)
+            .bind(task_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|_| "Ошибка сохранения".to_string())?;
+
+        Ok(task.get("text"))
+    }
+
+    // Методы для работы с напоминаниями
+    async fn get_user_reminders(&self, chat_id: ChatId) -> UserReminders {
+        let user_row = sqlx::query(
+            "SELECT global_enabled FROM user_reminders WHERE chat_id = ?1"
+        )
+        .bind(chat_id.0)
+        .fetch_optional(&self.pool)
+        .await
+        .unwrap_or(None);
+
+        let global_enabled = user_row.map(|row| row.get("global_enabled")).unwrap_or(true);
+
+        let counter_rows = sqlx::query(
+            "SELECT counter_type, name, emoji, zones, start_day, end_day, enabled, last_sent_month, last_sent_date, completed_this_month
+             FROM counter_reminders WHERE chat_id = ?1"
+        )
+        .bind(chat_id.0)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default();
+
+        let mut reminders = HashMap::new();
+        for row in counter_rows {
+            let counter_type = CounterType {
+                id: row.get("counter_type"),
+                name: row.get("name"),
+                emoji: row.get("emoji"),
+                zones: row.get::<String, _>("zones").split(',').filter(|z| !z.is_empty()).map(str::to_string).collect(),
+            };
+
+            let reminder = CounterReminder {
+                counter_type,
+                start_day: row.get::<i64, _>("start_day") as u32,
+                end_day: row.get::<i64, _>("end_day") as u32,
+                enabled: row.get("enabled"),
+                last_sent_month: row.get("last_sent_month"),
+                last_sent_date: row.get("last_sent_date"),
+                completed_this_month: row.get("completed_this_month"),
+            };
+
+            reminders.insert(reminder.counter_type.id.clone(), reminder);
+        }
+
+        UserReminders {
+            reminders,
+            global_enabled,
+        }
+    }
+
+    async fn save_user_reminders(&self, chat_id: ChatId, reminders: UserReminders) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // Все изменения пользователя сохраняем одной транзакцией
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            "INSERT INTO user_reminders (chat_id, global_enabled) VALUES (?1, ?2)
+             ON CONFLICT (chat_id) DO UPDATE SET global_enabled = ?2"
+        )
+        .bind(chat_id.0)
+        .bind(reminders.global_enabled)
+        .execute(&mut *tx)
+        .await?;
+
+        for reminder in reminders.reminders.into_values() {
+            sqlx::query(
+                "INSERT INTO counter_reminders (chat_id, counter_type, name, emoji, start_day, end_day, enabled, last_sent_month, last_sent_date, completed_this_month, zones)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+                 ON CONFLICT (chat_id, counter_type) DO UPDATE SET
+                 name = ?3, emoji = ?4, start_day = ?5, end_day = ?6, enabled = ?7, last_sent_month = ?8, last_sent_date = ?9, completed_this_month = ?10, zones = ?11"
+            )
+            .bind(chat_id.0)
+            .bind(&reminder.counter_type.id)
+            .bind(&reminder.counter_type.name)
+            .bind(&reminder.counter_type.emoji)
+            .bind(reminder.start_day as i64)
+            .bind(reminder.end_day as i64)
+            .bind(reminder.enabled)
+            .bind(&reminder.last_sent_month)
+            .bind(&reminder.last_sent_date)
+            .bind(reminder.completed_this_month)
+            .bind(reminder.counter_type.zones.join(","))
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn add_counter_reminder(&self, chat_id: ChatId, reminder: CounterReminder) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut user_reminders = self.get_user_reminders(chat_id).await;
+        user_reminders.add_reminder(reminder);
+        self.save_user_reminders(chat_id, user_reminders).await
+    }
+
+    async fn delete_counter_reminder(&self, chat_id: ChatId, counter_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        sqlx::query("DELETE FROM counter_reminders WHERE chat_id = ?1 AND counter_type = ?2")
+            .bind(chat_id.0)
+            .bind(counter_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn toggle_global_reminders(&self, chat_id: ChatId) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut user_reminders = self.get_user_reminders(chat_id).await;
+        let new_state = user_reminders.toggle_global();
+        self.save_user_reminders(chat_id, user_reminders).await?;
+        Ok(new_state)
+    }
+
+    async fn mark_counter_completed(&self, chat_id: ChatId, counter_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        sqlx::query(
+            "UPDATE counter_reminders SET completed_this_month = TRUE WHERE chat_id = ?1 AND counter_type = ?2"
+        )
+        .bind(chat_id.0)
+        .bind(counter_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_all_reminders(&self) -> HashMap<String, UserReminders> {
+        let mut result = HashMap::new();
+
+        // Получаем всех пользователей с напоминаниями
+        let chat_ids: Vec<i64> = sqlx::query("SELECT DISTINCT chat_id FROM user_reminders")
+            .fetch_all(&self.pool)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|row| row.get("chat_id"))
+            .collect();
+
+        for chat_id in chat_ids {
+            let reminders = self.get_user_reminders(ChatId(chat_id)).await;
+            result.insert(chat_id.to_string(), reminders);
+        }
+
+        result
+    }
+
+    async fn reset_monthly_statuses(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let moscow_now = chrono_tz::Europe::Moscow.from_utc_datetime(&chrono::Utc::now().naive_utc());
+        let current_month = moscow_now.format("%Y-%m").to_string();
+
+        sqlx::query(
+            "UPDATE counter_reminders SET completed_this_month = FALSE
+             WHERE last_sent_month IS NULL OR last_sent_month != ?1"
+        )
+        .bind(&current_month)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // Показания счетчиков
+    async fn add_meter_readings(&self, chat_id: ChatId, readings: Vec<MeterReading>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // Показания одной отправки сохраняются вместе или не сохраняются вовсе
+        let mut tx = self.pool.begin().await?;
+        for reading in readings {
+            sqlx::query(
+                "INSERT INTO meter_readings (id, chat_id, counter_id, zone, value, taken_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)"
+            )
+            .bind(&reading.id)
+            .bind(chat_id.0)
+            .bind(&reading.counter_id)
+            .bind(&reading.zone)
+            .bind(reading.value)
+            .bind(reading.taken_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_meter_readings(&self, chat_id: ChatId, counter_id: &str) -> Vec<MeterReading> {
+        sqlx::query(
+            "SELECT id, counter_id, zone, value, taken_at FROM meter_readings
+             WHERE chat_id = ?1 AND counter_id = ?2 ORDER BY taken_at, zone"
+        )
+        .bind(chat_id.0)
+        .bind(counter_id)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+        .iter()
+        .map(meter_reading_from_row)
+        .collect()
+    }
+
+    // Тарифы
+    async fn set_tariffs(&self, chat_id: ChatId, tariffs: Vec<Tariff>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut tx = self.pool.begin().await?;
+        for tariff in tariffs {
+            sqlx::query(
+                "INSERT INTO tariffs (chat_id, counter_id, zone, effective_from, price) VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT (chat_id, counter_id, zone, effective_from) DO UPDATE SET price = excluded.price"
+            )
+            .bind(chat_id.0)
+            .bind(&tariff.counter_id)
+            .bind(&tariff.zone)
+            .bind(tariff.effective_from)
+            .bind(tariff.price)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_tariffs(&self, chat_id: ChatId, counter_id: &str) -> Vec<Tariff> {
+        sqlx::query(
+            "SELECT counter_id, zone, price, effective_from FROM tariffs
+             WHERE chat_id = ?1 AND counter_id = ?2 ORDER BY effective_from, zone"
+        )
+        .bind(chat_id.0)
+        .bind(counter_id)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default()
+        .iter()
+        .map(tariff_from_row)
+        .collect()
+    }
+
+    // Методы для напоминаний о задачах
+    async fn add_task_reminder(&self, reminder: TaskReminder) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        sqlx::query(
+            "INSERT INTO task_reminders (id, chat_id, task_id, remind_at, attempts) VALUES (?1, ?2, ?3, ?4, ?5)"
+        )
+        .bind(&reminder.id)
+        .bind(reminder.chat_id)
+        .bind(&reminder.task_id)
+        .bind(reminder.remind_at)
+        .bind(reminder.attempts)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn take_due_task_reminders(&self, now: DateTime<Utc>) -> Result<Vec<TaskReminder>, Box<dyn std::error::Error + Send + Sync>> {
+        // Удаление с RETURNING атомарно «забирает» напоминания, даже если их ищут параллельно
+        let rows = sqlx::query(
+            "DELETE FROM task_reminders WHERE remind_at <= ?1 RETURNING id, chat_id, task_id, remind_at, attempts"
+        )
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(task_reminder_from_row).collect())
+    }
+
+    async fn remove_task_reminders(&self, chat_id: ChatId, task_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        sqlx::query("DELETE FROM task_reminders WHERE chat_id = ?1 AND task_id = ?2")
+            .bind(chat_id.0)
+            .bind(task_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_user_state(&self, chat_id: ChatId) -> Option<SavedUserState> {
+        let row = sqlx::query("SELECT state, updated_at FROM dialogue_states WHERE chat_id = ?1")
+            .bind(chat_id.0)
+            .fetch_optional(&self.pool)
+            .await
+            .unwrap_or(None)?;
+
+        saved_user_state(row.get("state"), row.get("updated_at"))
+    }
+
+    async fn save_user_state(&self, chat_id: ChatId, state: UserState) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if state == UserState::Default {
+            sqlx::query("DELETE FROM dialogue_states WHERE chat_id = ?1")
+                .bind(chat_id.0)
+                .execute(&self.pool)
+                .await?;
+            return Ok(());
+        }
+
+        sqlx::query(
+            "INSERT INTO dialogue_states (chat_id, state, updated_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT (chat_id) DO UPDATE SET state = excluded.state, updated_at = excluded.updated_at"
+        )
+        .bind(chat_id.0)
+        .bind(serde_json::to_string(&state)?)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn purge_user_states(&self, updated_before: DateTime<Utc>) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let result = sqlx::query("DELETE FROM dialogue_states WHERE updated_at <= ?1")
+            .bind(updated_before)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tasks_roundtrip() {
+        let storage = SqliteStorage::in_memory().await.unwrap();
+        let chat_id = ChatId(1);
+
+        let tags = vec!["дом".to_string(), "покупки".to_string()];
+        storage.add_task(chat_id, TodoItem::new("Купить молоко".to_string()).with_tags(tags)).await.unwrap();
+        storage.add_task(chat_id, TodoItem::new("Позвонить маме".to_string())).await.unwrap();
+
+        let tasks = storage.get_tasks(chat_id).await;
+        assert_eq!(storage.mark_task_completed(chat_id, &tasks[1].id).await, Ok("Позвонить маме".to_string()));
+        assert!(storage.get_tasks(chat_id).await[1].is_completed());
+        assert!(storage.mark_task_completed(ChatId(2), &tasks[0].id).await.is_err());
+
+        assert_eq!(storage.edit_task(chat_id, &tasks[1].id, &TaskEdit::text("Позвонить папе".to_string())).await, Ok("Позвонить маме".to_string()));
+        assert_eq!(storage.get_tasks(chat_id).await[1].text, "Позвонить папе");
+        assert!(storage.edit_task(ChatId(2), &tasks[1].id, &TaskEdit::text("Чужая".to_string())).await.is_err());
+
+        storage.set_task_priority(chat_id, &tasks[0].id, Priority::Urgent).await.unwrap();
+        assert_eq!(storage.get_tasks(chat_id).await[0].priority, Priority::Urgent);
+        storage.set_task_recurrence(chat_id, &tasks[0].id, Some(Recurrence::Monthly(5))).await.unwrap();
+        assert_eq!(storage.mark_task_completed(chat_id, &tasks[0].id).await, Ok("Купить молоко".to_string()));
+        let tasks_after = storage.get_tasks(chat_id).await;
+        assert_eq!(tasks_after.len(), 3);
+        assert_eq!(tasks_after[2].recurrence, Some(Recurrence::Monthly(5)));
+        assert_eq!(tasks_after[2].priority, Priority::Urgent);
+        assert_eq!(tasks_after[2].tags, vec!["дом".to_string(), "покупки".to_string()]);
+        assert_eq!(storage.get_tags(chat_id).await, vec!["дом".to_string(), "покупки".to_string()]);
+        assert!(!tasks_after[2].is_completed());
+
+        assert_eq!(storage.remove_task(chat_id, &tasks[0].id).await, Ok("Купить молоко".to_string()));
+        storage.clear_tasks(chat_id).await.unwrap();
+        assert!(storage.get_tasks(chat_id).await.is_empty());
+        assert!(storage.get_tags(chat_id).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_subtasks_roundtrip() {
+        let storage = SqliteStorage::in_memory().await.unwrap();
+        let chat_id = ChatId(1);
+
+        let parent = TodoItem::new("Магазин".to_string());
+        let parent_id = parent.id.clone();
+        storage.add_task(chat_id, parent).await.unwrap();
+        for text in ["Молоко", "Хлеб"] {
+            let child = TodoItem::new(text.to_string()).with_parent(Some(parent_id.clone()));
+            storage.add_task(chat_id, child).await.unwrap();
+        }
+
+        let tasks = storage.get_tasks(chat_id).await;
+        storage.mark_task_completed(chat_id, &tasks[1].id).await.unwrap();
+        assert!(!storage.get_tasks(chat_id).await[0].is_completed());
+        storage.mark_task_completed(chat_id, &tasks[2].id).await.unwrap();
+        assert!(storage.get_tasks(chat_id).await[0].is_completed());
+
+        // Подзадачи удаляются вместе с родителем
+        let removed: Vec<TodoItem> = storage.get_tasks(chat_id).await.into_iter().rev().collect();
+        storage.remove_task(chat_id, &parent_id).await.unwrap();
+        assert!(storage.get_tasks(chat_id).await.is_empty());
+
+        // Отмена удаления возвращает их с прежними id и порядком; родитель вставляется раньше подзадач,
+        // повторное восстановление ничего не дублирует
+        storage.restore_tasks(chat_id, removed.clone()).await.unwrap();
+        storage.restore_tasks(chat_id, removed).await.unwrap();
+        let restored = storage.get_tasks(chat_id).await;
+        let ids = |todos: &[TodoItem]| todos.iter().map(|t| t.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&restored), ids(&tasks));
+        assert!(restored[0].is_completed());
+    }
+
+    #[tokio::test]
+    async fn test_task_order_roundtrip() {
+        let storage = SqliteStorage::in_memory().await.unwrap();
+        let chat_id = ChatId(1);
+
+        for text in ["a", "b", "c"] {
+            storage.add_task(chat_id, TodoItem::new(text.to_string())).await.unwrap();
+        }
+        let ids: Vec<String> = storage.get_tasks(chat_id).await.into_iter().map(|t| t.id).collect();
+
+        storage.reorder_tasks(chat_id, &[ids[2].clone(), ids[0].clone(), ids[1].clone()]).await.unwrap();
+        storage.add_task(chat_id, TodoItem::new("d".to_string())).await.unwrap();
+        let texts: Vec<String> = storage.get_tasks(chat_id).await.into_iter().map(|t| t.text).collect();
+        assert_eq!(texts, vec!["c", "a", "b", "d"]);
+        assert!(storage.reorder_tasks(ChatId(2), &ids).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_archive_and_trash_roundtrip() {
+        let storage = SqliteStorage::in_memory().await.unwrap();
+        let chat_id = ChatId(1);
+
+        let parent = TodoItem::new("Магазин".to_string());
+        let parent_id = parent.id.clone();
+        storage.add_task(chat_id, parent).await.unwrap();
+        storage.add_task(chat_id, TodoItem::new("Молоко".to_string()).with_parent(Some(parent_id.clone()))).await.unwrap();
+        storage.add_task(chat_id, TodoItem::new("Позвонить".to_string())).await.unwrap();
+
+        // Выполненная задача уходит в архив вместе с подзадачей и возвращается невыполненной
+        storage.mark_task_completed(chat_id, &parent_id).await.unwrap();
+        assert_eq!(storage.archive_completed_tasks(Utc::now()).await.unwrap(), 1);
+        assert_eq!(storage.get_tasks(chat_id).await.len(), 1);
+        assert_eq!(storage.get_archived_tasks(chat_id).await.len(), 2);
+        assert!(storage.mark_task_completed(chat_id, &parent_id).await.is_err());
+        assert_eq!(storage.restore_task(chat_id, &parent_id).await, Ok("Магазин".to_string()));
+        let tasks = storage.get_tasks(chat_id).await;
+        assert_eq!(tasks.len(), 3);
+        assert!(tasks.iter().all(|t| !t.is_completed()));
+
+        // Удаленные задачи лежат в корзине, пока её не очистят
+        storage.remove_task(chat_id, &parent_id).await.unwrap();
+        assert!(storage.remove_task(chat_id, &parent_id).await.is_err());
+        assert_eq!(storage.get_trashed_tasks(chat_id).await.len(), 2);
+        assert_eq!(storage.purge_trash(Utc::now() - chrono::Duration::days(1)).await.unwrap(), 0);
+        assert_eq!(storage.restore_task(chat_id, &parent_id).await, Ok("Магазин".to_string()));
+        assert_eq!(storage.get_tasks(chat_id).await.len(), 3);
+
+        storage.clear_tasks(chat_id).await.unwrap();
+        assert!(storage.get_tasks(chat_id).await.is_empty());
+        assert_eq!(storage.empty_trash(chat_id).await, Ok(3));
+        assert!(storage.get_trashed_tasks(chat_id).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_edit_task_with_tags() {
+        let storage = SqliteStorage::in_memory().await.unwrap();
+        let chat_id = ChatId(1);
+        let due_at = Utc::now();
+
+        storage.add_task(chat_id, TodoItem::new("Отчет".to_string()).with_tags(vec!["работа".to_string()]).with_due(Some(due_at))).await.unwrap();
+        let task_id = storage.get_tasks(chat_id).await[0].id.clone();
+
+        let edit = TaskEdit {
+            text: "Квартальный отчет".to_string(),
+            priority: Some(Priority::High),
+            tags: Some(vec!["q3".to_string(), "срочно".to_string()]),
+            due_at: None,
+        };
+        assert_eq!(storage.edit_task(chat_id, &task_id, &edit).await, Ok("Отчет".to_string()));
+
+        let task = &storage.get_tasks(chat_id).await[0];
+        assert_eq!(task.text, "Квартальный отчет");
+        assert_eq!(task.tags, vec!["q3".to_string(), "срочно".to_string()]);
+        assert_eq!(task.priority, Priority::High);
+        // Срок не указан в новом тексте и сохраняется
+        assert_eq!(task.due_at.map(|d| d.timestamp()), Some(due_at.timestamp()));
+        assert_eq!(storage.get_tags(chat_id).await, vec!["q3".to_string(), "срочно".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_new_creates_missing_directory() {
+        let dir = std::env::temp_dir().join(format!("todo_bot_{}", uuid::Uuid::new_v4()));
+        let path = dir.join("data").join("todos.db");
+
+        let storage = SqliteStorage::new(path.to_str().unwrap()).await.unwrap();
+        storage.add_task(ChatId(1), TodoItem::new("Купить молоко".to_string())).await.unwrap();
+        assert!(path.exists());
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_task_lists_roundtrip() {
+        let storage = SqliteStorage::in_memory().await.unwrap();
+        let chat_id = ChatId(1);
+
+        let work = storage.create_list(chat_id, "Работа").await.unwrap();
+        assert!(storage.create_list(chat_id, "работа").await.is_err());
+        assert!(storage.set_current_list(ChatId(2), Some(&work.id)).await.is_err());
+
+        storage.add_task(chat_id, TodoItem::new("Купить молоко".to_string())).await.unwrap();
+        storage.set_current_list(chat_id, Some(&work.id)).await.unwrap();
+        storage.add_task(chat_id, TodoItem::new("Отчет".to_string()).with_tags(vec!["q3".to_string()]).with_list(Some(work.id.clone()))).await.unwrap();
+        assert_eq!(storage.get_current_tasks(chat_id).await.len(), 1);
+        assert_eq!(storage.get_tags(chat_id).await, vec!["q3".to_string()]);
+
+        // Перенос в основной список и обратно
+        let milk_id = storage.get_tasks(chat_id).await[0].id.clone();
+        storage.move_task_to_list(chat_id, &milk_id, Some(&work.id)).await.unwrap();
+        assert_eq!(storage.get_current_tasks(chat_id).await.len(), 2);
+        assert!(storage.move_task_to_list(chat_id, &milk_id, Some("missing")).await.is_err());
+
+        assert_eq!(storage.rename_list(chat_id, &work.id, "Офис").await, Ok("Работа".to_string()));
+        assert_eq!(storage.get_current_list(chat_id).await.unwrap().name, "Офис");
+
+        // Удаление списка: задачи уходят в основной, текущим снова становится основной
+        assert_eq!(storage.delete_list(chat_id, &work.id).await, Ok("Офис".to_string()));
+        assert!(storage.get_current_list(chat_id).await.is_none());
+        assert!(storage.get_lists(chat_id).await.is_empty());
+        assert_eq!(storage.get_current_tasks(chat_id).await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_reminders_roundtrip() {
+        let storage = SqliteStorage::in_memory().await.unwrap();
+        let chat_id = ChatId(1);
+
+        let heating = CounterType::new("Отопление".to_string(), "🌡".to_string());
+        storage.add_counter_reminder(chat_id, CounterReminder::new(heating.clone(), 20, 25)).await.unwrap();
+        storage.mark_counter_completed(chat_id, &heating.id).await.unwrap();
+
+        let reminders = storage.get_user_reminders(chat_id).await;
+        assert!(reminders.global_enabled);
+        assert_eq!(reminders.reminders[&heating.id].counter_type, heating);
+        assert_eq!(reminders.reminders[&heating.id].start_day, 20);
+        assert!(reminders.reminders[&heating.id].completed_this_month);
+
+        assert!(!storage.toggle_global_reminders(chat_id).await.unwrap());
+        assert_eq!(storage.get_all_reminders().await.len(), 1);
+
+        storage.delete_counter_reminder(chat_id, &heating.id).await.unwrap();
+        assert!(storage.get_user_reminders(chat_id).await.reminders.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_meter_readings_roundtrip() {
+        let storage = SqliteStorage::in_memory().await.unwrap();
+        let chat_id = ChatId(1);
+
+        let mut power = CounterType::new("Свет".to_string(), "⚡".to_string());
+        power.zones = vec!["день".to_string(), "ночь".to_string()];
+        storage.add_counter_reminder(chat_id, CounterReminder::new(power.clone(), 20, 25)).await.unwrap();
+        assert_eq!(storage.get_user_reminders(chat_id).await.reminders[&power.id].counter_type.zones, power.zones);
+
+        let earlier = Utc::now() - chrono::Duration::days(30);
+        storage.add_meter_readings(chat_id, vec![
+            MeterReading::new(power.id.clone(), "день".to_string(), 1200.0, Utc::now()),
+            MeterReading::new(power.id.clone(), "ночь".to_string(), 500.5, Utc::now()),
+        ]).await.unwrap();
+        storage.add_meter_readings(chat_id, vec![MeterReading::new(power.id.clone(), "день".to_string(), 1000.0, earlier)]).await.unwrap();
+
+        let readings = storage.get_meter_readings(chat_id, &power.id).await;
+        assert_eq!(readings.iter().map(|r| r.value).collect::<Vec<_>>(), vec![1000.0, 1200.0, 500.5]);
+        assert!(storage.get_meter_readings(ChatId(2), &power.id).await.is_empty());
+
+        let july = chrono::NaiveDate::from_ymd_opt(2025, 7, 1).unwrap();
+        let january = chrono::NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        storage.set_tariffs(chat_id, vec![Tariff::new(power.id.clone(), "день".to_string(), 7.0, july)]).await.unwrap();
+        storage.set_tariffs(chat_id, vec![Tariff::new(power.id.clone(), "день".to_string(), 6.0, january)]).await.unwrap();
+        // Тариф с той же датой начала заменяет прежний
+        storage.set_tariffs(chat_id, vec![Tariff::new(power.id.clone(), "день".to_string(), 7.5, july)]).await.unwrap();
+        let tariffs = storage.get_tariffs(chat_id, &power.id).await;
+        assert_eq!(tariffs.iter().map(|t| (t.effective_from, t.price)).collect::<Vec<_>>(), vec![(january, 6.0), (july, 7.5)]);
+
+        // Показания и тарифы удаляются вместе со счетчиком
+        storage.delete_counter_reminder(chat_id, &power.id).await.unwrap();
+        assert!(storage.get_meter_readings(chat_id, &power.id).await.is_empty());
+        assert!(storage.get_tariffs(chat_id, &power.id).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_take_due_task_reminders() {
+        let storage = SqliteStorage::in_memory().await.unwrap();
+        let now = Utc::now();
+
+        storage.add_task_reminder(TaskReminder::new(1, "a".to_string(), now - chrono::Duration::seconds(1))).await.unwrap();
+        storage.add_task_reminder(TaskReminder::new(1, "b".to_string(), now + chrono::Duration::minutes(1))).await.unwrap();
+
+        let due = storage.take_due_task_reminders(now).await.unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].task_id, "a");
+        assert!(storage.take_due_task_reminders(now).await.unwrap().is_empty());
+
+        // Повтор после неудачной отправки сохраняет число попыток
+        let retry = due[0].clone().retry(now - chrono::Duration::minutes(1)).unwrap();
+        storage.add_task_reminder(retry).await.unwrap();
+        let due = storage.take_due_task_reminders(now).await.unwrap();
+        assert_eq!(due[0].attempts, 1);
+
+        storage.remove_task_reminders(ChatId(1), "b").await.unwrap();
+        assert!(storage.take_due_task_reminders(now + chrono::Duration::hours(1)).await.unwrap().is_empty());
+    }
+    #[tokio::test]
+    async fn test_user_state_roundtrip() {
+        let storage = SqliteStorage::in_memory().await.unwrap();
+        let chat_id = ChatId(1);
+
+        storage.save_user_state(chat_id, UserState::WaitingForNewTaskText("abc".to_string())).await.unwrap();
+        let saved = storage.get_user_state(chat_id).await.unwrap();
+        assert_eq!(saved.state, UserState::WaitingForNewTaskText("abc".to_string()));
+
+        storage.save_user_state(ChatId(2), UserState::WaitingForTask).await.unwrap();
+        storage.save_user_state(ChatId(2), UserState::Default).await.unwrap();
+        assert!(storage.get_user_state(ChatId(2)).await.is_none());
+
+        assert_eq!(storage.purge_user_states(saved.updated_at - chrono::Duration::seconds(1)).await.unwrap(), 0);
+        assert_eq!(storage.purge_user_states(Utc::now()).await.unwrap(), 1);
+        assert!(storage.get_user_state(chat_id).await.is_none());
+    }
+}
@@ -0,0 +1,380 @@
+use chrono::{DateTime, Utc};
+
+use crate::models::{TaskList, TodoItem};
+
+/// Изменения после отметки задачи выполненной
+pub(crate) struct Completion {
+    /// Текст задачи, которую отметил пользователь
+    pub text: String,
+    /// Задачи, ставшие выполненными: сама задача, её подзадачи и, возможно, родитель
+    pub completed: Vec<String>,
+    /// Новые задачи: следующие повторения (вместе с копиями подзадач)
+    pub spawned: Vec<TodoItem>,
+}
+
+/// Отмечает задачу выполненной вместе с её подзадачами.
+/// Родитель считается выполненным, когда выполнены все его подзадачи.
+/// Для повторяющейся задачи добавляется следующее повторение; изменения применяются к `todos`.
+pub(crate) fn complete_task(todos: &mut Vec<TodoItem>, task_id: &str, now: DateTime<Utc>) -> Result<Completion, String> {
+    let task = todos
+        .iter()
+        .find(|t| t.id == task_id && t.is_active())
+        .ok_or_else(|| "Задача не найдена".to_string())?;
+    let mut completion = Completion {
+        text: task.text.clone(),
+        completed: Vec::new(),
+        spawned: Vec::new(),
+    };
+    let parent_id = task.parent_id.clone();
+
+    complete_with_children(todos, task_id, now, &mut completion);
+
+    if let Some(parent_id) = parent_id {
+        let all_children_done = todos
+            .iter()
+            .filter(|t| t.parent_id.as_deref() == Some(parent_id.as_str()) && t.is_active())
+            .all(|t| t.is_completed());
+        if all_children_done {
+            complete_with_children(todos, &parent_id, now, &mut completion);
+        }
+    }
+
+    let next = next_position(todos);
+    for (offset, todo) in completion.spawned.iter_mut().enumerate() {
+        todo.position = next + offset as i64;
+    }
+    todos.extend(completion.spawned.iter().cloned());
+    Ok(completion)
+}
+
+/// Позиция для новой задачи: в конце списка чата
+pub(crate) fn next_position(todos: &[TodoItem]) -> i64 {
+    todos.iter().map(|t| t.position).max().unwrap_or(0) + 1
+}
+
+/// Ставит задачи `task_ids` в заданном порядке на занимаемые ими места
+/// (остальные задачи не сдвигаются) и перенумеровывает позиции всех задач чата.
+/// `todos` должны идти в текущем порядке.
+pub(crate) fn reorder_tasks(todos: &mut [TodoItem], task_ids: &[String]) -> Result<(), String> {
+    let mut slots = Vec::with_capacity(task_ids.len());
+    let mut moved = Vec::with_capacity(task_ids.len());
+    for task_id in task_ids {
+        let slot = todos.iter().position(|t| &t.id == task_id).ok_or_else(|| "Задача не найдена".to_string())?;
+        slots.push(slot);
+        moved.push(todos[slot].clone());
+    }
+    slots.sort_unstable();
+    slots.dedup();
+    if slots.len() != task_ids.len() {
+        return Err("Задача указана дважды".to_string());
+    }
+
+    for (slot, todo) in slots.into_iter().zip(moved) {
+        todos[slot] = todo;
+    }
+    for (index, todo) in todos.iter_mut().enumerate() {
+        todo.position = index as i64 + 1;
+    }
+    Ok(())
+}
+
+fn complete_with_children(todos: &mut [TodoItem], task_id: &str, now: DateTime<Utc>, completion: &mut Completion) {
+    for todo in todos.iter_mut() {
+        let is_target = todo.id == task_id || todo.parent_id.as_deref() == Some(task_id);
+        if is_target && todo.is_active() && !todo.is_completed() {
+            todo.mark_completed(now);
+            completion.completed.push(todo.id.clone());
+        }
+    }
+
+    let Some(task) = todos.iter().find(|t| t.id == task_id) else { return };
+    // Повторение создается только при первом выполнении задачи
+    if !completion.completed.iter().any(|id| id == task_id) {
+        return;
+    }
+    if let Some(next) = task.next_occurrence(now) {
+        // Чек-лист повторяется вместе с пунктами
+        let children = todos
+            .iter()
+            .filter(|t| t.parent_id.as_deref() == Some(task_id) && t.is_active())
+            .map(|child| {
+                TodoItem::new(child.text.clone())
+                    .with_priority(child.priority)
+                    .with_tags(child.tags.clone())
+                    .with_parent(Some(next.id.clone()))
+                    .with_list(next.list_id.clone())
+            });
+        completion.spawned.push(next.clone());
+        completion.spawned.extend(children);
+    }
+}
+
+/// Добавляет список, если такого названия у чата еще нет
+pub(crate) fn create_list(lists: &mut Vec<TaskList>, name: &str) -> Result<TaskList, String> {
+    if lists.iter().any(|l| l.has_name(name)) {
+        return Err("Список с таким названием уже есть".to_string());
+    }
+    let list = TaskList::new(name.to_string());
+    lists.push(list.clone());
+    Ok(list)
+}
+
+/// Переименовывает список, возвращает прежнее название
+pub(crate) fn rename_list(lists: &mut [TaskList], list_id: &str, name: &str) -> Result<String, String> {
+    if lists.iter().any(|l| l.id != list_id && l.has_name(name)) {
+        return Err("Список с таким названием уже есть".to_string());
+    }
+    let list = lists.iter_mut().find(|l| l.id == list_id).ok_or_else(|| "Список не найден".to_string())?;
+    Ok(std::mem::replace(&mut list.name, name.to_string()))
+}
+
+/// Переносит задачу вместе с подзадачами; подзадачу отдельно от родителя перенести нельзя
+pub(crate) fn move_task(todos: &mut [TodoItem], task_id: &str, list_id: Option<&str>) -> Result<String, String> {
+    let task = todos
+        .iter()
+        .find(|t| t.id == task_id && t.is_active())
+        .ok_or_else(|| "Задача не найдена".to_string())?;
+    if task.parent_id.is_some() {
+        return Err("Подзадача переносится вместе с родительской задачей".to_string());
+    }
+    let text = task.text.clone();
+    for todo in todos.iter_mut() {
+        if todo.id == task_id || todo.parent_id.as_deref() == Some(task_id) {
+            todo.list_id = list_id.map(str::to_string);
+        }
+    }
+    Ok(text)
+}
+
+/// Возвращает удаленные задачи на прежние позиции; задачи, которые еще лежат в корзине,
+/// заменяются снимком, а не дублируются. Порядок остальных задач сохраняется.
+pub(crate) fn restore_tasks(todos: &mut Vec<TodoItem>, restored: Vec<TodoItem>) {
+    for task in restored {
+        match todos.iter_mut().find(|t| t.id == task.id) {
+            Some(existing) => *existing = task,
+            None => todos.push(task),
+        }
+    }
+    todos.sort_by_key(|t| t.position);
+}
+
+/// Переносит задачу вместе с подзадачами в корзину, возвращает её текст
+pub(crate) fn trash_task(todos: &mut [TodoItem], task_id: &str, now: DateTime<Utc>) -> Result<String, String> {
+    let task = todos
+        .iter_mut()
+        .find(|t| t.id == task_id && t.is_active())
+        .ok_or_else(|| "Задача не найдена".to_string())?;
+    task.deleted_at = Some(now);
+    let text = task.text.clone();
+
+    for child in todos.iter_mut().filter(|t| t.parent_id.as_deref() == Some(task_id) && t.is_active()) {
+        child.deleted_at = Some(now);
+    }
+    Ok(text)
+}
+
+/// Переносит в архив задачи, выполненные не позже `completed_before`, вместе с подзадачами.
+/// Подзадачи отдельно от родителя в архив не уходят. У задач, выполненных до появления архива,
+/// время выполнения неизвестно — для них берется время создания. Возвращает число задач верхнего уровня.
+pub(crate) fn archive_completed(todos: &mut [TodoItem], completed_before: DateTime<Utc>, now: DateTime<Utc>) -> usize {
+    let archived: Vec<String> = todos
+        .iter()
+        .filter(|t| t.parent_id.is_none() && t.is_active() && t.is_completed())
+        .filter(|t| t.completed_at.unwrap_or(t.created_at) <= completed_before)
+        .map(|t| t.id.clone())
+        .collect();
+
+    for todo in todos.iter_mut().filter(|t| t.is_active()) {
+        let root = todo.parent_id.as_ref().unwrap_or(&todo.id);
+        if archived.contains(root) {
+            todo.archived_at = Some(now);
+        }
+    }
+    archived.len()
+}
+
+/// Возвращает задачу из архива или корзины вместе с подзадачами, убранными одновременно с ней.
+/// Задача из архива возвращается в работу. Возвращает текст задачи.
+pub(crate) fn restore_task(todos: &mut [TodoItem], task_id: &str) -> Result<String, String> {
+    let task = todos
+        .iter()
+        .find(|t| t.id == task_id && !t.is_active())
+        .ok_or_else(|| "Задача не найдена в архиве и корзине".to_string())?;
+    if let Some(parent_id) = &task.parent_id {
+        if !todos.iter().any(|t| &t.id == parent_id && t.is_active()) {
+            return Err("Сначала восстановите родительскую задачу".to_string());
+        }
+    }
+    let text = task.text.clone();
+    let stamp = (task.archived_at, task.deleted_at);
+
+    for todo in todos.iter_mut() {
+        let is_target = todo.id == task_id || todo.parent_id.as_deref() == Some(task_id);
+        if is_target && (todo.archived_at, todo.deleted_at) == stamp {
+            if todo.archived_at.is_some() {
+                todo.completed = false;
+                todo.completed_at = None;
+            }
+            todo.archived_at = None;
+            todo.deleted_at = None;
+        }
+    }
+    Ok(text)
+}
+
+/// Окончательно удаляет задачи, лежащие в корзине с `deleted_before` или раньше,
+/// а также подзадачи удаленных родителей. Возвращает число удаленных задач.
+pub(crate) fn purge_trash(todos: &mut Vec<TodoItem>, deleted_before: DateTime<Utc>) -> usize {
+    let before = todos.len();
+    todos.retain(|t| t.deleted_at.is_none_or(|at| at > deleted_before));
+    let ids: Vec<String> = todos.iter().map(|t| t.id.clone()).collect();
+    todos.retain(|t| t.parent_id.as_ref().is_none_or(|parent_id| ids.contains(parent_id)));
+    before - todos.len()
+}
+
+/// Слова поискового запроса в нижнем регистре; `#` перед тегом отбрасывается
+pub(crate) fn query_words(query: &str) -> Vec<String> {
+    query
+        .split_whitespace()
+        .map(|word| word.trim_start_matches('#').to_lowercase())
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Каждое слово запроса встречается в тексте или тегах задачи (как подстрока, без учета регистра)
+pub(crate) fn matches_query(todo: &TodoItem, words: &[String]) -> bool {
+    let haystack = format!("{} {}", todo.text, todo.tags.join(" ")).to_lowercase();
+    !words.is_empty() && words.iter().all(|word| haystack.contains(word.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use crate::models::Recurrence;
+
+    #[test]
+    fn test_parent_completion_is_derived_from_children() {
+        let parent = TodoItem::new("Магазин".to_string());
+        let milk = TodoItem::new("Молоко".to_string()).with_parent(Some(parent.id.clone()));
+        let bread = TodoItem::new("Хлеб".to_string()).with_parent(Some(parent.id.clone()));
+        let (parent_id, milk_id, bread_id) = (parent.id.clone(), milk.id.clone(), bread.id.clone());
+        let mut todos = vec![parent, milk, bread];
+
+        let completion = complete_task(&mut todos, &milk_id, Utc::now()).unwrap();
+        assert_eq!(completion.completed, vec![milk_id.clone()]);
+        assert!(!todos[0].is_completed());
+
+        let completion = complete_task(&mut todos, &bread_id, Utc::now()).unwrap();
+        assert_eq!(completion.completed, vec![bread_id, parent_id]);
+        assert!(todos[0].is_completed());
+        assert!(complete_task(&mut todos, "missing", Utc::now()).is_err());
+    }
+
+    #[test]
+    fn test_recurring_checklist_is_copied() {
+        let mut parent = TodoItem::new("Уборка".to_string());
+        parent.recurrence = Some(Recurrence::Daily);
+        let child = TodoItem::new("Пропылесосить".to_string()).with_parent(Some(parent.id.clone()));
+        let parent_id = parent.id.clone();
+        let mut todos = vec![parent, child];
+
+        let completion = complete_task(&mut todos, &parent_id, Utc::now()).unwrap();
+        assert_eq!(completion.completed.len(), 2);
+        assert_eq!(completion.spawned.len(), 2);
+        assert_eq!(todos.len(), 4);
+        assert_eq!(todos[3].parent_id.as_deref(), Some(todos[2].id.as_str()));
+        assert!(!todos[2].is_completed() && !todos[3].is_completed());
+
+        // Повторная отметка ничего не меняет
+        let completion = complete_task(&mut todos, &parent_id, Utc::now()).unwrap();
+        assert!(completion.completed.is_empty() && completion.spawned.is_empty());
+    }
+
+    #[test]
+    fn test_lists_and_moving_tasks() {
+        let mut lists = Vec::new();
+        let work = create_list(&mut lists, "Работа").unwrap();
+        assert!(create_list(&mut lists, "работа").is_err());
+        let home = create_list(&mut lists, "Дом").unwrap();
+        assert!(rename_list(&mut lists, &home.id, "Работа").is_err());
+        assert_eq!(rename_list(&mut lists, &home.id, "Дача"), Ok("Дом".to_string()));
+
+        let parent = TodoItem::new("Отчет".to_string());
+        let child = TodoItem::new("Графики".to_string()).with_parent(Some(parent.id.clone()));
+        let (parent_id, child_id) = (parent.id.clone(), child.id.clone());
+        let mut todos = vec![parent, child];
+
+        assert!(move_task(&mut todos, &child_id, Some(&work.id)).is_err());
+        assert_eq!(move_task(&mut todos, &parent_id, Some(&work.id)), Ok("Отчет".to_string()));
+        assert!(todos.iter().all(|t| t.list_id.as_deref() == Some(work.id.as_str())));
+    }
+
+    #[test]
+    fn test_reorder_keeps_other_tasks_in_place() {
+        let mut todos: Vec<TodoItem> = ["a", "b", "c", "d"].iter().map(|t| TodoItem::new(t.to_string())).collect();
+        let ids: Vec<String> = todos.iter().map(|t| t.id.clone()).collect();
+
+        // «d» переезжает на место «a», «a» и «c» сдвигаются; «b» остается на месте
+        reorder_tasks(&mut todos, &[ids[3].clone(), ids[0].clone(), ids[2].clone()]).unwrap();
+        let texts: Vec<&str> = todos.iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(texts, vec!["d", "b", "a", "c"]);
+        assert_eq!(todos.iter().map(|t| t.position).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        assert_eq!(next_position(&todos), 5);
+
+        assert!(reorder_tasks(&mut todos, &[ids[0].clone(), ids[0].clone()]).is_err());
+        assert!(reorder_tasks(&mut todos, &["missing".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_restore_returns_tasks_to_their_places() {
+        let mut todos: Vec<TodoItem> = ["a", "b", "c"].iter().map(|t| TodoItem::new(t.to_string())).collect();
+        for (i, todo) in todos.iter_mut().enumerate() {
+            todo.position = i as i64 + 1;
+        }
+        let removed = todos.remove(1);
+
+        restore_tasks(&mut todos, vec![removed.clone()]);
+        restore_tasks(&mut todos, vec![removed]);
+        let texts: Vec<&str> = todos.iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(texts, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_archive_trash_and_restore() {
+        let now = Utc::now();
+        let parent = TodoItem::new("Магазин".to_string());
+        let milk = TodoItem::new("Молоко".to_string()).with_parent(Some(parent.id.clone()));
+        let note = TodoItem::new("Заметка".to_string());
+        let (parent_id, milk_id, note_id) = (parent.id.clone(), milk.id.clone(), note.id.clone());
+        let mut todos = vec![parent, milk, note];
+
+        complete_task(&mut todos, &parent_id, now - Duration::days(10)).unwrap();
+        // Подзадача уходит в архив вместе с родителем, свежие и невыполненные задачи остаются
+        assert_eq!(archive_completed(&mut todos, now - Duration::days(7), now), 1);
+        assert_eq!(todos.iter().filter(|t| t.is_active()).count(), 1);
+        assert!(restore_task(&mut todos, &milk_id).is_err());
+        assert_eq!(restore_task(&mut todos, &parent_id), Ok("Магазин".to_string()));
+        assert!(todos.iter().all(|t| t.is_active() && !t.is_completed()));
+
+        assert_eq!(trash_task(&mut todos, &parent_id, now), Ok("Магазин".to_string()));
+        assert!(trash_task(&mut todos, &milk_id, now).is_err());
+        trash_task(&mut todos, &note_id, now - Duration::days(40)).unwrap();
+        // Из корзины удаляются только просроченные задачи
+        assert_eq!(purge_trash(&mut todos, now - Duration::days(30)), 1);
+        assert_eq!(todos.len(), 2);
+        assert_eq!(restore_task(&mut todos, &parent_id), Ok("Магазин".to_string()));
+        assert!(todos.iter().all(|t| t.is_active()));
+    }
+
+    #[test]
+    fn test_search_matches_words_in_text_and_tags() {
+        let todo = TodoItem::new("Купить Молоко".to_string()).with_tags(vec!["магазин".to_string()]);
+
+        assert!(matches_query(&todo, &query_words("молок")));
+        assert!(matches_query(&todo, &query_words("МОЛОКО купить")));
+        assert!(matches_query(&todo, &query_words("#магазин")));
+        assert!(!matches_query(&todo, &query_words("молоко хлеб")));
+        assert!(!matches_query(&todo, &query_words("  # ")));
+    }
+}
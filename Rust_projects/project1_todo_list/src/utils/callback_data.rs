@@ -0,0 +1,386 @@
+use chrono::NaiveDate;
+
+use crate::models::Priority;
+use super::formatter::TaskSort;
+
+/// Версия формата данных кнопок. Данные — `{версия}:{действие}[:{параметр}...]`;
+/// если формат поменяется, кнопки в старых сообщениях распознаются по версии.
+/// Параметр, добавленный к действию без смены версии, должен разбираться и в прежнем виде
+/// (как страница у `up`/`down`) — это проверяется в тестах.
+const CALLBACK_VERSION: &str = "1";
+/// Предел Telegram для данных кнопки, в байтах
+pub const CALLBACK_DATA_MAX_LEN: usize = 64;
+
+/// На сколько отложить напоминание о задаче
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Snooze {
+    Hour,
+    Day,
+}
+
+/// Действие кнопки. Кнопки создаются и разбираются только через этот тип,
+/// поэтому данные кнопок в `keyboard.rs` и обработчик в `callbacks.rs` не расходятся.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CallbackAction {
+    // Меню и справка
+    MainMenu,
+    TodoMenu,
+    Help,
+    TodoHelp,
+    /// Прервать начатый ввод
+    Cancel,
+
+    // Задачи
+    AddTask,
+    AddList,
+    MarkDone,
+    RemoveTask,
+    RemindTask,
+    RepeatTask,
+    EditTask,
+    SearchTasks,
+    OrderMenu,
+    PriorityMenu,
+    TagsMenu,
+    /// Страница текущего списка (с нуля), при `tag` — только задачи с тегом
+    ListTasks { sort: TaskSort, page: usize, tag: Option<String> },
+    TaskDone(String),
+    DeleteTask(String),
+    /// Ввести новый текст задачи
+    EditText(String),
+    /// Показать кнопки перемещения задачи
+    TaskOrder(String),
+    /// Переместить задачу; `page` — страница списка, на которой были кнопки
+    MoveTaskUp { task_id: String, page: usize },
+    MoveTaskDown { task_id: String, page: usize },
+    /// Показать выбор приоритета задачи
+    TaskPriority(String),
+    SetPriority { task_id: String, priority: Priority },
+    SnoozeTask { task_id: String, snooze: Snooze },
+
+    // Очистка, архив и корзина
+    ClearAll,
+    ClearConfirm,
+    Undo,
+    ArchiveMenu,
+    TrashMenu,
+    EmptyTrash,
+    EmptyTrashConfirm,
+    Restore(String),
+
+    // Списки задач
+    ListsMenu,
+    CreateList,
+    RenameList,
+    DeleteList,
+    /// Сделать текущим список (`None` — основной)
+    SwitchList(Option<String>),
+    /// Выбрать задачу для переноса в другой список
+    MoveTaskPicker,
+    /// Показать, в какой список перенести задачу
+    MoveTask(String),
+    MoveTaskTo { task_id: String, list_id: Option<String> },
+
+    // Напоминания о счетчиках
+    ReminderMenu,
+    ReminderHelp,
+    CountersMenu,
+    ToggleReminders,
+    AddCounter,
+    /// Карточка счетчика с этим id
+    Counter(String),
+    /// Настроить период напоминаний счетчика
+    CounterPeriod(String),
+    RenameCounter(String),
+    /// Настроить тарифные зоны счетчика
+    CounterZones(String),
+    DeleteCounter(String),
+    /// Ввести показания счетчика
+    EnterReading(String),
+    /// Задать тариф счетчика
+    CounterTariff(String),
+    /// Отчет о расходах за месяц (первое число месяца)
+    ConsumptionReport(NaiveDate),
+    /// Ответ на напоминание: показания отправлены или еще нет
+    CounterSent { counter_id: String, sent: bool },
+}
+
+impl CallbackAction {
+    /// Данные кнопки; укладываются в `CALLBACK_DATA_MAX_LEN` для id задач и списков и тегов допустимой длины
+    pub fn encode(&self) -> String {
+        let parts: Vec<&str> = match self {
+            CallbackAction::MainMenu => vec!["menu"],
+            CallbackAction::TodoMenu => vec!["todo"],
+            CallbackAction::Help => vec!["help"],
+            CallbackAction::TodoHelp => vec!["todo_help"],
+            CallbackAction::Cancel => vec!["cancel"],
+            CallbackAction::AddTask => vec!["add"],
+            CallbackAction::AddList => vec!["add_list"],
+            CallbackAction::MarkDone => vec!["done_pick"],
+            CallbackAction::RemoveTask => vec!["remove_pick"],
+            CallbackAction::RemindTask => vec!["remind_pick"],
+            CallbackAction::RepeatTask => vec!["repeat_pick"],
+            CallbackAction::EditTask => vec!["edit_pick"],
+            CallbackAction::SearchTasks => vec!["search"],
+            CallbackAction::OrderMenu => vec!["order_pick"],
+            CallbackAction::PriorityMenu => vec!["prio_pick"],
+            CallbackAction::TagsMenu => vec!["tags"],
+            CallbackAction::ListTasks { sort, page, tag } => {
+                let page = page.to_string();
+                let mut parts = vec!["list", sort.code()];
+                parts.push(&page);
+                if let Some(tag) = tag {
+                    parts.push(tag);
+                }
+                return join(&parts);
+            }
+            CallbackAction::TaskDone(task_id) => vec!["done", task_id],
+            CallbackAction::DeleteTask(task_id) => vec!["del", task_id],
+            CallbackAction::EditText(task_id) => vec!["edit", task_id],
+            CallbackAction::TaskOrder(task_id) => vec!["order", task_id],
+            CallbackAction::MoveTaskUp { task_id, page } => return join(&["up", &page.to_string(), task_id]),
+            CallbackAction::MoveTaskDown { task_id, page } => return join(&["down", &page.to_string(), task_id]),
+            CallbackAction::TaskPriority(task_id) => vec!["prio", task_id],
+            CallbackAction::SetPriority { task_id, priority } => vec!["set_prio", priority.as_str(), task_id],
+            CallbackAction::SnoozeTask { task_id, snooze } => {
+                let delay = match snooze {
+                    Snooze::Hour => "hour",
+                    Snooze::Day => "day",
+                };
+                vec!["snooze", delay, task_id]
+            }
+            CallbackAction::ClearAll => vec!["clear"],
+            CallbackAction::ClearConfirm => vec!["clear_yes"],
+            CallbackAction::Undo => vec!["undo"],
+            CallbackAction::ArchiveMenu => vec!["archive"],
+            CallbackAction::TrashMenu => vec!["trash"],
+            CallbackAction::EmptyTrash => vec!["empty_trash"],
+            CallbackAction::EmptyTrashConfirm => vec!["empty_trash_yes"],
+            CallbackAction::Restore(task_id) => vec!["restore", task_id],
+            CallbackAction::ListsMenu => vec!["lists"],
+            CallbackAction::CreateList => vec!["new_list"],
+            CallbackAction::RenameList => vec!["rename_list"],
+            CallbackAction::DeleteList => vec!["delete_list"],
+            CallbackAction::SwitchList(list_id) => vec!["switch", list_id.as_deref().unwrap_or_default()],
+            CallbackAction::MoveTaskPicker => vec!["move_pick"],
+            CallbackAction::MoveTask(task_id) => vec!["move", task_id],
+            CallbackAction::MoveTaskTo { task_id, list_id } => {
+                vec!["move_to", list_id.as_deref().unwrap_or_default(), task_id]
+            }
+            CallbackAction::ReminderMenu => vec!["reminders"],
+            CallbackAction::ReminderHelp => vec!["reminders_help"],
+            CallbackAction::CountersMenu => vec!["counters"],
+            CallbackAction::ToggleReminders => vec!["reminders_toggle"],
+            CallbackAction::AddCounter => vec!["new_counter"],
+            CallbackAction::Counter(counter_id) => vec!["counter", counter_id],
+            CallbackAction::CounterPeriod(counter_id) => vec!["counter_period", counter_id],
+            CallbackAction::RenameCounter(counter_id) => vec!["counter_rename", counter_id],
+            CallbackAction::CounterZones(counter_id) => vec!["counter_zones", counter_id],
+            CallbackAction::DeleteCounter(counter_id) => vec!["counter_del", counter_id],
+            CallbackAction::EnterReading(counter_id) => vec!["reading", counter_id],
+            CallbackAction::CounterTariff(counter_id) => vec!["tariff", counter_id],
+            CallbackAction::ConsumptionReport(month) => return join(&["report", &month.format("%Y-%m").to_string()]),
+            CallbackAction::CounterSent { counter_id, sent } => {
+                vec!["sent", if *sent { "yes" } else { "no" }, counter_id]
+            }
+        };
+        join(&parts)
+    }
+
+    /// Разбирает данные кнопки; `None` — кнопка неизвестна или устарела
+    pub fn parse(data: &str) -> Option<Self> {
+        let Some(rest) = data.strip_prefix(CALLBACK_VERSION).and_then(|rest| rest.strip_prefix(':')) else {
+            return Self::parse_legacy(data);
+        };
+        let parts: Vec<&str> = rest.split(':').collect();
+        let task_id = || parts.get(1).filter(|id| !id.is_empty()).map(|id| id.to_string());
+
+        let action = match parts[0] {
+            "menu" => CallbackAction::MainMenu,
+            "todo" => CallbackAction::TodoMenu,
+            "help" => CallbackAction::Help,
+            "todo_help" => CallbackAction::TodoHelp,
+            "cancel" => CallbackAction::Cancel,
+            "add" => CallbackAction::AddTask,
+            "add_list" => CallbackAction::AddList,
+            "done_pick" => CallbackAction::MarkDone,
+            "remove_pick" => CallbackAction::RemoveTask,
+            "remind_pick" => CallbackAction::RemindTask,
+            "repeat_pick" => CallbackAction::RepeatTask,
+            "edit_pick" => CallbackAction::EditTask,
+            "search" => CallbackAction::SearchTasks,
+            "order_pick" => CallbackAction::OrderMenu,
+            "prio_pick" => CallbackAction::PriorityMenu,
+            "tags" => CallbackAction::TagsMenu,
+            "list" => CallbackAction::ListTasks {
+                sort: TaskSort::from_code(parts.get(1)?)?,
+                page: parts.get(2)?.parse().ok()?,
+                tag: parts.get(3).map(|tag| tag.to_string()),
+            },
+            "done" => CallbackAction::TaskDone(task_id()?),
+            "del" => CallbackAction::DeleteTask(task_id()?),
+            "edit" => CallbackAction::EditText(task_id()?),
+            "order" => CallbackAction::TaskOrder(task_id()?),
+            "up" | "down" => {
+                // Принимаются два формата: `up:{страница}:{id}` и прежний `up:{id}` без страницы
+                // (кнопки в старых сообщениях), который открывает первую страницу
+                let (task_id, page) = match parts[1..] {
+                    [page, task_id] if !task_id.is_empty() => (task_id.to_string(), page.parse().ok()?),
+                    [_] => (task_id()?, 0),
+                    _ => return None,
+                };
+                if parts[0] == "up" {
+                    CallbackAction::MoveTaskUp { task_id, page }
+                } else {
+                    CallbackAction::MoveTaskDown { task_id, page }
+                }
+            }
+            "prio" => CallbackAction::TaskPriority(task_id()?),
+            "set_prio" => CallbackAction::SetPriority {
+                priority: Priority::from_str(parts.get(1)?)?,
+                task_id: parts.get(2)?.to_string(),
+            },
+            "snooze" => CallbackAction::SnoozeTask {
+                snooze: match *parts.get(1)? {
+                    "hour" => Snooze::Hour,
+                    "day" => Snooze::Day,
+                    _ => return None,
+                },
+                task_id: parts.get(2)?.to_string(),
+            },
+            "clear" => CallbackAction::ClearAll,
+            "clear_yes" => CallbackAction::ClearConfirm,
+            "undo" => CallbackAction::Undo,
+            "archive" => CallbackAction::ArchiveMenu,
+            "trash" => CallbackAction::TrashMenu,
+            "empty_trash" => CallbackAction::EmptyTrash,
+            "empty_trash_yes" => CallbackAction::EmptyTrashConfirm,
+            "restore" => CallbackAction::Restore(task_id()?),
+            "lists" => CallbackAction::ListsMenu,
+            "new_list" => CallbackAction::CreateList,
+            "rename_list" => CallbackAction::RenameList,
+            "delete_list" => CallbackAction::DeleteList,
+            "switch" => CallbackAction::SwitchList(task_id()),
+            "move_pick" => CallbackAction::MoveTaskPicker,
+            "move" => CallbackAction::MoveTask(task_id()?),
+            "move_to" => CallbackAction::MoveTaskTo {
+                list_id: task_id(),
+                task_id: parts.get(2)?.to_string(),
+            },
+            "reminders" => CallbackAction::ReminderMenu,
+            "reminders_help" => CallbackAction::ReminderHelp,
+            "counters" => CallbackAction::CountersMenu,
+            "reminders_toggle" => CallbackAction::ToggleReminders,
+            "new_counter" => CallbackAction::AddCounter,
+            "counter" => CallbackAction::Counter(task_id()?),
+            "counter_period" => CallbackAction::CounterPeriod(task_id()?),
+            "counter_rename" => CallbackAction::RenameCounter(task_id()?),
+            "counter_zones" => CallbackAction::CounterZones(task_id()?),
+            "counter_del" => CallbackAction::DeleteCounter(task_id()?),
+            "reading" => CallbackAction::EnterReading(task_id()?),
+            "tariff" => CallbackAction::CounterTariff(task_id()?),
+            "report" => CallbackAction::ConsumptionReport(NaiveDate::parse_from_str(&format!("{}-01", parts.get(1)?), "%Y-%m-%d").ok()?),
+            "sent" => CallbackAction::CounterSent {
+                sent: match *parts.get(1)? {
+                    "yes" => true,
+                    "no" => false,
+                    _ => return None,
+                },
+                counter_id: parts.get(2).filter(|id| !id.is_empty())?.to_string(),
+            },
+            _ => return None,
+        };
+        Some(action)
+    }
+
+    /// Кнопки напоминаний до появления версий: такие сообщения бот отправляет сам,
+    /// и они могут долго лежать в чате. Кнопки старых меню не поддерживаются.
+    fn parse_legacy(data: &str) -> Option<Self> {
+        if let Some(task_id) = data.strip_prefix("task_done_") {
+            return Some(CallbackAction::TaskDone(task_id.to_string()));
+        }
+        if let Some(task_id) = data.strip_prefix("task_snooze_hour_") {
+            return Some(CallbackAction::SnoozeTask { task_id: task_id.to_string(), snooze: Snooze::Hour });
+        }
+        if let Some(task_id) = data.strip_prefix("task_snooze_day_") {
+            return Some(CallbackAction::SnoozeTask { task_id: task_id.to_string(), snooze: Snooze::Day });
+        }
+        if let Some(counter_id) = data.strip_prefix("sent_yes_") {
+            return Some(CallbackAction::CounterSent { counter_id: counter_id.to_string(), sent: true });
+        }
+        if let Some(counter_id) = data.strip_prefix("sent_no_") {
+            return Some(CallbackAction::CounterSent { counter_id: counter_id.to_string(), sent: false });
+        }
+        None
+    }
+}
+
+fn join(parts: &[&str]) -> String {
+    let data = format!("{}:{}", CALLBACK_VERSION, parts.join(":"));
+    debug_assert!(data.len() <= CALLBACK_DATA_MAX_LEN, "callback data too long: {}", data);
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::parser::MAX_TAG_LEN;
+
+    #[test]
+    fn test_callback_actions_roundtrip_within_limit() {
+        let task_id = uuid::Uuid::new_v4().to_string();
+        let actions = vec![
+            CallbackAction::TodoMenu,
+            CallbackAction::ListTasks { sort: TaskSort::Priority, page: 999, tag: Some("ж".repeat(MAX_TAG_LEN)) },
+            CallbackAction::ListTasks { sort: TaskSort::Added, page: 0, tag: None },
+            CallbackAction::SetPriority { task_id: task_id.clone(), priority: Priority::Urgent },
+            CallbackAction::SnoozeTask { task_id: task_id.clone(), snooze: Snooze::Day },
+            CallbackAction::SwitchList(None),
+            CallbackAction::MoveTaskDown { task_id: task_id.clone(), page: 12 },
+            CallbackAction::MoveTaskTo { task_id: task_id.clone(), list_id: Some("1a2b3c4d".to_string()) },
+            CallbackAction::CounterPeriod("1a2b3c4d".to_string()),
+            CallbackAction::EnterReading("electricity".to_string()),
+            CallbackAction::ConsumptionReport(NaiveDate::from_ymd_opt(2025, 6, 1).unwrap()),
+            CallbackAction::CounterSent { counter_id: "electricity".to_string(), sent: false },
+        ];
+
+        for action in actions {
+            let data = action.encode();
+            assert!(data.len() <= CALLBACK_DATA_MAX_LEN, "{}", data);
+            assert_eq!(CallbackAction::parse(&data), Some(action));
+        }
+    }
+
+    #[test]
+    fn test_unknown_and_legacy_callback_data() {
+        assert_eq!(CallbackAction::parse("todo_menu"), None);
+        assert_eq!(CallbackAction::parse("1:unknown"), None);
+        assert_eq!(CallbackAction::parse("1:done"), None);
+        assert_eq!(CallbackAction::parse("2:todo"), None);
+        assert_eq!(CallbackAction::parse("task_done_abc"), Some(CallbackAction::TaskDone("abc".to_string())));
+        assert_eq!(
+            CallbackAction::parse("sent_yes_water"),
+            Some(CallbackAction::CounterSent { counter_id: "water".to_string(), sent: true })
+        );
+    }
+
+    #[test]
+    fn test_move_task_callback_formats() {
+        let action = CallbackAction::MoveTaskUp { task_id: "abc".to_string(), page: 3 };
+        assert_eq!(action.encode(), "1:up:3:abc");
+        assert_eq!(CallbackAction::parse("1:up:3:abc"), Some(action));
+        assert_eq!(
+            CallbackAction::parse("1:down:0:abc"),
+            Some(CallbackAction::MoveTaskDown { task_id: "abc".to_string(), page: 0 })
+        );
+
+        // Прежний формат без страницы
+        assert_eq!(CallbackAction::parse("1:up:abc"), Some(CallbackAction::MoveTaskUp { task_id: "abc".to_string(), page: 0 }));
+        assert_eq!(CallbackAction::parse("1:down:abc"), Some(CallbackAction::MoveTaskDown { task_id: "abc".to_string(), page: 0 }));
+
+        assert_eq!(CallbackAction::parse("1:up"), None);
+        assert_eq!(CallbackAction::parse("1:up:x:abc"), None);
+        assert_eq!(CallbackAction::parse("1:up:3:"), None);
+        assert_eq!(CallbackAction::parse("1:up:3:abc:extra"), None);
+    }
+}
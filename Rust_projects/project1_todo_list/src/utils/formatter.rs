@@ -0,0 +1,411 @@
+use std::cmp::Reverse;
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Utc};
+use chrono_tz::Europe::Moscow;
+
+use crate::models::{MeterReading, MonthlyConsumption, TodoItem};
+
+/// Порядок вывода задач в `/list`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TaskSort {
+    /// В порядке списка: по умолчанию — порядок добавления, меняется через `/move`
+    Added,
+    /// Сначала задачи с ближайшим сроком, задачи без срока — в конце
+    Due,
+    /// Сначала срочные, внутри одного приоритета — по сроку
+    Priority,
+}
+
+impl TaskSort {
+    /// Короткий код для данных кнопок
+    pub fn code(&self) -> &'static str {
+        match self {
+            TaskSort::Added => "a",
+            TaskSort::Due => "d",
+            TaskSort::Priority => "p",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        [TaskSort::Added, TaskSort::Due, TaskSort::Priority]
+            .into_iter()
+            .find(|sort| sort.code() == code)
+    }
+
+    pub fn from_arg(arg: &str) -> Option<Self> {
+        match arg.trim().to_lowercase().as_str() {
+            "" => Some(TaskSort::Added),
+            "срок" | "due" => Some(TaskSort::Due),
+            "приоритет" | "priority" => Some(TaskSort::Priority),
+            _ => None,
+        }
+    }
+}
+
+/// Форматирует срок задачи по московскому времени
+pub fn format_due(due_at: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let due = Moscow.from_utc_datetime(&due_at.naive_utc());
+    if due.year() == Moscow.from_utc_datetime(&now.naive_utc()).year() {
+        due.format("%d.%m %H:%M").to_string()
+    } else {
+        due.format("%d.%m.%Y %H:%M").to_string()
+    }
+}
+
+/// Число без лишних нулей, не больше трех знаков после точки: 1234.5 → «1234.5», 12.2999999 → «12.3»
+pub fn format_amount(value: f64) -> String {
+    let text = format!("{:.3}", value);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// Дата показаний по московскому времени
+pub fn format_reading_date(taken_at: DateTime<Utc>) -> String {
+    Moscow.from_utc_datetime(&taken_at.naive_utc()).format("%d.%m.%Y").to_string()
+}
+
+/// Значения показаний одной отправки: «1234.5» или «день 1234, ночь 567»
+pub fn format_reading_values(readings: &[&MeterReading]) -> String {
+    readings
+        .iter()
+        .map(|r| {
+            if r.zone.is_empty() {
+                format_amount(r.value)
+            } else {
+                format!("{} {}", r.zone, format_amount(r.value))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+const MONTH_NAMES: [&str; 12] = [
+    "январь", "февраль", "март", "апрель", "май", "июнь",
+    "июль", "август", "сентябрь", "октябрь", "ноябрь", "декабрь",
+];
+
+/// Месяц отчета: «июнь 2025»
+pub fn format_month(month: NaiveDate) -> String {
+    format!("{} {}", MONTH_NAMES[month.month0() as usize], month.year())
+}
+
+/// Сумма в рублях: «2447.20 ₽»
+pub fn format_money(value: f64) -> String {
+    format!("{:.2} ₽", value)
+}
+
+/// Расход счетчика за месяц по зонам: «день 150 × 6.5 ₽ = 975.00 ₽, ночь 30 (тариф не задан)»
+pub fn format_consumption(consumption: &MonthlyConsumption) -> String {
+    consumption
+        .zones
+        .iter()
+        .map(|zone| {
+            let amount = if zone.zone.is_empty() {
+                format_amount(zone.amount)
+            } else {
+                format!("{} {}", zone.zone, format_amount(zone.amount))
+            };
+            match (zone.price, zone.cost()) {
+                (Some(price), Some(cost)) => format!("{} × {} ₽ = {}", amount, format_amount(price), format_money(cost)),
+                _ => format!("{} (тариф не задан)", amount),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Текст задачи со сроком, для подтверждений после добавления
+pub fn format_task_title(todo: &TodoItem) -> String {
+    let mut text = match todo.priority.marker() {
+        Some(marker) => format!("{} {}", marker, todo.text),
+        None => todo.text.clone(),
+    };
+    for tag in &todo.tags {
+        text.push_str(&format!(" #{}", tag));
+    }
+    match todo.due_at {
+        Some(due_at) => format!("{} (📅 до {})", text, format_due(due_at, Utc::now())),
+        None => text,
+    }
+}
+
+/// Сколько задач (вместе с подзадачами) показывать на одной странице списка
+pub const TASKS_PER_PAGE: usize = 8;
+/// Предел длины строк задач на странице: сообщение Telegram — не больше 4096 символов,
+/// остаток оставлен на заголовки
+const PAGE_TEXT_LIMIT: usize = 3500;
+
+/// Страница списка задач
+pub struct TaskPage<'a> {
+    /// Заголовок и строки задач страницы
+    pub text: String,
+    /// Задачи страницы в порядке вывода, с номерами в списке (с нуля)
+    pub tasks: Vec<(usize, &'a TodoItem)>,
+    /// Номер страницы с нуля; номер за концом списка заменяется последней страницей
+    pub page: usize,
+    pub pages: usize,
+}
+
+/// Страница списка задач для вывода пользователю.
+/// Номер — позиция в исходном списке (её принимают `/done` и `/remove`) даже при сортировке,
+/// в скобках — постоянный короткий псевдоним задачи. С `tag` выводятся только задачи с этим тегом.
+/// Подзадачи выводятся с отступом под родителем, сортировка применяется к задачам верхнего уровня.
+/// На странице не больше `TASKS_PER_PAGE` задач и `PAGE_TEXT_LIMIT` символов;
+/// если страница начинается с подзадачи, над ней повторяется родитель.
+pub fn format_task_page<'a>(todos: &'a [TodoItem], sort: TaskSort, tag: Option<&str>, page: usize) -> TaskPage<'a> {
+    let lines = task_lines(todos, sort, tag);
+    let starts = page_starts(&lines);
+    let pages = starts.len();
+    let page = page.min(pages - 1);
+    let end = starts.get(page + 1).copied().unwrap_or(lines.len());
+    let page_lines = &lines[starts[page]..end];
+
+    let mut text = list_header(tag);
+    if let Some((index, parent)) = page_lines.first().and_then(|line| line.parent) {
+        text.push_str(&format!("{}. {} (продолжение)\n", index + 1, parent.text));
+    }
+    for line in page_lines {
+        text.push_str(&line.text);
+        text.push('\n');
+    }
+    TaskPage {
+        text,
+        tasks: page_lines.iter().map(|line| (line.index, line.todo)).collect(),
+        page,
+        pages,
+    }
+}
+
+/// Номер страницы (с нуля), на которой `format_task_page` выводит задачу; `None`, если задачи нет в списке
+pub fn task_page_number(todos: &[TodoItem], sort: TaskSort, tag: Option<&str>, task_id: &str) -> Option<usize> {
+    let lines = task_lines(todos, sort, tag);
+    let position = lines.iter().position(|line| line.todo.id == task_id)?;
+    Some(page_starts(&lines).partition_point(|&start| start <= position) - 1)
+}
+
+/// Индексы первых строк страниц
+fn page_starts(lines: &[TaskLine]) -> Vec<usize> {
+    let mut starts = vec![0];
+    let (mut count, mut len) = (0, 0);
+    for (i, line) in lines.iter().enumerate() {
+        let line_len = line.text.chars().count() + 1;
+        if count > 0 && (count == TASKS_PER_PAGE || len + line_len > PAGE_TEXT_LIMIT) {
+            starts.push(i);
+            (count, len) = (0, 0);
+        }
+        count += 1;
+        len += line_len;
+    }
+    starts
+}
+
+fn list_header(tag: Option<&str>) -> String {
+    match tag {
+        Some(tag) => format!("📋 Задачи с тегом #{}:\n\n", tag),
+        None => "📋 Ваши задачи:\n\n".to_string(),
+    }
+}
+
+/// Строка списка задач
+struct TaskLine<'a> {
+    index: usize,
+    todo: &'a TodoItem,
+    /// Родитель с номером, если задача выводится с отступом под ним
+    parent: Option<(usize, &'a TodoItem)>,
+    text: String,
+}
+
+/// Строки списка в порядке вывода: видимые задачи верхнего уровня, под каждой — её подзадачи
+fn task_lines<'a>(todos: &'a [TodoItem], sort: TaskSort, tag: Option<&str>) -> Vec<TaskLine<'a>> {
+    let now = Utc::now();
+    let is_visible = |todo: &TodoItem| tag.is_none_or(|tag| todo.has_tag(tag));
+    // Подзадача видимого родителя выводится под ним, даже если сама не подходит под фильтр
+    let under_visible_parent = |todo: &TodoItem| {
+        todo.parent_id
+            .as_ref()
+            .and_then(|parent_id| todos.iter().find(|t| &t.id == parent_id))
+            .is_some_and(is_visible)
+    };
+
+    let mut ordered: Vec<(usize, &TodoItem)> = todos
+        .iter()
+        .enumerate()
+        .filter(|(_, todo)| is_visible(todo) && !under_visible_parent(todo))
+        .collect();
+    // sort_by_key стабилен, поэтому при равных ключах сохраняется порядок добавления
+    match sort {
+        TaskSort::Added => {}
+        TaskSort::Due => ordered.sort_by_key(|(_, todo)| (todo.due_at.is_none(), todo.due_at)),
+        TaskSort::Priority => {
+            ordered.sort_by_key(|(_, todo)| (Reverse(todo.priority), todo.due_at.is_none(), todo.due_at))
+        }
+    }
+
+    let mut lines = Vec::with_capacity(todos.len());
+    for (index, todo) in ordered {
+        let children: Vec<(usize, &TodoItem)> = todos
+            .iter()
+            .enumerate()
+            .filter(|(_, child)| child.parent_id.as_deref() == Some(todo.id.as_str()))
+            .collect();
+
+        let mut text = format_task_line(index, todo, now);
+        if !children.is_empty() {
+            let done = children.iter().filter(|(_, child)| child.is_completed()).count();
+            text.push_str(&format!(" ({}/{})", done, children.len()));
+        }
+        lines.push(TaskLine { index, todo, parent: None, text });
+
+        for (child_index, child) in children {
+            lines.push(TaskLine {
+                index: child_index,
+                todo: child,
+                parent: Some((index, todo)),
+                text: format!("      {}", format_task_line(child_index, child, now)),
+            });
+        }
+    }
+    lines
+}
+
+/// Задачи архива или корзины: номер (его принимают `/unarchive` и `/restore`), текст, код
+/// и когда задача убрана. Подзадачи, убранные вместе с родителем, выводятся под ним с отступом.
+pub fn format_removed_tasks(todos: &[TodoItem]) -> String {
+    let now = Utc::now();
+    let in_slice = |id: &String| todos.iter().any(|t| &t.id == id);
+    let mut response = String::new();
+
+    for (index, todo) in todos.iter().enumerate() {
+        if todo.parent_id.as_ref().is_some_and(in_slice) {
+            continue;
+        }
+        response.push_str(&format_removed_line(index, todo, now));
+        response.push('\n');
+
+        for (child_index, child) in todos.iter().enumerate() {
+            if child.parent_id.as_deref() == Some(todo.id.as_str()) {
+                response.push_str("      ");
+                response.push_str(&format_removed_line(child_index, child, now));
+                response.push('\n');
+            }
+        }
+    }
+    response
+}
+
+/// Строка результата поиска: номер в выдаче, задача и где она лежит
+/// (`location` — например, «🗂 Дом, №3» или «📦 архив, №2»)
+pub fn format_search_result(index: usize, todo: &TodoItem, location: &str) -> String {
+    let status = if todo.is_completed() { "✅" } else { "⏳" };
+    let mut line = format!("{}. {} {} [{}]", index + 1, status, todo.text, todo.short_id());
+    for tag in &todo.tags {
+        line.push_str(&format!(" #{}", tag));
+    }
+    line.push_str(&format!(" — {}", location));
+    line
+}
+
+fn format_removed_line(index: usize, todo: &TodoItem, now: DateTime<Utc>) -> String {
+    let status = if todo.is_completed() { "✅" } else { "⏳" };
+    let mut line = format!("{}. {} {} [{}]", index + 1, status, todo.text, todo.short_id());
+    if let Some(deleted_at) = todo.deleted_at {
+        line.push_str(&format!(" — 🗑 {}", format_due(deleted_at, now)));
+    } else if let Some(archived_at) = todo.archived_at {
+        line.push_str(&format!(" — 📦 {}", format_due(archived_at, now)));
+    }
+    line
+}
+
+fn format_task_line(index: usize, todo: &TodoItem, now: DateTime<Utc>) -> String {
+    let status = if todo.is_completed() { "✅" } else { "⏳" };
+    let mut line = format!("{}. {} ", index + 1, status);
+    if let Some(marker) = todo.priority.marker() {
+        line.push_str(marker);
+        line.push(' ');
+    }
+    line.push_str(&format!("{} [{}]", todo.text, todo.short_id()));
+    for tag in &todo.tags {
+        line.push_str(&format!(" #{}", tag));
+    }
+    if let Some(due_at) = todo.due_at {
+        if todo.is_overdue(now) {
+            line.push_str(&format!(" — 🔥 просрочено ({})", format_due(due_at, now)));
+        } else {
+            line.push_str(&format!(" — 📅 до {}", format_due(due_at, now)));
+        }
+    }
+    if let Some(recurrence) = &todo.recurrence {
+        line.push_str(&format!(" 🔁 {}", recurrence.describe()));
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_task_page_nests_children() {
+        let parent = TodoItem::new("Магазин".to_string());
+        let mut milk = TodoItem::new("Молоко".to_string()).with_parent(Some(parent.id.clone()));
+        milk.mark_completed(Utc::now());
+        let bread = TodoItem::new("Хлеб".to_string()).with_parent(Some(parent.id.clone())).with_tags(vec!["еда".to_string()]);
+        let other = TodoItem::new("Позвонить".to_string());
+        let todos = vec![parent, other, milk, bread];
+
+        let lines: Vec<String> = format_task_page(&todos, TaskSort::Added, None, 0).text.lines().skip(2).map(str::to_string).collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("1. ⏳ Магазин") && lines[0].ends_with("(1/2)"));
+        assert!(lines[1].trim_start().starts_with("3. ✅ Молоко"));
+        assert!(lines[2].trim_start().starts_with("4. ⏳ Хлеб"));
+        assert!(lines[3].starts_with("2. ⏳ Позвонить"));
+
+        // Подзадача, подходящая под фильтр, выводится и без родителя
+        let filtered = format_task_page(&todos, TaskSort::Added, Some("еда"), 0).text;
+        assert_eq!(filtered.lines().skip(2).collect::<Vec<_>>().len(), 1);
+        assert!(filtered.contains("4. ⏳ Хлеб"));
+    }
+
+    #[test]
+    fn test_format_task_page_splits_long_lists() {
+        let parent = TodoItem::new("Магазин".to_string());
+        let mut todos = vec![parent.clone()];
+        todos.extend((0..TASKS_PER_PAGE + 2).map(|i| TodoItem::new(format!("Пункт {}", i)).with_parent(Some(parent.id.clone()))));
+        todos.push(TodoItem::new("Позвонить".to_string()));
+
+        let first = format_task_page(&todos, TaskSort::Added, None, 0);
+        assert_eq!((first.page, first.pages), (0, 2));
+        assert_eq!(first.tasks.len(), TASKS_PER_PAGE);
+
+        // Страница за концом списка заменяется последней, продолжение подписано родителем
+        let last = format_task_page(&todos, TaskSort::Added, None, 5);
+        assert_eq!(last.page, 1);
+        assert_eq!(last.tasks.len(), todos.len() - TASKS_PER_PAGE);
+        assert!(last.text.contains("1. Магазин (продолжение)"));
+        assert_eq!(last.tasks.last().map(|(index, _)| *index), Some(todos.len() - 1));
+
+        let last_id = &todos[todos.len() - 1].id;
+        assert_eq!(task_page_number(&todos, TaskSort::Added, None, &todos[0].id), Some(0));
+        assert_eq!(task_page_number(&todos, TaskSort::Added, None, last_id), Some(1));
+        assert_eq!(task_page_number(&todos, TaskSort::Added, None, "missing"), None);
+
+        let empty = format_task_page(&[], TaskSort::Added, None, 0);
+        assert_eq!((empty.pages, empty.tasks.len()), (1, 0));
+    }
+
+    #[test]
+    fn test_format_removed_tasks() {
+        let now = Utc::now();
+        let mut parent = TodoItem::new("Магазин".to_string());
+        let mut milk = TodoItem::new("Молоко".to_string()).with_parent(Some(parent.id.clone()));
+        let mut note = TodoItem::new("Заметка".to_string());
+        for todo in [&mut parent, &mut milk, &mut note] {
+            todo.deleted_at = Some(now);
+        }
+
+        let text = format_removed_tasks(&[note, parent, milk]);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("1. ⏳ Заметка") && lines[0].contains("🗑"));
+        assert!(lines[1].starts_with("2. ⏳ Магазин"));
+        assert!(lines[2].trim_start().starts_with("3. ⏳ Молоко"));
+    }
+}
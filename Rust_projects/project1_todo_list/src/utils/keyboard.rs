@@ -1,13 +1,25 @@
+use chrono::{Months, NaiveDate, Utc};
 use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
 
+use crate::models::{month_of, CounterReminder, CounterType, Priority, TaskList, TodoItem, MAIN_LIST_NAME};
+use super::callback_data::{CallbackAction, Snooze};
+use super::formatter::TaskSort;
+
+/// Максимальная длина текста задачи на кнопке
+const BUTTON_TEXT_LEN: usize = 30;
+
+fn button(label: impl Into<String>, action: CallbackAction) -> InlineKeyboardButton {
+    InlineKeyboardButton::callback(label, action.encode())
+}
+
 pub fn create_main_menu() -> InlineKeyboardMarkup {
     InlineKeyboardMarkup::new(vec![
         vec![
-            InlineKeyboardButton::callback("📝 TODO List", "todo_menu"),
-            InlineKeyboardButton::callback("⏰ Напоминалка", "reminder_menu"),
+            button("📝 TODO List", CallbackAction::TodoMenu),
+            button("⏰ Напоминалка", CallbackAction::ReminderMenu),
         ],
         vec![
-            InlineKeyboardButton::callback("❓ Справка", "help"),
+            button("❓ Справка", CallbackAction::Help),
         ],
     ])
 }
@@ -15,19 +27,43 @@ pub fn create_main_menu() -> InlineKeyboardMarkup {
 pub fn create_todo_menu() -> InlineKeyboardMarkup {
     InlineKeyboardMarkup::new(vec![
         vec![
-            InlineKeyboardButton::callback("➕ Добавить задачу", "add_task"),
-            InlineKeyboardButton::callback("📋 Показать задачи", "list_tasks"),
+            button("➕ Добавить задачу", CallbackAction::AddTask),
+            button("📋 Показать задачи", CallbackAction::ListTasks { sort: TaskSort::Added, page: 0, tag: None }),
+        ],
+        vec![
+            button("📝 Добавить список", CallbackAction::AddList),
+            button("✅ Отметить выполненной", CallbackAction::MarkDone),
+        ],
+        vec![
+            button("🗑️ Удалить задачу", CallbackAction::RemoveTask),
+            button("🧹 Очистить все", CallbackAction::ClearAll),
+        ],
+        vec![
+            button("📅 По сроку", CallbackAction::ListTasks { sort: TaskSort::Due, page: 0, tag: None }),
+            button("‼️ По приоритету", CallbackAction::ListTasks { sort: TaskSort::Priority, page: 0, tag: None }),
+        ],
+        vec![
+            button("🔔 Напомнить", CallbackAction::RemindTask),
+            button("🔁 Повторение", CallbackAction::RepeatTask),
+        ],
+        vec![
+            button("🔼 Приоритет задачи", CallbackAction::PriorityMenu),
+            button("🏷 Теги", CallbackAction::TagsMenu),
+        ],
+        vec![
+            button("✏️ Изменить", CallbackAction::EditTask),
+            button("↕️ Порядок", CallbackAction::OrderMenu),
         ],
         vec![
-            InlineKeyboardButton::callback("📝 Добавить список", "add_list"),
-            InlineKeyboardButton::callback("✅ Отметить выполненной", "mark_done"),
+            button("🗂 Списки", CallbackAction::ListsMenu),
+            button("🔍 Поиск", CallbackAction::SearchTasks),
         ],
         vec![
-            InlineKeyboardButton::callback("🗑️ Удалить задачу", "remove_task"),
-            InlineKeyboardButton::callback("🧹 Очистить все", "clear_all"),
+            button("📦 Архив", CallbackAction::ArchiveMenu),
+            button("♻️ Корзина", CallbackAction::TrashMenu),
         ],
         vec![
-            InlineKeyboardButton::callback("🔙 Назад в главное меню", "main_menu"),
+            button("🔙 Назад в главное меню", CallbackAction::MainMenu),
         ],
     ])
 }
@@ -35,37 +71,331 @@ pub fn create_todo_menu() -> InlineKeyboardMarkup {
 pub fn create_reminder_menu() -> InlineKeyboardMarkup {
     InlineKeyboardMarkup::new(vec![
         vec![
-            InlineKeyboardButton::callback("🏠 Счетчики", "counters_menu"),
+            button("🏠 Счетчики", CallbackAction::CountersMenu),
+            button("📊 Расходы", CallbackAction::ConsumptionReport(month_of(Utc::now()))),
+        ],
+        vec![
+            button("🔔 Вкл/Выкл напоминания", CallbackAction::ToggleReminders),
+        ],
+        vec![
+            button("❓ Справка по напоминаниям", CallbackAction::ReminderHelp),
+        ],
+        vec![
+            button("🔙 Назад в главное меню", CallbackAction::MainMenu),
+        ],
+    ])
+}
+
+/// Кнопка на каждый счетчик пользователя (в порядке `UserReminders::counters`)
+pub fn create_counters_menu(counters: &[&CounterReminder]) -> InlineKeyboardMarkup {
+    let buttons: Vec<InlineKeyboardButton> = counters
+        .iter()
+        .map(|r| button(r.counter_type.display_name(), CallbackAction::Counter(r.counter_type.id.clone())))
+        .collect();
+    let mut rows: Vec<Vec<InlineKeyboardButton>> = buttons.chunks(2).map(|pair| pair.to_vec()).collect();
+
+    rows.push(vec![button("➕ Новый счетчик", CallbackAction::AddCounter)]);
+    rows.push(vec![button("🔙 Назад к напоминаниям", CallbackAction::ReminderMenu)]);
+    InlineKeyboardMarkup::new(rows)
+}
+
+pub fn create_counter_keyboard(counter_id: &str) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![
+        vec![
+            button("🔢 Ввести показания", CallbackAction::EnterReading(counter_id.to_string())),
+        ],
+        vec![
+            button("📅 Период", CallbackAction::CounterPeriod(counter_id.to_string())),
+            button("✏️ Переименовать", CallbackAction::RenameCounter(counter_id.to_string())),
+        ],
+        vec![
+            button("🔀 Тарифные зоны", CallbackAction::CounterZones(counter_id.to_string())),
+            button("💰 Тариф", CallbackAction::CounterTariff(counter_id.to_string())),
+        ],
+        vec![
+            button("🗑️ Удалить счетчик", CallbackAction::DeleteCounter(counter_id.to_string())),
+        ],
+        vec![
+            button("🔙 К счетчикам", CallbackAction::CountersMenu),
+        ],
+    ])
+}
+
+/// Переход между месяцами отчета о расходах; будущие месяцы не показываются
+pub fn create_consumption_report_keyboard(month: NaiveDate) -> InlineKeyboardMarkup {
+    let mut navigation = vec![button("◀️", CallbackAction::ConsumptionReport(month - Months::new(1)))];
+    if month < month_of(Utc::now()) {
+        navigation.push(button("▶️", CallbackAction::ConsumptionReport(month + Months::new(1))));
+    }
+    InlineKeyboardMarkup::new(vec![
+        navigation,
+        vec![
+            button("🏠 Счетчики", CallbackAction::CountersMenu),
+            button("🔙 Назад к напоминаниям", CallbackAction::ReminderMenu),
+        ],
+    ])
+}
+
+pub fn create_reminder_response_keyboard(counter_type: &CounterType) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![
+        vec![
+            button("🔢 Ввести показания", CallbackAction::EnterReading(counter_type.id.clone())),
         ],
         vec![
-            InlineKeyboardButton::callback("🔔 Вкл/Выкл напоминания", "toggle_reminders"),
+            button("✅ Да, отправил", CallbackAction::CounterSent { counter_id: counter_type.id.clone(), sent: true }),
+            button("❌ Нет, еще не отправил", CallbackAction::CounterSent { counter_id: counter_type.id.clone(), sent: false }),
         ],
+    ])
+}
+
+pub fn create_task_reminder_keyboard(task_id: &str) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![
         vec![
-            InlineKeyboardButton::callback("❓ Справка по напоминаниям", "reminder_help"),
+            button("✅ Выполнено", CallbackAction::TaskDone(task_id.to_string())),
         ],
         vec![
-            InlineKeyboardButton::callback("🔙 Назад в главное меню", "main_menu"),
+            button("⏰ Через час", CallbackAction::SnoozeTask { task_id: task_id.to_string(), snooze: Snooze::Hour }),
+            button("⏰ Завтра", CallbackAction::SnoozeTask { task_id: task_id.to_string(), snooze: Snooze::Day }),
         ],
     ])
 }
 
-pub fn create_counters_menu() -> InlineKeyboardMarkup {
+/// Кнопка на каждую невыполненную задачу; `action` — действие кнопки для id задачи
+pub fn create_task_picker_keyboard(todos: &[TodoItem], action: fn(String) -> CallbackAction) -> InlineKeyboardMarkup {
+    let mut rows: Vec<Vec<InlineKeyboardButton>> = todos
+        .iter()
+        .enumerate()
+        .filter(|(_, todo)| !todo.is_completed())
+        .map(|(index, todo)| {
+            let mut label = format!("{}. ", index + 1);
+            if let Some(marker) = todo.priority.marker() {
+                label.push_str(marker);
+                label.push(' ');
+            }
+            label.extend(todo.text.chars().take(BUTTON_TEXT_LEN));
+            if todo.text.chars().count() > BUTTON_TEXT_LEN {
+                label.push('…');
+            }
+            vec![button(label, action(todo.id.clone()))]
+        })
+        .collect();
+
+    rows.push(vec![button("🔙 Назад", CallbackAction::TodoMenu)]);
+    InlineKeyboardMarkup::new(rows)
+}
+
+pub fn create_priority_keyboard(task_id: &str) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(
+        Priority::ALL
+            .chunks(2)
+            .map(|pair| {
+                pair.iter()
+                    .map(|p| button(p.display_name(), CallbackAction::SetPriority { task_id: task_id.to_string(), priority: *p }))
+                    .collect()
+            })
+            .collect::<Vec<Vec<_>>>(),
+    )
+}
+
+/// Перемещение задачи выше или ниже соседней; кнопки остаются под списком, пока порядок не устроит
+pub fn create_task_order_keyboard(task_id: &str, page: usize) -> InlineKeyboardMarkup {
     InlineKeyboardMarkup::new(vec![
         vec![
-            InlineKeyboardButton::callback("💧 Вода", "counter_water"),
-            InlineKeyboardButton::callback("⚡ Электричество", "counter_electricity"),
+            button("⬆️ Выше", CallbackAction::MoveTaskUp { task_id: task_id.to_string(), page }),
+            button("⬇️ Ниже", CallbackAction::MoveTaskDown { task_id: task_id.to_string(), page }),
+        ],
+        vec![
+            button("✅ Готово", CallbackAction::TodoMenu),
         ],
+    ])
+}
+
+/// Кнопки задач страницы списка (✅ — для невыполненных, 🗑 и ✏️ — для всех) и перелистывание
+pub fn create_task_page_keyboard(
+    tasks: &[(usize, &TodoItem)],
+    page: usize,
+    pages: usize,
+    sort: TaskSort,
+    tag: Option<&str>,
+) -> InlineKeyboardMarkup {
+    let mut rows: Vec<Vec<InlineKeyboardButton>> = tasks
+        .iter()
+        .map(|(index, todo)| {
+            let number = index + 1;
+            let mut row = Vec::with_capacity(3);
+            if !todo.is_completed() {
+                row.push(button(format!("✅ {}", number), CallbackAction::TaskDone(todo.id.clone())));
+            }
+            row.push(button(format!("🗑 {}", number), CallbackAction::DeleteTask(todo.id.clone())));
+            row.push(button(format!("✏️ {}", number), CallbackAction::EditText(todo.id.clone())));
+            row
+        })
+        .collect();
+
+    let page_data = |page: usize| CallbackAction::ListTasks { sort, page, tag: tag.map(str::to_string) };
+    let mut navigation = Vec::new();
+    if page > 0 {
+        navigation.push(button("◀️", page_data(page - 1)));
+    }
+    if page + 1 < pages {
+        navigation.push(button("▶️", page_data(page + 1)));
+    }
+    if !navigation.is_empty() {
+        rows.push(navigation);
+    }
+    rows.push(vec![button("🔙 Назад", CallbackAction::TodoMenu)]);
+    InlineKeyboardMarkup::new(rows)
+}
+
+/// Кнопка «вернуть» на каждую задачу архива или корзины (подзадачи возвращаются с родителем).
+/// Под корзиной — кнопка окончательной очистки.
+pub fn create_removed_tasks_keyboard(todos: &[TodoItem], trash: bool) -> InlineKeyboardMarkup {
+    let mut rows: Vec<Vec<InlineKeyboardButton>> = todos
+        .iter()
+        .enumerate()
+        .filter(|(_, todo)| !todo.parent_id.as_ref().is_some_and(|parent_id| todos.iter().any(|t| &t.id == parent_id)))
+        .map(|(index, todo)| {
+            let mut label = format!("♻️ {}. ", index + 1);
+            label.extend(todo.text.chars().take(BUTTON_TEXT_LEN));
+            if todo.text.chars().count() > BUTTON_TEXT_LEN {
+                label.push('…');
+            }
+            vec![button(label, CallbackAction::Restore(todo.id.clone()))]
+        })
+        .collect();
+
+    if trash && !todos.is_empty() {
+        rows.push(vec![button("🔥 Очистить корзину", CallbackAction::EmptyTrash)]);
+    }
+    rows.push(vec![button("🔙 Назад", CallbackAction::TodoMenu)]);
+    InlineKeyboardMarkup::new(rows)
+}
+
+/// Действия с найденными задачами: невыполненную можно отметить или удалить,
+/// выполненную — удалить, задачу из архива — вернуть в список
+pub fn create_search_results_keyboard(todos: &[TodoItem]) -> InlineKeyboardMarkup {
+    let mut rows: Vec<Vec<InlineKeyboardButton>> = todos
+        .iter()
+        .enumerate()
+        .map(|(index, todo)| {
+            let mut title = format!("{}. ", index + 1);
+            title.extend(todo.text.chars().take(BUTTON_TEXT_LEN));
+            if todo.text.chars().count() > BUTTON_TEXT_LEN {
+                title.push('…');
+            }
+
+            if !todo.is_active() {
+                vec![button(format!("♻️ {}", title), CallbackAction::Restore(todo.id.clone()))]
+            } else if todo.is_completed() {
+                vec![button(format!("🗑️ {}", title), CallbackAction::DeleteTask(todo.id.clone()))]
+            } else {
+                vec![
+                    button(format!("✅ {}", title), CallbackAction::TaskDone(todo.id.clone())),
+                    button("🗑️", CallbackAction::DeleteTask(todo.id.clone())),
+                ]
+            }
+        })
+        .collect();
+
+    rows.push(vec![
+        button("🔍 Новый поиск", CallbackAction::SearchTasks),
+        button("🔙 Назад", CallbackAction::TodoMenu),
+    ]);
+    InlineKeyboardMarkup::new(rows)
+}
+
+/// Подтверждение окончательной очистки корзины
+pub fn create_empty_trash_confirm_keyboard() -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![
         vec![
-            InlineKeyboardButton::callback("🔙 Назад к напоминаниям", "reminder_menu"),
+            button("✅ Да, удалить навсегда", CallbackAction::EmptyTrashConfirm),
+            button("❌ Отмена", CallbackAction::TrashMenu),
         ],
     ])
 }
 
-pub fn create_reminder_response_keyboard(counter_type: &str) -> InlineKeyboardMarkup {
+/// Подтверждение очистки текущего списка
+pub fn create_clear_confirm_keyboard() -> InlineKeyboardMarkup {
     InlineKeyboardMarkup::new(vec![
         vec![
-            InlineKeyboardButton::callback("✅ Да, отправил", &format!("sent_yes_{}", counter_type)),
-            InlineKeyboardButton::callback("❌ Нет, еще не отправил", &format!("sent_no_{}", counter_type)),
+            button("✅ Да, очистить", CallbackAction::ClearConfirm),
+            button("❌ Отмена", CallbackAction::TodoMenu),
         ],
     ])
-}
\ No newline at end of file
+}
+
+/// Под вопросом бота: прервать ввод, не отвечая
+pub fn create_cancel_keyboard() -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![
+        vec![
+            button("❌ Отмена", CallbackAction::Cancel),
+        ],
+    ])
+}
+
+/// Отмена последнего удаления, пока она доступна
+pub fn create_undo_keyboard() -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![
+        vec![
+            button("↩️ Отменить", CallbackAction::Undo),
+        ],
+        vec![
+            button("🔙 Назад", CallbackAction::TodoMenu),
+        ],
+    ])
+}
+
+/// Кнопка на каждый тег, нажатие показывает задачи с этим тегом
+pub fn create_tags_keyboard(tags: &[String]) -> InlineKeyboardMarkup {
+    let mut rows: Vec<Vec<InlineKeyboardButton>> = tags
+        .chunks(2)
+        .map(|pair| {
+            pair.iter()
+                .map(|tag| button(format!("#{}", tag), CallbackAction::ListTasks { sort: TaskSort::Added, page: 0, tag: Some(tag.clone()) }))
+                .collect()
+        })
+        .collect();
+
+    rows.push(vec![button("🔙 Назад", CallbackAction::TodoMenu)]);
+    InlineKeyboardMarkup::new(rows)
+}
+
+/// Основной и именованные списки: название и id (`None` — основной)
+fn list_entries(lists: &[TaskList]) -> impl Iterator<Item = (&str, Option<&str>)> {
+    std::iter::once((MAIN_LIST_NAME, None)).chain(lists.iter().map(|l| (l.name.as_str(), Some(l.id.as_str()))))
+}
+
+/// Выбор текущего списка (отмечен ✔️) и действия над списками
+pub fn create_lists_keyboard(lists: &[TaskList], current: Option<&str>) -> InlineKeyboardMarkup {
+    let buttons: Vec<InlineKeyboardButton> = list_entries(lists)
+        .map(|(name, list_id)| {
+            let label = if list_id == current { format!("✔️ {}", name) } else { name.to_string() };
+            button(label, CallbackAction::SwitchList(list_id.map(str::to_string)))
+        })
+        .collect();
+    let mut rows: Vec<Vec<InlineKeyboardButton>> = buttons.chunks(2).map(|pair| pair.to_vec()).collect();
+
+    rows.push(vec![
+        button("➕ Новый список", CallbackAction::CreateList),
+        button("✏️ Переименовать", CallbackAction::RenameList),
+    ]);
+    rows.push(vec![
+        button("🗑️ Удалить список", CallbackAction::DeleteList),
+        button("📦 Перенести задачу", CallbackAction::MoveTaskPicker),
+    ]);
+    rows.push(vec![button("🔙 Назад", CallbackAction::TodoMenu)]);
+    InlineKeyboardMarkup::new(rows)
+}
+
+/// Куда перенести задачу: все списки, кроме текущего
+pub fn create_move_target_keyboard(lists: &[TaskList], current: Option<&str>, task_id: &str) -> InlineKeyboardMarkup {
+    let mut rows: Vec<Vec<InlineKeyboardButton>> = list_entries(lists)
+        .filter(|(_, list_id)| *list_id != current)
+        .map(|(name, list_id)| {
+            vec![button(name, CallbackAction::MoveTaskTo { task_id: task_id.to_string(), list_id: list_id.map(str::to_string) })]
+        })
+        .collect();
+
+    rows.push(vec![button("🔙 Назад", CallbackAction::ListsMenu)]);
+    InlineKeyboardMarkup::new(rows)
+}
@@ -1,7 +1,10 @@
 pub mod keyboard;
 pub mod parser;
+pub mod formatter;
 pub mod validation;
+pub mod callback_data;
 
-pub use keyboard::{create_main_menu, create_todo_menu, create_reminder_menu, create_counters_menu, create_reminder_response_keyboard};
-pub use parser::parse_task_list;
-pub use validation::{TaskValidator, TaskIndexValidator, DayValidator, ChatIdValidator, ValidationResult};
\ No newline at end of file
+pub use keyboard::{create_main_menu, create_todo_menu, create_reminder_menu, create_counters_menu, create_counter_keyboard, create_consumption_report_keyboard, create_reminder_response_keyboard, create_task_reminder_keyboard, create_task_picker_keyboard, create_priority_keyboard, create_task_order_keyboard, create_task_page_keyboard, create_clear_confirm_keyboard, create_removed_tasks_keyboard, create_search_results_keyboard, create_empty_trash_confirm_keyboard, create_undo_keyboard, create_cancel_keyboard, create_tags_keyboard, create_lists_keyboard, create_move_target_keyboard};
+pub use parser::{parse_task_list, resolve_task, resolve_list, extract_priority, extract_tags, normalize_tag, extract_due_date, parse_remind_at, parse_recurrence, parse_day_range, parse_counter_name, parse_new_counter, parse_zones, parse_reading_values, parse_tariff};
+pub use formatter::{format_task_page, task_page_number, format_removed_tasks, format_search_result, format_task_title, format_due, format_amount, format_reading_date, format_reading_values, format_month, format_money, format_consumption, TaskPage, TaskSort};
+pub use validation::{TaskValidator, ListNameValidator, ReadingValidator, SearchQueryValidator, ChatIdValidator, ValidationResult};pub use callback_data::{CallbackAction, Snooze};
@@ -1,11 +1,29 @@
-pub fn parse_task_list(text: &str) -> Vec<String> {
-    let mut tasks = Vec::new();
+use std::sync::OnceLock;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Europe::Moscow;
+use regex::{Captures, Regex};
+
+use crate::models::{CounterReminder, CounterType, Priority, Recurrence, TaskList, TodoItem, DEFAULT_COUNTER_EMOJI, MAIN_LIST_NAME};
+use super::validation::{CounterNameValidator, DayValidator, TariffValidator, TaskIndexValidator, ValidationResult};
+
+/// Задача из многострочного списка вместе с подзадачами
+#[derive(Debug, PartialEq)]
+pub struct ParsedTask {
+    pub text: String,
+    pub children: Vec<String>,
+}
+
+/// Разбирает список задач по строкам. Строки с отступом (пробелы или табуляция)
+/// становятся подзадачами ближайшей предыдущей строки без отступа.
+pub fn parse_task_list(text: &str) -> Vec<ParsedTask> {
+    let mut tasks: Vec<ParsedTask> = Vec::new();
     
-    for line in text.lines() {
-        let line = line.trim();
+    for raw_line in text.lines() {
+        let line = raw_line.trim();
         if line.is_empty() {
             continue;
         }
+        let indented = raw_line.starts_with(|c: char| c.is_whitespace());
         
         // Удаляем нумерацию если есть (1., 2), 3-, * и т.д.)
         let clean_line = if let Some(pos) = line.find(|c: char| c.is_alphabetic() || c == '"' || c == '(' || c == '[') {
@@ -20,9 +38,626 @@ pub fn parse_task_list(text: &str) -> Vec<String> {
         };
         
         if !clean_line.is_empty() && clean_line.len() <= 500 {
-            tasks.push(clean_line.to_string());
+            match tasks.last_mut() {
+                Some(parent) if indented => parent.children.push(clean_line.to_string()),
+                _ => tasks.push(ParsedTask {
+                    text: clean_line.to_string(),
+                    children: Vec::new(),
+                }),
+            }
         }
     }
     
     tasks
-}
\ No newline at end of file
+}
+
+/// Отделяет приоритет в начале текста задачи: `!high Купить билеты`, `!срочно Позвонить`.
+/// Неизвестное слово после `!` считается частью текста.
+pub fn extract_priority(text: &str) -> (String, Priority) {
+    let text = text.trim();
+    if let Some(rest) = text.strip_prefix('!') {
+        let (word, tail) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        if let Some(priority) = Priority::from_str(word) {
+            return (tail.trim().to_string(), priority);
+        }
+    }
+    (text.to_string(), Priority::default())
+}
+
+/// Максимальная длина тега в символах (тег попадает в данные кнопки, а они ограничены 64 байтами)
+pub const MAX_TAG_LEN: usize = 24;
+
+/// Тег: `#` и буквы, цифры, `_` или `-`
+fn tag_regex() -> &'static Regex {
+    static TAG_REGEX: OnceLock<Regex> = OnceLock::new();
+    TAG_REGEX.get_or_init(|| {
+        Regex::new(r"(?:^|\s)#([\p{L}\p{N}_-]+)").expect("valid tag regex")
+    })
+}
+
+/// Приводит тег к виду, в котором он хранится: без `#`, в нижнем регистре
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag.trim().trim_start_matches('#').to_lowercase();
+    let valid = !tag.is_empty()
+        && tag.chars().count() <= MAX_TAG_LEN
+        && tag.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+    valid.then_some(tag)
+}
+
+/// Убирает теги `#работа` из текста задачи и возвращает их отдельно (без повторов, по алфавиту).
+/// Слишком длинные теги остаются частью текста.
+pub fn extract_tags(text: &str) -> (String, Vec<String>) {
+    let mut tags = Vec::new();
+    let cleaned = tag_regex().replace_all(text, |captures: &Captures| match normalize_tag(&captures[1]) {
+        Some(tag) => {
+            tags.push(tag);
+            " ".to_string()
+        }
+        None => captures[0].to_string(),
+    });
+
+    tags.sort();
+    tags.dedup();
+    (cleaned.split_whitespace().collect::<Vec<_>>().join(" "), tags)
+}
+
+/// Дата (`сегодня`, `завтра`, `ДД.ММ`, `ДД.ММ.ГГГГ`) и необязательное время `ЧЧ:ММ`
+const DATE_TIME_PATTERN: &str = r"(сегодня|завтра|(\d{1,2})\.(\d{1,2})(?:\.(\d{4}))?)(?:\s+(\d{1,2}):(\d{2}))?";
+
+/// Срок в конце текста задачи: `@25.12`, `@25.12.2025 18:30`, `@завтра 9:00`
+fn due_date_regex() -> &'static Regex {
+    static DUE_DATE_REGEX: OnceLock<Regex> = OnceLock::new();
+    DUE_DATE_REGEX.get_or_init(|| {
+        Regex::new(&format!(r"(?i)\s*@{}\s*$", DATE_TIME_PATTERN)).expect("valid due date regex")
+    })
+}
+
+/// Время напоминания: `завтра 18:30`, `25.12 9:00`
+fn remind_at_regex() -> &'static Regex {
+    static REMIND_AT_REGEX: OnceLock<Regex> = OnceLock::new();
+    REMIND_AT_REGEX.get_or_init(|| {
+        Regex::new(&format!(r"(?i)^{}$", DATE_TIME_PATTERN)).expect("valid remind time regex")
+    })
+}
+
+/// Собирает момент времени из групп `DATE_TIME_PATTERN` (время московское).
+/// Без времени берется `default_time`; дата без года, которая уже прошла, относится к следующему году.
+fn date_time_from_captures(captures: &Captures, now: DateTime<Utc>, default_time: NaiveTime) -> Result<DateTime<Utc>, String> {
+    let today = Moscow.from_utc_datetime(&now.naive_utc()).date_naive();
+    let word = captures[1].to_lowercase();
+    let explicit_year = captures.get(4).is_some();
+
+    let date = match word.as_str() {
+        "сегодня" => today,
+        "завтра" => today + Duration::days(1),
+        _ => {
+            let day: u32 = captures[2].parse().map_err(|_| "Неверный день")?;
+            let month: u32 = captures[3].parse().map_err(|_| "Неверный месяц")?;
+            let year: i32 = match captures.get(4) {
+                Some(year) => year.as_str().parse().map_err(|_| "Неверный год")?,
+                None => today.year(),
+            };
+            NaiveDate::from_ymd_opt(year, month, day).ok_or("Такой даты не существует")?
+        }
+    };
+
+    let time = match (captures.get(5), captures.get(6)) {
+        (Some(hour), Some(minute)) => parse_time(hour.as_str(), minute.as_str())?,
+        _ => default_time,
+    };
+
+    let date = if !explicit_year && date < today {
+        date.with_year(date.year() + 1).ok_or("Такой даты не существует")?
+    } else {
+        date
+    };
+
+    moscow_to_utc(date, time)
+}
+
+fn parse_time(hour: &str, minute: &str) -> Result<NaiveTime, String> {
+    let hour: u32 = hour.parse().map_err(|_| "Неверное время")?;
+    let minute: u32 = minute.parse().map_err(|_| "Неверное время")?;
+    NaiveTime::from_hms_opt(hour, minute, 0).ok_or_else(|| "Неверное время".to_string())
+}
+
+fn moscow_to_utc(date: NaiveDate, time: NaiveTime) -> Result<DateTime<Utc>, String> {
+    Moscow
+        .from_local_datetime(&date.and_time(time))
+        .earliest()
+        .map(|dt| dt.with_timezone(&Utc))
+        .ok_or_else(|| "Неверное время".to_string())
+}
+
+/// Выделяет срок из конца текста задачи и возвращает текст без него.
+/// Время указывается по Москве; если время не задано, срок — конец дня.
+pub fn extract_due_date(text: &str, now: DateTime<Utc>) -> Result<(String, Option<DateTime<Utc>>), String> {
+    let captures = match due_date_regex().captures(text) {
+        Some(captures) => captures,
+        None => return Ok((text.to_string(), None)),
+    };
+
+    let end_of_day = NaiveTime::from_hms_opt(23, 59, 0).expect("valid end of day");
+    let due_at = date_time_from_captures(&captures, now, end_of_day)?;
+
+    let clean_text = text[..captures.get(0).map_or(text.len(), |m| m.start())].trim().to_string();
+    Ok((clean_text, Some(due_at)))
+}
+
+/// Самая дальняя отсрочка для `через N мин/ч` — год
+const MAX_REMIND_IN_MINUTES: i64 = 365 * 24 * 60;
+
+/// Разбирает время напоминания (по Москве): `18:30` (сегодня, а если уже прошло — завтра),
+/// `завтра 18:30`, `25.12 9:00`, `через 30 мин`, `через 2 ч`
+pub fn parse_remind_at(input: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    let input = input.trim().to_lowercase();
+
+    if let Some(rest) = input.strip_prefix("через") {
+        let rest = rest.trim();
+        let split = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let amount: i64 = rest[..split].parse().map_err(|_| "Укажите число, например: через 30 мин")?;
+        let minutes_per_unit = match rest[split..].trim() {
+            "мин" | "минут" | "минуты" | "минуту" => 1,
+            "ч" | "час" | "часа" | "часов" => 60,
+            _ => return Err("Укажите единицы: мин или ч".to_string()),
+        };
+        let too_far = || "Слишком большой срок: напоминание можно поставить не дальше чем через год".to_string();
+        let minutes = amount
+            .checked_mul(minutes_per_unit)
+            .filter(|minutes| *minutes <= MAX_REMIND_IN_MINUTES)
+            .ok_or_else(too_far)?;
+        if minutes <= 0 {
+            return Err("Время напоминания должно быть в будущем".to_string());
+        }
+        return Duration::try_minutes(minutes)
+            .and_then(|delay| now.checked_add_signed(delay))
+            .ok_or_else(too_far);
+    }
+
+    if let Some((hour, minute)) = input.split_once(':').filter(|(h, m)| {
+        !h.is_empty() && !m.is_empty() && h.chars().chain(m.chars()).all(|c| c.is_ascii_digit())
+    }) {
+        let today = Moscow.from_utc_datetime(&now.naive_utc()).date_naive();
+        let time = parse_time(hour, minute)?;
+        let remind_at = moscow_to_utc(today, time)?;
+        return if remind_at > now { Ok(remind_at) } else { moscow_to_utc(today + Duration::days(1), time) };
+    }
+
+    let captures = remind_at_regex()
+        .captures(&input)
+        .ok_or("Не понял время. Примеры: 18:30, завтра 9:00, 25.12 18:00, через 30 мин")?;
+    if captures.get(5).is_none() {
+        return Err("Укажите время, например: завтра 9:00".to_string());
+    }
+
+    let remind_at = date_time_from_captures(&captures, now, NaiveTime::MIN)?;
+    if remind_at <= now {
+        return Err("Время напоминания должно быть в будущем".to_string());
+    }
+    Ok(remind_at)
+}
+
+fn parse_weekday(name: &str) -> Option<u32> {
+    let day = match name {
+        "пн" | "пон" | "понедельник" => 0,
+        "вт" | "вторник" => 1,
+        "ср" | "среда" | "среду" => 2,
+        "чт" | "четверг" => 3,
+        "пт" | "пятница" | "пятницу" => 4,
+        "сб" | "суббота" | "субботу" => 5,
+        "вс" | "воскресенье" => 6,
+        _ => return None,
+    };
+    Some(day)
+}
+
+/// Разбирает правило повторения: `каждый день`, `по пн, чт`, `ежемесячно 5`, `каждые 3 дня`.
+/// `нет` снимает повторение (`Ok(None)`).
+pub fn parse_recurrence(input: &str) -> Result<Option<Recurrence>, String> {
+    const HELP: &str = "Не понял правило. Примеры: «каждый день», «по пн, чт», «ежемесячно 5», «каждые 3 дня», «нет»";
+
+    let input = input.trim().to_lowercase();
+    let words: Vec<&str> = input
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    let recurrence = match words.as_slice() {
+        ["нет"] | ["не", "повторять"] => return Ok(None),
+        ["каждый", "день"] | ["ежедневно"] => Recurrence::Daily,
+        ["каждые", n, "дней" | "дня" | "дн" | "дн."] | ["каждый", n, "день"] => {
+            Recurrence::EveryNDays(n.parse().map_err(|_| HELP.to_string())?)
+        }
+        ["ежемесячно", day] | ["каждый", "месяц", day] => {
+            Recurrence::Monthly(day.parse().map_err(|_| HELP.to_string())?)
+        }
+        ["по" | "еженедельно" | "каждый" | "каждую" | "каждое", days @ ..] if !days.is_empty() => {
+            let mut days = days
+                .iter()
+                .map(|d| parse_weekday(d))
+                .collect::<Option<Vec<u32>>>()
+                .ok_or_else(|| HELP.to_string())?;
+            days.sort_unstable();
+            days.dedup();
+            Recurrence::Weekly(days)
+        }
+        _ => return Err(HELP.to_string()),
+    };
+
+    if recurrence.is_valid() {
+        Ok(Some(recurrence))
+    } else {
+        Err("День месяца — от 1 до 31, интервал — от 1 до 365 дней".to_string())
+    }
+}
+
+/// Минимальная длина псевдонима, по которому ищется задача
+const MIN_ALIAS_LEN: usize = 4;
+
+/// Находит задачу по вводу пользователя: номеру в списке (с 1)
+/// или короткому псевдониму из `/list` (началу id задачи)
+pub fn resolve_task<'a>(tasks: &'a [TodoItem], input: &str) -> Result<&'a TodoItem, String> {
+    let input = input.trim().trim_start_matches('[').trim_end_matches(']').to_lowercase();
+
+    if let Ok(num) = input.parse::<usize>() {
+        if num > 0 && num <= tasks.len() {
+            return Ok(&tasks[num - 1]);
+        }
+    }
+
+    if input.chars().count() < MIN_ALIAS_LEN {
+        return match input.parse::<usize>() {
+            Ok(num) if num > 0 => match TaskIndexValidator::validate_task_index(num - 1, tasks.len()) {
+                ValidationResult::Invalid(error_msg) => Err(error_msg),
+                ValidationResult::Valid => Err("Задача не найдена".to_string()),
+            },
+            _ => Err("Укажите номер задачи или её код из списка".to_string()),
+        };
+    }
+
+    let mut matches = tasks.iter().filter(|t| t.id.starts_with(&input));
+    match (matches.next(), matches.next()) {
+        (Some(task), None) => Ok(task),
+        (Some(_), Some(_)) => Err("Под этот код подходит несколько задач, укажите его полностью".to_string()),
+        _ => Err("Задача не найдена".to_string()),
+    }
+}
+
+/// Находит список по названию без учета регистра; `None` — основной список
+pub fn resolve_list<'a>(lists: &'a [TaskList], name: &str) -> Result<Option<&'a TaskList>, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Укажите название списка".to_string());
+    }
+    if name.to_lowercase() == MAIN_LIST_NAME.to_lowercase() {
+        return Ok(None);
+    }
+
+    lists.iter()
+        .find(|list| list.has_name(name))
+        .map(Some)
+        .ok_or_else(|| format!("Список \"{}\" не найден", name))
+}
+
+/// Период подачи показаний «начало-конец»: `16-25`
+pub fn parse_day_range(input: &str) -> Result<(u32, u32), String> {
+    let Some((start, end)) = input.trim().split_once(['-', '–']) else {
+        return Err("Неверный формат периода. Используйте формат: начало-конец, например: 16-25".to_string());
+    };
+    let start_day: u32 = start.trim().parse()
+        .map_err(|_| "Неверный формат дня начала. Укажите число от 1 до 31.".to_string())?;
+    let end_day: u32 = end.trim().parse()
+        .map_err(|_| "Неверный формат дня окончания. Укажите число от 1 до 31.".to_string())?;
+
+    match DayValidator::validate_day_range(start_day, end_day) {
+        ValidationResult::Valid => Ok((start_day, end_day)),
+        ValidationResult::Invalid(error_msg) => Err(error_msg),
+    }
+}
+
+/// Максимальная длина эмодзи счетчика в символах (с модификаторами и составными значками)
+const MAX_COUNTER_EMOJI_LEN: usize = 8;
+
+/// Разбирает название счетчика с необязательным эмодзи в начале: `🔥 Газ`, `🌐Интернет`.
+/// Без эмодзи берется `DEFAULT_COUNTER_EMOJI`. Возвращает эмодзи и название
+pub fn parse_counter_name(input: &str) -> Result<(String, String), String> {
+    let input = input.trim();
+    let emoji: String = input
+        .chars()
+        .take_while(|c| !c.is_ascii() && !c.is_alphanumeric() && !c.is_whitespace())
+        .collect();
+    let name = input[emoji.len()..].trim();
+
+    if let ValidationResult::Invalid(error_msg) = CounterNameValidator::validate_counter_name(name) {
+        return Err(error_msg);
+    }
+    if emoji.chars().count() > MAX_COUNTER_EMOJI_LEN {
+        return Err("Перед названием можно поставить только один эмодзи".to_string());
+    }
+
+    let emoji = if emoji.is_empty() { DEFAULT_COUNTER_EMOJI.to_string() } else { emoji };
+    Ok((emoji, name.to_string()))
+}
+
+/// Новый счетчик из названия и периода: `🔥 Газ 20-25`
+pub fn parse_new_counter(input: &str) -> Result<CounterReminder, String> {
+    let Some((name, period)) = input.trim().rsplit_once(char::is_whitespace) else {
+        return Err("Укажите название и период, например: 🔥 Газ 20-25".to_string());
+    };
+    let (start_day, end_day) = parse_day_range(period)?;
+    let (emoji, name) = parse_counter_name(name)?;
+    Ok(CounterReminder::new(CounterType::new(name, emoji), start_day, end_day))
+}
+
+/// Максимальное число тарифных зон счетчика (у электричества бывает до трех)
+const MAX_COUNTER_ZONES: usize = 4;
+/// Максимальная длина названия зоны в символах
+const MAX_ZONE_LEN: usize = 16;
+
+/// Разбирает тарифные зоны счетчика через запятую: `день, ночь`. `нет` — счетчик без зон
+pub fn parse_zones(input: &str) -> Result<Vec<String>, String> {
+    let input = input.trim();
+    if input == "-" || input.to_lowercase() == "нет" {
+        return Ok(Vec::new());
+    }
+
+    let zones: Vec<String> = input.split(',').map(|zone| zone.trim().to_lowercase()).collect();
+    if zones.len() < 2 {
+        return Err("Укажите хотя бы две зоны через запятую (например: день, ночь) или «нет»".to_string());
+    }
+    if zones.len() > MAX_COUNTER_ZONES {
+        return Err(format!("Зон может быть не больше {}", MAX_COUNTER_ZONES));
+    }
+    if zones.iter().any(|zone| zone.is_empty() || zone.chars().count() > MAX_ZONE_LEN) {
+        return Err(format!("Название зоны — от 1 до {} символов", MAX_ZONE_LEN));
+    }
+    if zones.iter().enumerate().any(|(i, zone)| zones[..i].contains(zone)) {
+        return Err("Названия зон не должны повторяться".to_string());
+    }
+    Ok(zones)
+}
+
+/// Числа через пробел (`12345.6 789`); дробная часть — через точку или запятую
+fn parse_numbers(input: &str) -> Result<Vec<f64>, String> {
+    input
+        .split(|c: char| c.is_whitespace() || c == ';' || c == '/')
+        .filter(|part| !part.is_empty())
+        .map(|part| part.replace(',', ".").parse::<f64>().map_err(|_| format!("«{}» — не число", part)))
+        .collect()
+}
+
+/// Разбирает показания: по числу на зону через пробел (`12345.6 789`); дробная часть — через точку или запятую
+pub fn parse_reading_values(input: &str, zones_count: usize) -> Result<Vec<f64>, String> {
+    let values = parse_numbers(input)?;
+
+    if values.len() != zones_count {
+        return Err(if zones_count == 1 {
+            "Введите одно число — текущее показание счетчика".to_string()
+        } else {
+            format!("Нужно ввести {} числа через пробел, по одному на зону", zones_count)
+        });
+    }
+    Ok(values)
+}
+
+/// Дата начала действия тарифа в конце ввода: `с 01.07`, `с 01.07.2025`
+fn tariff_date_regex() -> &'static Regex {
+    static TARIFF_DATE_REGEX: OnceLock<Regex> = OnceLock::new();
+    TARIFF_DATE_REGEX.get_or_init(|| {
+        Regex::new(r"(?i)\s*\bс\s+(\d{1,2})\.(\d{1,2})(?:\.(\d{4}))?\s*$").expect("valid tariff date regex")
+    })
+}
+
+/// Разбирает тариф: цены по одной на зону через пробел и необязательную дату начала (`6.43 2.51 с 01.07.2025`).
+/// Без даты тариф действует с начала текущего месяца, без года — в текущем году.
+pub fn parse_tariff(input: &str, zones_count: usize, now: DateTime<Utc>) -> Result<(Vec<f64>, NaiveDate), String> {
+    let today = Moscow.from_utc_datetime(&now.naive_utc()).date_naive();
+    let (prices, effective_from) = match tariff_date_regex().captures(input) {
+        Some(captures) => {
+            let day: u32 = captures[1].parse().map_err(|_| "Неверный день")?;
+            let month: u32 = captures[2].parse().map_err(|_| "Неверный месяц")?;
+            let year: i32 = match captures.get(3) {
+                Some(year) => year.as_str().parse().map_err(|_| "Неверный год")?,
+                None => today.year(),
+            };
+            let date = NaiveDate::from_ymd_opt(year, month, day).ok_or("Такой даты не существует")?;
+            (&input[..captures.get(0).map_or(0, |m| m.start())], date)
+        }
+        None => (input, today.with_day(1).expect("first day of month exists")),
+    };
+
+    let prices = parse_numbers(prices)?;
+    if prices.len() != zones_count {
+        return Err(if zones_count == 1 {
+            "Введите одно число — цену за единицу".to_string()
+        } else {
+            format!("Нужно ввести {} цены через пробел, по одной на зону", zones_count)
+        });
+    }
+    for price in &prices {
+        if let ValidationResult::Invalid(error_msg) = TariffValidator::validate_price(*price) {
+            return Err(error_msg);
+        }
+    }
+    Ok((prices, effective_from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_task_list_with_children() {
+        let tasks = parse_task_list("1. Магазин\n   - Молоко\n\t* Хлеб\n2. Позвонить маме\n\n  Сделать домашку");
+        assert_eq!(tasks, vec![
+            ParsedTask { text: "Магазин".to_string(), children: vec!["Молоко".to_string(), "Хлеб".to_string()] },
+            ParsedTask { text: "Позвонить маме".to_string(), children: vec!["Сделать домашку".to_string()] },
+        ]);
+
+        // Отступ у первой строки не делает её подзадачей
+        let tasks = parse_task_list("  Первая\nВторая");
+        assert_eq!(tasks.len(), 2);
+        assert!(tasks.iter().all(|t| t.children.is_empty()));
+    }
+
+    #[test]
+    fn test_resolve_task() {
+        let mut first = TodoItem::new("Купить молоко".to_string());
+        first.id = "3f2a9c11-0000-4000-8000-000000000000".to_string();
+        let mut second = TodoItem::new("Позвонить маме".to_string());
+        second.id = "3f2b0000-0000-4000-8000-000000000000".to_string();
+        let tasks = vec![first, second];
+
+        assert_eq!(resolve_task(&tasks, "2").unwrap().text, "Позвонить маме");
+        assert_eq!(resolve_task(&tasks, "3F2A9C").unwrap().text, "Купить молоко");
+        assert_eq!(resolve_task(&tasks, "[3f2b00]").unwrap().text, "Позвонить маме");
+        assert!(resolve_task(&tasks, "3f2").is_err());
+        assert!(resolve_task(&tasks, "3f2a9c-").is_err());
+        assert!(resolve_task(&tasks, "3").is_err());
+        assert!(resolve_task(&tasks, "0").is_err());
+        assert!(resolve_task(&[], "1").is_err());
+    }
+
+    #[test]
+    fn test_resolve_list() {
+        let lists = vec![TaskList::new("Работа".to_string()), TaskList::new("Дом".to_string())];
+
+        assert_eq!(resolve_list(&lists, " работа ").unwrap().unwrap().name, "Работа");
+        assert_eq!(resolve_list(&lists, "основной"), Ok(None));
+        assert!(resolve_list(&lists, "Покупки").is_err());
+        assert!(resolve_list(&lists, "").is_err());
+    }
+
+    #[test]
+    fn test_parse_counter() {
+        assert_eq!(parse_day_range("16-25"), Ok((16, 25)));
+        assert_eq!(parse_day_range(" 1 – 10 "), Ok((1, 10)));
+        assert!(parse_day_range("25-16").is_err());
+        assert!(parse_day_range("16").is_err());
+
+        assert_eq!(parse_counter_name("🔥 Газ"), Ok(("🔥".to_string(), "Газ".to_string())));
+        assert_eq!(parse_counter_name("🌡️Отопление"), Ok(("🌡️".to_string(), "Отопление".to_string())));
+        assert_eq!(parse_counter_name("Домофон"), Ok((DEFAULT_COUNTER_EMOJI.to_string(), "Домофон".to_string())));
+        assert!(parse_counter_name("🔥").is_err());
+
+        let counter = parse_new_counter("🌐 Интернет провайдер 1-5").unwrap();
+        assert_eq!(counter.counter_type.display_name(), "🌐 Интернет провайдер");
+        assert_eq!((counter.start_day, counter.end_day), (1, 5));
+        assert!(parse_new_counter("Газ").is_err());
+        assert!(parse_new_counter("20-25").is_err());
+    }
+
+    #[test]
+    fn test_parse_readings() {
+        assert_eq!(parse_zones("День, НОЧЬ"), Ok(vec!["день".to_string(), "ночь".to_string()]));
+        assert_eq!(parse_zones("нет"), Ok(vec![]));
+        assert!(parse_zones("день").is_err());
+        assert!(parse_zones("день, день").is_err());
+        assert!(parse_zones("пик, ночь, ,").is_err());
+
+        assert_eq!(parse_reading_values("12345,6", 1), Ok(vec![12345.6]));
+        assert_eq!(parse_reading_values(" 100  200.5 ", 2), Ok(vec![100.0, 200.5]));
+        assert!(parse_reading_values("100", 2).is_err());
+        assert!(parse_reading_values("сто", 1).is_err());
+    }
+
+    #[test]
+    fn test_parse_tariff() {
+        let now = "2025-06-20T09:00:00Z".parse().unwrap();
+        let june = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        assert_eq!(parse_tariff("6,43", 1, now), Ok((vec![6.43], june)));
+        assert_eq!(
+            parse_tariff("6.43 2.51 С 01.07", 2, now),
+            Ok((vec![6.43, 2.51], NaiveDate::from_ymd_opt(2025, 7, 1).unwrap()))
+        );
+        assert_eq!(parse_tariff("40 с 15.01.2024", 1, now).map(|(_, date)| date), Ok(NaiveDate::from_ymd_opt(2024, 1, 15).unwrap()));
+        assert!(parse_tariff("6.43", 2, now).is_err());
+        assert!(parse_tariff("0", 1, now).is_err());
+        assert!(parse_tariff("6.43 с 31.02", 1, now).is_err());
+    }
+
+    #[test]
+    fn test_extract_priority() {
+        assert_eq!(extract_priority("!high Купить билеты"), ("Купить билеты".to_string(), Priority::High));
+        assert_eq!(extract_priority("!СРОЧНО позвонить @завтра"), ("позвонить @завтра".to_string(), Priority::Urgent));
+        assert_eq!(extract_priority("Купить молоко"), ("Купить молоко".to_string(), Priority::Normal));
+        assert_eq!(extract_priority("!важное дело"), ("!важное дело".to_string(), Priority::Normal));
+        assert_eq!(extract_priority("!low"), (String::new(), Priority::Low));
+    }
+
+    #[test]
+    fn test_extract_tags() {
+        let (text, tags) = extract_tags("Купить #Дом билеты #work #дом");
+        assert_eq!(text, "Купить билеты");
+        assert_eq!(tags, vec!["work".to_string(), "дом".to_string()]);
+
+        let (text, tags) = extract_tags("Ответить на issue#42 и C#");
+        assert_eq!(text, "Ответить на issue#42 и C#");
+        assert!(tags.is_empty());
+
+        let long = format!("Задача #{}", "а".repeat(MAX_TAG_LEN + 1));
+        assert_eq!(extract_tags(&long), (long.clone(), vec![]));
+        assert_eq!(normalize_tag("#Work"), Some("work".to_string()));
+        assert_eq!(normalize_tag("#"), None);
+    }
+
+    #[test]
+    fn test_extract_due_date() {
+        // 10 мая 2024, 12:00 по Москве
+        let now = Utc.with_ymd_and_hms(2024, 5, 10, 9, 0, 0).unwrap();
+
+        let (text, due) = extract_due_date("Купить билеты @15.05 18:30", now).unwrap();
+        assert_eq!(text, "Купить билеты");
+        assert_eq!(due, Some(Utc.with_ymd_and_hms(2024, 5, 15, 15, 30, 0).unwrap()));
+
+        let (_, due) = extract_due_date("Оплатить @завтра", now).unwrap();
+        assert_eq!(due, Some(Utc.with_ymd_and_hms(2024, 5, 11, 20, 59, 0).unwrap()));
+
+        // Прошедшая дата без года — следующий год
+        let (_, due) = extract_due_date("Поздравить @01.03", now).unwrap();
+        assert_eq!(due, Some(Utc.with_ymd_and_hms(2025, 3, 1, 20, 59, 0).unwrap()));
+
+        let (text, due) = extract_due_date("Написать на mail@example.com", now).unwrap();
+        assert_eq!(text, "Написать на mail@example.com");
+        assert_eq!(due, None);
+
+        assert!(extract_due_date("Задача @31.02", now).is_err());
+        assert!(extract_due_date("Задача @01.06 25:00", now).is_err());
+    }
+
+    #[test]
+    fn test_parse_remind_at() {
+        // 10 мая 2024, 12:00 по Москве
+        let now = Utc.with_ymd_and_hms(2024, 5, 10, 9, 0, 0).unwrap();
+
+        assert_eq!(parse_remind_at("18:30", now), Ok(Utc.with_ymd_and_hms(2024, 5, 10, 15, 30, 0).unwrap()));
+        // Время уже прошло — завтра
+        assert_eq!(parse_remind_at("9:00", now), Ok(Utc.with_ymd_and_hms(2024, 5, 11, 6, 0, 0).unwrap()));
+        assert_eq!(parse_remind_at("Завтра 18:30", now), Ok(Utc.with_ymd_and_hms(2024, 5, 11, 15, 30, 0).unwrap()));
+        assert_eq!(parse_remind_at("через 30 мин", now), Ok(now + Duration::minutes(30)));
+        assert_eq!(parse_remind_at("через 2 ч", now), Ok(now + Duration::hours(2)));
+        // Огромный срок — ошибка, а не паника при переполнении
+        assert!(parse_remind_at("через 200000000000 мин", now).is_err());
+        assert!(parse_remind_at("через 9223372036854775807 ч", now).is_err());
+        assert_eq!(parse_remind_at("через 8760 ч", now), Ok(now + Duration::days(365)));
+        assert!(parse_remind_at("через 8761 ч", now).is_err());
+
+        assert!(parse_remind_at("завтра", now).is_err());
+        assert!(parse_remind_at("сегодня 8:00", now).is_err());
+        assert!(parse_remind_at("когда-нибудь", now).is_err());
+    }
+
+    #[test]
+    fn test_parse_recurrence() {
+        assert_eq!(parse_recurrence("Каждый день"), Ok(Some(Recurrence::Daily)));
+        assert_eq!(parse_recurrence("по чт, пн"), Ok(Some(Recurrence::Weekly(vec![0, 3]))));
+        assert_eq!(parse_recurrence("каждую субботу"), Ok(Some(Recurrence::Weekly(vec![5]))));
+        assert_eq!(parse_recurrence("ежемесячно 5"), Ok(Some(Recurrence::Monthly(5))));
+        assert_eq!(parse_recurrence("каждые 3 дня"), Ok(Some(Recurrence::EveryNDays(3))));
+        assert_eq!(parse_recurrence("нет"), Ok(None));
+
+        assert!(parse_recurrence("ежемесячно 32").is_err());
+        assert!(parse_recurrence("по выходным").is_err());
+        assert!(parse_recurrence("иногда").is_err());
+    }
+}
@@ -168,13 +168,148 @@ impl TaskIndexValidator {
     }
 }
 
+/// Валидатор для названий списков задач
+pub struct ListNameValidator;
+
+impl ListNameValidator {
+    /// Максимальная длина названия списка в символах
+    pub const MAX_LEN: usize = 32;
+
+    pub fn validate_list_name(name: &str) -> ValidationResult {
+        let name = name.trim();
+        if name.is_empty() {
+            return ValidationResult::Invalid("Название списка не может быть пустым".to_string());
+        }
+
+        if name.chars().count() > Self::MAX_LEN {
+            return ValidationResult::Invalid(format!(
+                "Название списка слишком длинное (максимум {} символов)",
+                Self::MAX_LEN
+            ));
+        }
+
+        if name.chars().any(|c| c.is_control()) {
+            return ValidationResult::Invalid("Название содержит недопустимые символы".to_string());
+        }
+
+        ValidationResult::Valid
+    }
+}
+
+/// Валидатор для названий счетчиков
+pub struct CounterNameValidator;
+
+impl CounterNameValidator {
+    /// Максимальная длина названия счетчика в символах
+    pub const MAX_LEN: usize = 32;
+
+    pub fn validate_counter_name(name: &str) -> ValidationResult {
+        let name = name.trim();
+        if name.is_empty() {
+            return ValidationResult::Invalid("Название счетчика не может быть пустым".to_string());
+        }
+
+        if name.chars().count() > Self::MAX_LEN {
+            return ValidationResult::Invalid(format!(
+                "Название счетчика слишком длинное (максимум {} символов)",
+                Self::MAX_LEN
+            ));
+        }
+
+        if name.chars().any(|c| c.is_control()) {
+            return ValidationResult::Invalid("Название содержит недопустимые символы".to_string());
+        }
+
+        ValidationResult::Valid
+    }
+}
+
+/// Валидатор для показаний счетчиков
+pub struct ReadingValidator;
+
+impl ReadingValidator {
+    /// Максимальное показание: у бытовых счетчиков не больше девяти знаков до точки
+    pub const MAX_VALUE: f64 = 1_000_000_000.0;
+
+    /// Показание неотрицательное и не меньше предыдущего в той же зоне: счетчики не крутятся назад
+    pub fn validate_reading(value: f64, previous: Option<f64>) -> ValidationResult {
+        if !value.is_finite() || value < 0.0 {
+            return ValidationResult::Invalid("Показание должно быть неотрицательным числом".to_string());
+        }
+
+        if value >= Self::MAX_VALUE {
+            return ValidationResult::Invalid("Показание слишком большое".to_string());
+        }
+
+        if let Some(previous) = previous {
+            if value < previous {
+                return ValidationResult::Invalid(format!(
+                    "Показание {} меньше предыдущего ({}) — счетчик не может крутиться назад",
+                    value, previous
+                ));
+            }
+        }
+
+        ValidationResult::Valid
+    }
+}
+
+/// Валидатор для цен тарифов
+pub struct TariffValidator;
+
+impl TariffValidator {
+    /// Максимальная цена единицы расхода в рублях
+    pub const MAX_PRICE: f64 = 100_000.0;
+
+    pub fn validate_price(price: f64) -> ValidationResult {
+        if !price.is_finite() || price <= 0.0 {
+            return ValidationResult::Invalid("Цена должна быть положительным числом".to_string());
+        }
+
+        if price > Self::MAX_PRICE {
+            return ValidationResult::Invalid(format!("Цена не может быть больше {} ₽", Self::MAX_PRICE));
+        }
+
+        ValidationResult::Valid
+    }
+}
+
+/// Валидатор для поисковых запросов
+pub struct SearchQueryValidator;
+
+impl SearchQueryValidator {
+    /// Минимальная длина запроса в символах: по одной букве находится почти всё
+    pub const MIN_LEN: usize = 2;
+    /// Максимальная длина запроса в символах
+    pub const MAX_LEN: usize = 100;
+
+    pub fn validate_search_query(query: &str) -> ValidationResult {
+        let len = query.trim().trim_start_matches('#').chars().count();
+        if len < Self::MIN_LEN {
+            return ValidationResult::Invalid(format!(
+                "Запрос слишком короткий (минимум {} символа)",
+                Self::MIN_LEN
+            ));
+        }
+
+        if len > Self::MAX_LEN {
+            return ValidationResult::Invalid(format!(
+                "Запрос слишком длинный (максимум {} символов)",
+                Self::MAX_LEN
+            ));
+        }
+
+        ValidationResult::Valid
+    }
+}
+
 /// Валидатор для дней месяца (для напоминаний)
 pub struct DayValidator;
 
 impl DayValidator {
     /// Валидирует день месяца
     pub fn validate_day(day: u32) -> ValidationResult {
-        if day < 1 || day > 31 {
+        if !(1..=31).contains(&day) {
             return ValidationResult::Invalid("День должен быть от 1 до 31".to_string());
         }
 
@@ -262,6 +397,43 @@ mod tests {
         assert!(matches!(TaskIndexValidator::validate_task_index(0, 0), ValidationResult::Invalid(_)));
     }
 
+    #[test]
+    fn test_list_name_validation() {
+        assert_eq!(ListNameValidator::validate_list_name("Работа"), ValidationResult::Valid);
+        assert!(matches!(ListNameValidator::validate_list_name("   "), ValidationResult::Invalid(_)));
+        assert!(matches!(ListNameValidator::validate_list_name(&"а".repeat(33)), ValidationResult::Invalid(_)));
+    }
+
+    #[test]
+    fn test_counter_name_validation() {
+        assert_eq!(CounterNameValidator::validate_counter_name("Газ"), ValidationResult::Valid);
+        assert!(matches!(CounterNameValidator::validate_counter_name(""), ValidationResult::Invalid(_)));
+        assert!(matches!(CounterNameValidator::validate_counter_name(&"г".repeat(33)), ValidationResult::Invalid(_)));
+    }
+
+    #[test]
+    fn test_reading_validation() {
+        assert_eq!(ReadingValidator::validate_reading(0.0, None), ValidationResult::Valid);
+        assert_eq!(ReadingValidator::validate_reading(120.5, Some(120.5)), ValidationResult::Valid);
+        assert!(matches!(ReadingValidator::validate_reading(119.9, Some(120.0)), ValidationResult::Invalid(_)));
+        assert!(matches!(ReadingValidator::validate_reading(-1.0, None), ValidationResult::Invalid(_)));
+        assert!(matches!(ReadingValidator::validate_reading(f64::NAN, None), ValidationResult::Invalid(_)));
+    }
+
+    #[test]
+    fn test_price_validation() {
+        assert_eq!(TariffValidator::validate_price(6.43), ValidationResult::Valid);
+        assert!(matches!(TariffValidator::validate_price(0.0), ValidationResult::Invalid(_)));
+        assert!(matches!(TariffValidator::validate_price(1e6), ValidationResult::Invalid(_)));
+    }
+
+    #[test]
+    fn test_search_query_validation() {
+        assert_eq!(SearchQueryValidator::validate_search_query("мол"), ValidationResult::Valid);
+        assert!(matches!(SearchQueryValidator::validate_search_query(" #м "), ValidationResult::Invalid(_)));
+        assert!(matches!(SearchQueryValidator::validate_search_query(&"а".repeat(101)), ValidationResult::Invalid(_)));
+    }
+
     #[test]
     fn test_day_validation() {
         assert_eq!(DayValidator::validate_day(1), ValidationResult::Valid);